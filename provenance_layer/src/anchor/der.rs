@@ -0,0 +1,217 @@
+// Minimal DER encoding and decoding (X.690), enough for RFC 3161 requests, responses
+// and the CMS structures inside timestamp tokens.
+
+use crate::ProvenanceError;
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const UTF8_STRING: u8 = 0x0c;
+pub const UTC_TIME: u8 = 0x17;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const BOOLEAN: u8 = 0x01;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+/// Context-specific constructed tag `[n]`.
+pub const fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+fn malformed(what: &str) -> ProvenanceError {
+    ProvenanceError::AnchorError(format!("malformed DER: {}", what))
+}
+
+/// Encodes one tag-length-value.
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Encodes a constructed value from already encoded parts.
+pub fn constructed(tag: u8, parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+pub fn sequence(parts: &[Vec<u8>]) -> Vec<u8> {
+    constructed(SEQUENCE, parts)
+}
+
+/// INTEGER content of a non-negative integer given as big-endian bytes.
+pub fn unsigned_content(bytes: &[u8]) -> Vec<u8> {
+    let trimmed: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    let mut content = Vec::with_capacity(trimmed.len() + 1);
+    if trimmed.first().is_none_or(|b| b & 0x80 != 0) {
+        content.push(0);
+    }
+    content.extend_from_slice(&trimmed);
+    content
+}
+
+/// Encodes a non-negative integer given as big-endian bytes.
+pub fn unsigned(bytes: &[u8]) -> Vec<u8> {
+    tlv(INTEGER, &unsigned_content(bytes))
+}
+
+/// BIT STRING with no unused bits.
+pub fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    tlv(BIT_STRING, &[&[0], bytes].concat())
+}
+
+/// Content bytes of an object identifier.
+pub fn oid_content(arcs: &[u64]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut encode = |mut arc: u64| {
+        let mut chunk = vec![(arc & 0x7f) as u8];
+        arc >>= 7;
+        while arc > 0 {
+            chunk.push(0x80 | (arc & 0x7f) as u8);
+            arc >>= 7;
+        }
+        chunk.reverse();
+        out.extend(chunk);
+    };
+    encode(arcs[0] * 40 + arcs[1]);
+    for &arc in &arcs[2..] {
+        encode(arc);
+    }
+    out
+}
+
+pub fn oid(arcs: &[u64]) -> Vec<u8> {
+    tlv(OID, &oid_content(arcs))
+}
+
+/// AlgorithmIdentifier with absent parameters.
+pub fn algorithm(arcs: &[u64]) -> Vec<u8> {
+    sequence(&[oid(arcs)])
+}
+
+pub fn utc_time(t: DateTime<Utc>) -> Vec<u8> {
+    tlv(UTC_TIME, t.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+}
+
+pub fn generalized_time(t: DateTime<Utc>) -> Vec<u8> {
+    tlv(GENERALIZED_TIME, t.format("%Y%m%d%H%M%SZ").to_string().as_bytes())
+}
+
+/// Parses a GeneralizedTime in UTC, with optional fractional seconds.
+pub fn parse_generalized_time(content: &[u8]) -> Result<DateTime<Utc>, ProvenanceError> {
+    let text = std::str::from_utf8(content).map_err(|_| malformed("time"))?;
+    let text = text.strip_suffix('Z').ok_or_else(|| malformed("time is not UTC"))?;
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let t = NaiveDateTime::parse_from_str(whole, "%Y%m%d%H%M%S").map_err(|_| malformed("time"))?;
+    let nanos = match fraction.len() {
+        0 => 0,
+        n if n <= 9 && fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            fraction.parse::<u32>().map_err(|_| malformed("time"))? * 10u32.pow(9 - n as u32)
+        }
+        _ => return Err(malformed("time")),
+    };
+    Ok(t.and_utc() + chrono::Duration::nanoseconds(nanos as i64))
+}
+
+/// One decoded value.
+#[derive(Debug, Clone, Copy)]
+pub struct Value<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// The whole encoding, tag and length included.
+    pub raw: &'a [u8],
+}
+
+impl<'a> Value<'a> {
+    /// Reader over the children of a constructed value.
+    pub fn children(&self) -> Reader<'a> {
+        Reader::new(self.content)
+    }
+}
+
+/// Sequential reader over concatenated DER values.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Reads the next value of any tag.
+    pub fn next_value(&mut self) -> Result<Value<'a>, ProvenanceError> {
+        let data = self.data;
+        if data.len() < 2 {
+            return Err(malformed("truncated"));
+        }
+        let tag = data[0];
+        if tag & 0x1f == 0x1f {
+            return Err(malformed("high tag numbers are not supported"));
+        }
+        let (len, header) = match data[1] {
+            n if n < 0x80 => (n as usize, 2),
+            0x80 => return Err(malformed("indefinite length")),
+            n => {
+                let count = (n & 0x7f) as usize;
+                if count > 4 || data.len() < 2 + count {
+                    return Err(malformed("length"));
+                }
+                let len = data[2..2 + count].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+                (len, 2 + count)
+            }
+        };
+        let end = header.checked_add(len).filter(|end| *end <= data.len()).ok_or_else(|| malformed("truncated"))?;
+        self.data = &data[end..];
+        Ok(Value { tag, content: &data[header..end], raw: &data[..end] })
+    }
+
+    /// Reads the next value, which must have `tag`.
+    pub fn expect(&mut self, tag: u8) -> Result<Value<'a>, ProvenanceError> {
+        let value = self.next_value()?;
+        if value.tag != tag {
+            return Err(malformed(&format!("expected tag {:#04x}, found {:#04x}", tag, value.tag)));
+        }
+        Ok(value)
+    }
+
+    /// Reads the next value if it has `tag`.
+    pub fn optional(&mut self, tag: u8) -> Result<Option<Value<'a>>, ProvenanceError> {
+        if self.peek_tag() == Some(tag) {
+            self.next_value().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Decodes a non-negative INTEGER content that fits in 64 bits.
+pub fn parse_u64(content: &[u8]) -> Result<u64, ProvenanceError> {
+    if content.is_empty() || content[0] & 0x80 != 0 {
+        return Err(malformed("integer"));
+    }
+    let bytes: Vec<u8> = content.iter().copied().skip_while(|b| *b == 0).collect();
+    if bytes.len() > 8 {
+        return Err(malformed("integer too large"));
+    }
+    Ok(bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
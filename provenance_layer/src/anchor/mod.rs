@@ -0,0 +1,115 @@
+// External anchoring of block hashes.
+// After a block is sealed its hash is handed to each configured `Anchor`, which
+// commits it somewhere outside the PL (an RFC 3161 timestamp authority, a public log)
+// and returns a receipt. Receipts are stored alongside the block and can be checked
+// offline against its hash, proving the block existed no later than the anchor time.
+
+use crate::keystore::Keystore;
+use crate::{Block, ProvenanceError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub mod der;
+pub mod rfc3161;
+
+pub use self::rfc3161::{HttpTsa, LocalTsa, TimestampAnchor, TimestampAuthority, LOCAL_TSA_NAME};
+
+/// Receipt kind for RFC 3161 timestamp tokens.
+pub const RFC3161_KIND: &str = "rfc3161";
+
+/// Keystore actor whose key the local stand-in TSA signs with.
+pub const LOCAL_TSA_ACTOR: &str = "local-tsa";
+
+/// Commits sealed block hashes to an external witness.
+#[async_trait]
+pub trait Anchor: Send + Sync {
+    /// Stable name of the anchor; a block holds at most one receipt per name.
+    fn name(&self) -> &str;
+
+    /// Anchors the hash of a sealed block and returns the receipt.
+    async fn anchor(&self, block: &Block) -> Result<AnchorReceipt, ProvenanceError>;
+}
+
+/// Evidence that a block hash was anchored externally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorReceipt {
+    /// Name of the anchor that issued the receipt.
+    pub anchor: String,
+    /// Proof format, which selects the verifier; e.g. RFC3161_KIND.
+    pub kind: String,
+    /// Hex hash of the anchored block.
+    pub block_hash: String,
+    /// Time the anchor attests for the block hash.
+    pub anchored_at: DateTime<Utc>,
+    /// Hex proof in the format named by `kind`; for RFC 3161, the DER TimeStampToken.
+    pub proof: String,
+}
+
+/// The last failed attempt of an anchor that holds no receipt for a block yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorFailure {
+    /// Name of the anchor that failed.
+    pub anchor: String,
+    pub failed_at: DateTime<Utc>,
+    pub error: String,
+}
+
+/// Result of checking one receipt against a block hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorVerification {
+    pub anchor: String,
+    pub kind: String,
+    pub valid: bool,
+    /// Why the receipt does not verify.
+    pub reason: Option<String>,
+    /// Time attested by the verified proof.
+    pub anchored_at: Option<DateTime<Utc>>,
+    /// Hex SHA-256 of the certificate that signed the proof; pin it to trust a TSA.
+    pub signer: Option<String>,
+}
+
+/// Checks `receipt` against `block_hash`, offline.
+pub fn verify_receipt(block_hash: &str, receipt: &AnchorReceipt) -> AnchorVerification {
+    let result = match receipt.kind.as_str() {
+        RFC3161_KIND => rfc3161::verify_receipt(block_hash, receipt),
+        other => Err(ProvenanceError::AnchorError(format!("unknown receipt kind: {}", other))),
+    };
+    let (valid, reason, anchored_at, signer) = match result {
+        Ok(info) => (true, None, Some(info.gen_time), Some(info.signer)),
+        Err(ProvenanceError::AnchorError(reason)) => (false, Some(reason), None, None),
+        Err(e) => (false, Some(e.to_string()), None, None),
+    };
+    AnchorVerification {
+        anchor: receipt.anchor.clone(),
+        kind: receipt.kind.clone(),
+        valid,
+        reason,
+        anchored_at,
+        signer,
+    }
+}
+
+/// Anchors named in `PL_ANCHOR`, a comma-separated list of `rfc3161` (the TSA at
+/// `PL_TSA_URL`) and `local-tsa` (the in-process stand-in, signing with the keystore
+/// key of LOCAL_TSA_ACTOR). None if unset.
+pub fn from_env(keystore: &Keystore) -> Result<Vec<Arc<dyn Anchor>>, ProvenanceError> {
+    let names = std::env::var("PL_ANCHOR").unwrap_or_default();
+    let mut anchors: Vec<Arc<dyn Anchor>> = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        match name {
+            "rfc3161" => {
+                let url = std::env::var("PL_TSA_URL")
+                    .map_err(|_| ProvenanceError::AnchorError("PL_TSA_URL is not set".to_string()))?;
+                anchors.push(Arc::new(TimestampAnchor::new(HttpTsa::new(&url)?)));
+            }
+            "local-tsa" => {
+                let key_pair = keystore.provision(LOCAL_TSA_ACTOR)?;
+                anchors.push(Arc::new(TimestampAnchor::new(LocalTsa::new(key_pair))));
+            }
+            _ => return Err(ProvenanceError::AnchorError(format!("unknown anchor: {}", name))),
+        }
+    }
+    Ok(anchors)
+}
@@ -0,0 +1,555 @@
+// RFC 3161 timestamping of block hashes.
+// A timestamp authority (TSA) signs a TSTInfo binding the block hash, as a SHA-256
+// message imprint, to its clock. The DER TimeStampToken (CMS SignedData, RFC 5652) is
+// the receipt proof and is checked offline against the block hash and the certificate
+// it carries.
+
+use super::der::{self, Reader, Value};
+use super::{Anchor, AnchorReceipt, RFC3161_KIND};
+use crate::{Block, ProvenanceError};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Request, Uri};
+use ring::digest::{self, Algorithm};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey, VerificationAlgorithm};
+use std::time::Duration;
+use uuid::Uuid;
+
+const SHA256: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+const SHA384: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 2];
+const SHA512: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 3];
+const SIGNED_DATA: &[u64] = &[1, 2, 840, 113549, 1, 7, 2];
+const TST_INFO: &[u64] = &[1, 2, 840, 113549, 1, 9, 16, 1, 4];
+const CONTENT_TYPE: &[u64] = &[1, 2, 840, 113549, 1, 9, 3];
+const MESSAGE_DIGEST: &[u64] = &[1, 2, 840, 113549, 1, 9, 4];
+const SIGNING_CERTIFICATE_V2: &[u64] = &[1, 2, 840, 113549, 1, 9, 16, 2, 47];
+const RSA_ENCRYPTION: &[u64] = &[1, 2, 840, 113549, 1, 1, 1];
+const SHA256_WITH_RSA: &[u64] = &[1, 2, 840, 113549, 1, 1, 11];
+const SHA384_WITH_RSA: &[u64] = &[1, 2, 840, 113549, 1, 1, 12];
+const SHA512_WITH_RSA: &[u64] = &[1, 2, 840, 113549, 1, 1, 13];
+const EC_PUBLIC_KEY: &[u64] = &[1, 2, 840, 10045, 2, 1];
+const P256: &[u64] = &[1, 2, 840, 10045, 3, 1, 7];
+const P384: &[u64] = &[1, 3, 132, 0, 34];
+const ECDSA_SHA256: &[u64] = &[1, 2, 840, 10045, 4, 3, 2];
+const ECDSA_SHA384: &[u64] = &[1, 2, 840, 10045, 4, 3, 3];
+const ED25519: &[u64] = &[1, 3, 101, 112];
+const COMMON_NAME: &[u64] = &[2, 5, 4, 3];
+const EXT_KEY_USAGE: &[u64] = &[2, 5, 29, 37];
+const TIME_STAMPING: &[u64] = &[1, 3, 6, 1, 5, 5, 7, 3, 8];
+const ANY_POLICY: &[u64] = &[2, 5, 29, 32, 0];
+
+/// How long a TSA gets to answer one request.
+const TSA_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn anchor_error(message: impl Into<String>) -> ProvenanceError {
+    ProvenanceError::AnchorError(message.into())
+}
+
+fn is_oid(content: &[u8], arcs: &[u64]) -> bool {
+    content == der::oid_content(arcs).as_slice()
+}
+
+/// OID content of an AlgorithmIdentifier, ignoring its parameters.
+fn algorithm_oid<'a>(value: Value<'a>) -> Result<&'a [u8], ProvenanceError> {
+    Ok(value.children().expect(der::OID)?.content)
+}
+
+fn digest_algorithm(oid: &[u8]) -> Option<&'static Algorithm> {
+    if is_oid(oid, SHA256) {
+        Some(&digest::SHA256)
+    } else if is_oid(oid, SHA384) {
+        Some(&digest::SHA384)
+    } else if is_oid(oid, SHA512) {
+        Some(&digest::SHA512)
+    } else {
+        None
+    }
+}
+
+/// Message imprint for a block: its hash decoded from hex.
+pub fn block_imprint(block_hash: &str) -> Result<Vec<u8>, ProvenanceError> {
+    match hex::decode(block_hash) {
+        Ok(bytes) if bytes.len() == digest::SHA256_OUTPUT_LEN => Ok(bytes),
+        _ => Err(anchor_error("block hash is not a hex SHA-256 digest")),
+    }
+}
+
+/// DER TimeStampReq for a SHA-256 imprint, asking for the TSA certificate.
+pub fn timestamp_request(imprint: &[u8], nonce: &[u8]) -> Vec<u8> {
+    der::sequence(&[
+        der::unsigned(&[1]),
+        der::sequence(&[
+            der::sequence(&[der::oid(SHA256), der::tlv(der::NULL, &[])]),
+            der::tlv(der::OCTET_STRING, imprint),
+        ]),
+        der::unsigned(nonce),
+        der::tlv(der::BOOLEAN, &[0xff]),
+    ])
+}
+
+/// Extracts the TimeStampToken from a DER TimeStampResp, failing unless it was granted.
+pub fn parse_response(response: &[u8]) -> Result<Vec<u8>, ProvenanceError> {
+    let mut outer = Reader::new(response).expect(der::SEQUENCE)?.children();
+    let mut status_info = outer.expect(der::SEQUENCE)?.children();
+    let status = der::parse_u64(status_info.expect(der::INTEGER)?.content)?;
+    // 0 is granted, 1 granted with modifications
+    if status > 1 {
+        let text = match status_info.optional(der::SEQUENCE)? {
+            Some(free_text) => free_text
+                .children()
+                .next_value()
+                .map(|v| format!(": {}", String::from_utf8_lossy(v.content)))
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        return Err(anchor_error(format!("timestamp authority refused the request (status {}){}", status, text)));
+    }
+    Ok(outer.expect(der::SEQUENCE)?.raw.to_vec())
+}
+
+/// What a verified timestamp token attests.
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    /// Hashed message of the SHA-256 imprint.
+    pub imprint: Vec<u8>,
+    pub gen_time: DateTime<Utc>,
+    /// INTEGER content of the nonce, if the request carried one.
+    pub nonce: Option<Vec<u8>>,
+    /// Hex SHA-256 of the DER certificate whose key signed the token.
+    pub signer: String,
+}
+
+/// Checks the CMS signature of a DER TimeStampToken and returns what it attests.
+/// The signer is any certificate embedded in the token; whether that TSA is trusted
+/// is up to the caller, who can pin `TokenInfo::signer`.
+pub fn verify_token(token: &[u8]) -> Result<TokenInfo, ProvenanceError> {
+    let mut content_info = Reader::new(token).expect(der::SEQUENCE)?.children();
+    if !is_oid(content_info.expect(der::OID)?.content, SIGNED_DATA) {
+        return Err(anchor_error("token is not CMS signed data"));
+    }
+    let signed_data = content_info.expect(der::context(0))?.children().expect(der::SEQUENCE)?;
+    let mut signed_data = signed_data.children();
+    signed_data.expect(der::INTEGER)?;
+    signed_data.expect(der::SET)?;
+    let mut encapsulated = signed_data.expect(der::SEQUENCE)?.children();
+    if !is_oid(encapsulated.expect(der::OID)?.content, TST_INFO) {
+        return Err(anchor_error("token does not hold a TSTInfo"));
+    }
+    let tst_info = encapsulated.expect(der::context(0))?.children().expect(der::OCTET_STRING)?.content;
+
+    let mut certificates = Vec::new();
+    if let Some(set) = signed_data.optional(der::context(0))? {
+        let mut reader = set.children();
+        while !reader.is_empty() {
+            certificates.push(reader.next_value()?.raw);
+        }
+    }
+    signed_data.optional(der::context(1))?;
+    let mut signer_info = signed_data.expect(der::SET)?.children().expect(der::SEQUENCE)?.children();
+    signer_info.expect(der::INTEGER)?;
+    signer_info.next_value()?;
+    let digest_oid = algorithm_oid(signer_info.expect(der::SEQUENCE)?)?;
+    let signed_attrs = signer_info
+        .optional(der::context(0))?
+        .ok_or_else(|| anchor_error("token has no signed attributes"))?;
+    let signature_oid = algorithm_oid(signer_info.expect(der::SEQUENCE)?)?;
+    let signature = signer_info.expect(der::OCTET_STRING)?.content;
+
+    // The signed attributes must commit to the TSTInfo
+    let digest_algorithm =
+        digest_algorithm(digest_oid).ok_or_else(|| anchor_error("unsupported digest algorithm"))?;
+    let expected_digest = digest::digest(digest_algorithm, tst_info);
+    let mut message_digest = None;
+    let mut attributes = signed_attrs.children();
+    while !attributes.is_empty() {
+        let mut attribute = attributes.expect(der::SEQUENCE)?.children();
+        let oid = attribute.expect(der::OID)?.content;
+        let value = attribute.expect(der::SET)?.children().next_value()?;
+        if is_oid(oid, MESSAGE_DIGEST) {
+            message_digest = Some(value.content);
+        } else if is_oid(oid, CONTENT_TYPE) && !is_oid(value.content, TST_INFO) {
+            return Err(anchor_error("signed content type is not TSTInfo"));
+        }
+    }
+    if message_digest != Some(expected_digest.as_ref()) {
+        return Err(anchor_error("message digest does not match the TSTInfo"));
+    }
+
+    // The signature covers the attributes re-tagged as a SET OF
+    let mut signed_bytes = signed_attrs.raw.to_vec();
+    signed_bytes[0] = der::SET;
+    let mut signer = None;
+    for certificate in certificates {
+        let public_key = match subject_public_key(certificate) {
+            Ok(public_key) => public_key,
+            Err(_) => continue,
+        };
+        let algorithm = match verification_algorithm(signature_oid, digest_oid, &public_key) {
+            Some(algorithm) => algorithm,
+            None => continue,
+        };
+        if UnparsedPublicKey::new(algorithm, public_key.key).verify(&signed_bytes, signature).is_ok() {
+            signer = Some(hex::encode(digest::digest(&digest::SHA256, certificate)));
+            break;
+        }
+    }
+    let signer = signer.ok_or_else(|| anchor_error("signature does not verify against any certificate in the token"))?;
+
+    let mut tst = Reader::new(tst_info).expect(der::SEQUENCE)?.children();
+    tst.expect(der::INTEGER)?;
+    tst.expect(der::OID)?;
+    let mut message_imprint = tst.expect(der::SEQUENCE)?.children();
+    if !is_oid(algorithm_oid(message_imprint.expect(der::SEQUENCE)?)?, SHA256) {
+        return Err(anchor_error("message imprint is not SHA-256"));
+    }
+    let imprint = message_imprint.expect(der::OCTET_STRING)?.content.to_vec();
+    tst.expect(der::INTEGER)?;
+    let gen_time = der::parse_generalized_time(tst.expect(der::GENERALIZED_TIME)?.content)?;
+    tst.optional(der::SEQUENCE)?;
+    tst.optional(der::BOOLEAN)?;
+    let nonce = tst.optional(der::INTEGER)?.map(|v| v.content.to_vec());
+
+    Ok(TokenInfo { imprint, gen_time, nonce, signer })
+}
+
+/// Subject public key of an X.509 certificate.
+struct PublicKeyInfo<'a> {
+    /// Key algorithm OID content.
+    algorithm: &'a [u8],
+    /// Named curve OID content, for EC keys.
+    curve: Option<&'a [u8]>,
+    key: &'a [u8],
+}
+
+fn subject_public_key(certificate: &[u8]) -> Result<PublicKeyInfo<'_>, ProvenanceError> {
+    let mut tbs = Reader::new(certificate)
+        .expect(der::SEQUENCE)?
+        .children()
+        .expect(der::SEQUENCE)?
+        .children();
+    tbs.optional(der::context(0))?;
+    tbs.expect(der::INTEGER)?;
+    // signature, issuer, validity, subject
+    for _ in 0..4 {
+        tbs.expect(der::SEQUENCE)?;
+    }
+    let mut spki = tbs.expect(der::SEQUENCE)?.children();
+    let mut algorithm = spki.expect(der::SEQUENCE)?.children();
+    let key_algorithm = algorithm.expect(der::OID)?.content;
+    let curve = algorithm.optional(der::OID)?.map(|v| v.content);
+    match spki.expect(der::BIT_STRING)?.content.split_first() {
+        Some((0, key)) => Ok(PublicKeyInfo { algorithm: key_algorithm, curve, key }),
+        _ => Err(anchor_error("malformed subject public key")),
+    }
+}
+
+/// ring algorithm for a CMS signature, given the signer's key type.
+fn verification_algorithm(
+    signature_oid: &[u8],
+    digest_oid: &[u8],
+    public_key: &PublicKeyInfo,
+) -> Option<&'static dyn VerificationAlgorithm> {
+    let key_oid = public_key.algorithm;
+    if is_oid(key_oid, RSA_ENCRYPTION) {
+        // Plain rsaEncryption takes its hash from the digest algorithm
+        let hash = if is_oid(signature_oid, RSA_ENCRYPTION) {
+            digest_oid.to_vec()
+        } else if is_oid(signature_oid, SHA256_WITH_RSA) {
+            der::oid_content(SHA256)
+        } else if is_oid(signature_oid, SHA384_WITH_RSA) {
+            der::oid_content(SHA384)
+        } else if is_oid(signature_oid, SHA512_WITH_RSA) {
+            der::oid_content(SHA512)
+        } else {
+            return None;
+        };
+        if is_oid(&hash, SHA256) {
+            Some(&signature::RSA_PKCS1_2048_8192_SHA256)
+        } else if is_oid(&hash, SHA384) {
+            Some(&signature::RSA_PKCS1_2048_8192_SHA384)
+        } else if is_oid(&hash, SHA512) {
+            Some(&signature::RSA_PKCS1_2048_8192_SHA512)
+        } else {
+            None
+        }
+    } else if is_oid(key_oid, EC_PUBLIC_KEY) {
+        let curve = public_key.curve?;
+        match (is_oid(curve, P256), is_oid(curve, P384)) {
+            (true, _) if is_oid(signature_oid, ECDSA_SHA256) => Some(&signature::ECDSA_P256_SHA256_ASN1),
+            (true, _) if is_oid(signature_oid, ECDSA_SHA384) => Some(&signature::ECDSA_P256_SHA384_ASN1),
+            (_, true) if is_oid(signature_oid, ECDSA_SHA256) => Some(&signature::ECDSA_P384_SHA256_ASN1),
+            (_, true) if is_oid(signature_oid, ECDSA_SHA384) => Some(&signature::ECDSA_P384_SHA384_ASN1),
+            _ => None,
+        }
+    } else if is_oid(key_oid, ED25519) && is_oid(signature_oid, ED25519) {
+        Some(&signature::ED25519)
+    } else {
+        None
+    }
+}
+
+/// Transport to a timestamp authority: takes a DER TimeStampReq and returns the DER
+/// TimeStampResp.
+#[async_trait]
+pub trait TimestampAuthority: Send + Sync {
+    /// Name recorded on receipts, e.g. the TSA URL.
+    fn name(&self) -> &str;
+
+    async fn request(&self, request: &[u8]) -> Result<Vec<u8>, ProvenanceError>;
+}
+
+/// TSA reached over HTTP (RFC 3161, section 3.4). Tokens are signed, so plain HTTP
+/// does not weaken them; `https` URLs are not supported.
+pub struct HttpTsa {
+    url: String,
+    uri: Uri,
+    client: Client<HttpConnector>,
+}
+
+impl HttpTsa {
+    pub fn new(url: &str) -> Result<Self, ProvenanceError> {
+        let uri: Uri = url.parse().map_err(|_| anchor_error(format!("invalid TSA URL: {}", url)))?;
+        if uri.scheme_str() != Some("http") {
+            return Err(anchor_error(format!("TSA URL must use http: {}", url)));
+        }
+        Ok(Self { url: url.to_string(), uri, client: Client::new() })
+    }
+}
+
+#[async_trait]
+impl TimestampAuthority for HttpTsa {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn request(&self, request: &[u8]) -> Result<Vec<u8>, ProvenanceError> {
+        let request = Request::post(self.uri.clone())
+            .header(hyper::header::CONTENT_TYPE, "application/timestamp-query")
+            .body(Body::from(request.to_vec()))
+            .map_err(|e| anchor_error(e.to_string()))?;
+        let exchange = async {
+            let response = self.client.request(request).await?;
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            Ok::<_, hyper::Error>((status, body))
+        };
+        let (status, body) = tokio::time::timeout(TSA_TIMEOUT, exchange)
+            .await
+            .map_err(|_| anchor_error(format!("timestamp authority {} timed out", self.url)))?
+            .map_err(|e| anchor_error(format!("timestamp authority {}: {}", self.url, e)))?;
+        if !status.is_success() {
+            return Err(anchor_error(format!("timestamp authority {} returned {}", self.url, status)));
+        }
+        Ok(body.to_vec())
+    }
+}
+
+/// In-process stand-in TSA for tests and single-node setups. Issues RFC 3161 tokens
+/// signed with an Ed25519 key (RFC 8419) and a self-signed certificate derived from it,
+/// so its receipts verify like those of a real TSA but attest only the local clock.
+pub struct LocalTsa {
+    key_pair: Ed25519KeyPair,
+    name: Vec<u8>,
+    certificate: Vec<u8>,
+}
+
+/// Name recorded on receipts issued by `LocalTsa`.
+pub const LOCAL_TSA_NAME: &str = "local-tsa";
+
+/// Serial number of the `LocalTsa` certificate.
+const LOCAL_TSA_CERT_SERIAL: &[u8] = &[1];
+
+impl LocalTsa {
+    /// Stand-in TSA signing with `key_pair`. The certificate depends only on the key,
+    /// so its fingerprint is stable across restarts.
+    pub fn new(key_pair: Ed25519KeyPair) -> Self {
+        let name = der::sequence(&[der::constructed(
+            der::SET,
+            &[der::sequence(&[der::oid(COMMON_NAME), der::tlv(der::UTF8_STRING, b"CAPCF local TSA")])],
+        )]);
+        let not_after = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+        let extended_key_usage = der::sequence(&[
+            der::oid(EXT_KEY_USAGE),
+            der::tlv(der::BOOLEAN, &[0xff]),
+            der::tlv(der::OCTET_STRING, &der::sequence(&[der::oid(TIME_STAMPING)])),
+        ]);
+        let tbs = der::sequence(&[
+            der::constructed(der::context(0), &[der::unsigned(&[2])]),
+            der::unsigned(LOCAL_TSA_CERT_SERIAL),
+            der::algorithm(ED25519),
+            name.clone(),
+            der::sequence(&[der::utc_time(DateTime::UNIX_EPOCH), der::generalized_time(not_after)]),
+            name.clone(),
+            der::sequence(&[der::algorithm(ED25519), der::bit_string(key_pair.public_key().as_ref())]),
+            der::constructed(der::context(3), &[der::sequence(&[extended_key_usage])]),
+        ]);
+        let signature = key_pair.sign(&tbs);
+        let certificate = der::sequence(&[tbs, der::algorithm(ED25519), der::bit_string(signature.as_ref())]);
+        Self { key_pair, name, certificate }
+    }
+
+    /// Stand-in TSA with a fresh key.
+    pub fn generate() -> Result<Self, ProvenanceError> {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)
+            .map_err(|_| ProvenanceError::KeyError("key generation failed".to_string()))?;
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+            .map_err(|e| ProvenanceError::KeyError(e.to_string()))?;
+        Ok(Self::new(key_pair))
+    }
+
+    /// DER certificate embedded in every token.
+    pub fn certificate(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    /// Hex SHA-256 of the certificate, as reported by `verify_token`.
+    pub fn fingerprint(&self) -> String {
+        hex::encode(digest::digest(&digest::SHA256, &self.certificate))
+    }
+
+    /// Answers a DER TimeStampReq with a granted DER TimeStampResp.
+    pub fn respond(&self, request: &[u8]) -> Result<Vec<u8>, ProvenanceError> {
+        let mut fields = Reader::new(request).expect(der::SEQUENCE)?.children();
+        if der::parse_u64(fields.expect(der::INTEGER)?.content)? != 1 {
+            return Err(anchor_error("unsupported TimeStampReq version"));
+        }
+        let message_imprint = fields.expect(der::SEQUENCE)?;
+        let policy = fields.optional(der::OID)?;
+        let nonce = fields.optional(der::INTEGER)?;
+        let cert_req = fields.optional(der::BOOLEAN)?.is_some_and(|v| v.content != [0]);
+
+        let mut tst_info = vec![
+            der::unsigned(&[1]),
+            policy.map_or_else(|| der::oid(ANY_POLICY), |p| p.raw.to_vec()),
+            message_imprint.raw.to_vec(),
+            der::unsigned(Uuid::new_v4().as_bytes()),
+            der::generalized_time(Utc::now()),
+        ];
+        if let Some(nonce) = nonce {
+            tst_info.push(nonce.raw.to_vec());
+        }
+        let token = self.sign(&der::sequence(&tst_info), cert_req);
+        Ok(der::sequence(&[der::sequence(&[der::unsigned(&[0])]), token]))
+    }
+
+    /// Wraps a DER TSTInfo in CMS SignedData.
+    fn sign(&self, tst_info: &[u8], include_certificate: bool) -> Vec<u8> {
+        let attribute = |oid: &[u64], value: Vec<u8>| der::sequence(&[der::oid(oid), der::constructed(der::SET, &[value])]);
+        // RFC 8419: Ed25519 with signed attributes uses SHA-512 for the message digest
+        let message_digest = digest::digest(&digest::SHA512, tst_info);
+        let cert_hash = digest::digest(&digest::SHA256, &self.certificate);
+        let mut attributes = vec![
+            attribute(CONTENT_TYPE, der::oid(TST_INFO)),
+            attribute(MESSAGE_DIGEST, der::tlv(der::OCTET_STRING, message_digest.as_ref())),
+            // ESSCertIDv2 with the default SHA-256 hash algorithm
+            attribute(
+                SIGNING_CERTIFICATE_V2,
+                der::sequence(&[der::sequence(&[der::sequence(&[der::tlv(der::OCTET_STRING, cert_hash.as_ref())])])]),
+            ),
+        ];
+        // DER orders SET OF elements by their encoding
+        attributes.sort();
+        let signature = self.key_pair.sign(&der::constructed(der::SET, &attributes));
+
+        let signer_info = der::sequence(&[
+            der::unsigned(&[1]),
+            der::sequence(&[self.name.clone(), der::unsigned(LOCAL_TSA_CERT_SERIAL)]),
+            der::algorithm(SHA512),
+            der::constructed(der::context(0), &attributes),
+            der::algorithm(ED25519),
+            der::tlv(der::OCTET_STRING, signature.as_ref()),
+        ]);
+        let mut signed_data = vec![
+            der::unsigned(&[3]),
+            der::constructed(der::SET, &[der::algorithm(SHA512)]),
+            der::sequence(&[
+                der::oid(TST_INFO),
+                der::constructed(der::context(0), &[der::tlv(der::OCTET_STRING, tst_info)]),
+            ]),
+        ];
+        if include_certificate {
+            signed_data.push(der::tlv(der::context(0), &self.certificate));
+        }
+        signed_data.push(der::constructed(der::SET, &[signer_info]));
+        der::sequence(&[
+            der::oid(SIGNED_DATA),
+            der::constructed(der::context(0), &[der::sequence(&signed_data)]),
+        ])
+    }
+}
+
+#[async_trait]
+impl TimestampAuthority for LocalTsa {
+    fn name(&self) -> &str {
+        LOCAL_TSA_NAME
+    }
+
+    async fn request(&self, request: &[u8]) -> Result<Vec<u8>, ProvenanceError> {
+        self.respond(request)
+    }
+}
+
+/// Anchors blocks by timestamping their hashes with an RFC 3161 authority.
+pub struct TimestampAnchor<T> {
+    tsa: T,
+}
+
+impl<T: TimestampAuthority> TimestampAnchor<T> {
+    pub fn new(tsa: T) -> Self {
+        Self { tsa }
+    }
+
+    pub fn authority(&self) -> &T {
+        &self.tsa
+    }
+}
+
+#[async_trait]
+impl<T: TimestampAuthority> Anchor for TimestampAnchor<T> {
+    fn name(&self) -> &str {
+        self.tsa.name()
+    }
+
+    async fn anchor(&self, block: &Block) -> Result<AnchorReceipt, ProvenanceError> {
+        let imprint = block_imprint(&block.hash)?;
+        let mut nonce = [0u8; 8];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anchor_error("failed to generate a nonce"))?;
+        let response = self.tsa.request(&timestamp_request(&imprint, &nonce)).await?;
+        let token = parse_response(&response)?;
+        let info = verify_token(&token)?;
+        if info.imprint != imprint {
+            return Err(anchor_error("timestamp is for a different hash"));
+        }
+        if info.nonce != Some(der::unsigned_content(&nonce)) {
+            return Err(anchor_error("timestamp nonce does not match the request"));
+        }
+        Ok(AnchorReceipt {
+            anchor: self.name().to_string(),
+            kind: RFC3161_KIND.to_string(),
+            block_hash: block.hash.clone(),
+            anchored_at: info.gen_time,
+            proof: hex::encode(token),
+        })
+    }
+}
+
+/// Checks an RFC 3161 receipt against `block_hash`.
+pub fn verify_receipt(block_hash: &str, receipt: &AnchorReceipt) -> Result<TokenInfo, ProvenanceError> {
+    if receipt.block_hash != block_hash {
+        return Err(anchor_error("receipt is for a different block hash"));
+    }
+    let token = hex::decode(&receipt.proof).map_err(|_| anchor_error("receipt proof is not hex"))?;
+    let info = verify_token(&token)?;
+    if info.imprint != block_imprint(block_hash)? {
+        return Err(anchor_error("timestamp is for a different hash"));
+    }
+    if info.gen_time != receipt.anchored_at {
+        return Err(anchor_error("receipt time does not match the timestamp"));
+    }
+    Ok(info)
+}
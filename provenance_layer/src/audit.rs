@@ -0,0 +1,249 @@
+// Offline audit of a provenance database.
+// Re-checks the PL invariants from stored state alone, without the service: event
+// signatures, log sequencing, the block hash chain, Merkle roots, acyclicity of G_P and
+// referential integrity between events, artifacts and blocks. Only reads; run it on a
+// read-only storage handle (see `StorageConfig::open_read_only`).
+
+use crate::graph::{GraphView, ProvenanceGraph};
+use crate::keystore::Keystore;
+use crate::provenance_impl::{
+    decode_seq, verify_blocks, ARTIFACTS_TREE, BLOCKS_TREE, CHAIN_HEAD_KEY, CHAIN_TREE, EVENT_SEQS_TREE, LEGACY_EVENTS_TREE,
+    LOG_SEQUENCED_KEY, LOG_TREE,
+};
+use crate::storage::Storage;
+use crate::*;
+use std::sync::Arc;
+
+/// Failures listed per check; further failures are only counted.
+const MAX_LISTED_FAILURES: usize = 100;
+
+/// Outcome of an audit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// Whether every check passed.
+    pub valid: bool,
+    pub events: u64,
+    pub artifacts: u64,
+    pub blocks: u64,
+    pub checks: Vec<AuditCheck>,
+}
+
+/// Outcome of one kind of check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditCheck {
+    pub name: String,
+    pub passed: bool,
+    /// Items examined.
+    pub checked: u64,
+    /// Items that failed.
+    pub failed: u64,
+    /// The first failures, in the order found.
+    pub failures: Vec<AuditFailure>,
+}
+
+/// One item that failed a check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditFailure {
+    /// `event:<id>`, `artifact:<id>` or `block:<height>`.
+    pub subject: String,
+    pub reason: String,
+}
+
+impl AuditCheck {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: true,
+            checked: 0,
+            failed: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Records one examined item, failed if there is a `problem`.
+    fn record(&mut self, subject: impl FnOnce() -> String, problem: Option<String>) {
+        self.checked += 1;
+        if let Some(reason) = problem {
+            self.passed = false;
+            self.failed += 1;
+            if self.failures.len() < MAX_LISTED_FAILURES {
+                self.failures.push(AuditFailure { subject: subject(), reason });
+            }
+        }
+    }
+}
+
+/// Audits the database in `storage`.
+pub fn audit(storage: Arc<dyn Storage>) -> Result<AuditReport, ProvenanceError> {
+    let keystore = Keystore::open(storage.clone());
+    let marker = storage.get(CHAIN_TREE, LOG_SEQUENCED_KEY.as_bytes())?;
+    let sequenced = marker.is_some();
+    let mut signatures = AuditCheck::new("event_signatures");
+    let mut sequence = AuditCheck::new("log_sequence");
+    let mut chain = AuditCheck::new("block_chain");
+    let mut roots = AuditCheck::new("merkle_roots");
+    let mut dag = AuditCheck::new("dag_acyclic");
+    let mut references = AuditCheck::new("referential_integrity");
+
+    // Events, in log order
+    let mut graph = ProvenanceGraph::new();
+    let events = logged_events(storage.as_ref(), sequenced)?;
+    // Events migrated from before sequence numbers head the log and carry none; a
+    // marker set before they were counted leaves them to be told by the missing `seq`
+    let migrated = match marker.as_deref().map(<[u8; 8]>::try_from) {
+        Some(Ok(count)) => u64::from_be_bytes(count),
+        _ => events.iter().take_while(|(_, event)| event.seq.is_none()).count() as u64,
+    };
+    for (position, (stored_at, event)) in events.iter().enumerate() {
+        let subject = || format!("event:{}", event.id);
+
+        if sequenced {
+            let position = position as u64;
+            let expected = if position < migrated { None } else { Some(position) };
+            let problem = if *stored_at != position {
+                Some(format!("log position {} is missing; the next event is at {}", position, stored_at))
+            } else if event.seq != expected {
+                Some(format!("log position {} holds sequence number {:?}", position, event.seq))
+            } else if storage.get(EVENT_SEQS_TREE, event.id.to_string().as_bytes())?.as_deref()
+                != Some(&position.to_be_bytes()[..])
+            {
+                Some("event is not indexed at its log position".to_string())
+            } else {
+                None
+            };
+            sequence.record(subject, problem);
+        }
+
+        let problem = match &event.signature {
+            None => Some("event is unsigned".to_string()),
+            Some(signature) if signature.signer != event.actor => {
+                Some(format!("signed by {}, not by the actor", signature.signer))
+            }
+            Some(signature) => keystore.verify_at(&event.signing_payload()?, signature, event.timestamp)?.reason,
+        };
+        signatures.record(subject, problem);
+
+        let mut missing = Vec::new();
+        for &id in event.in_artifacts.iter().chain(&event.out_artifacts) {
+            if !storage.contains(ARTIFACTS_TREE, id.to_string().as_bytes())? {
+                missing.push(id.to_string());
+            }
+        }
+        let problem = (!missing.is_empty()).then(|| format!("references unknown artifacts {}", missing.join(", ")));
+        references.record(subject, problem);
+
+        // An event closing a cycle is left out so later events are judged on the valid graph
+        match graph.find_cycle(event)? {
+            Some((from, to)) => dag.record(subject, Some(format!("derivation {} -> {} closes a cycle", from, to))),
+            None => {
+                graph.add_event(event);
+                dag.record(subject, None);
+            }
+        }
+    }
+
+    // Artifacts: version links must point at registered artifacts
+    let mut artifacts = 0;
+    for entry in storage.iter(ARTIFACTS_TREE) {
+        let (_key, value) = entry?;
+        let artifact: Artifact = serde_json::from_slice(&value)?;
+        artifacts += 1;
+        let mut problem = None;
+        for (link, target) in [("family", artifact.family_id), ("previous version", artifact.previous_version)] {
+            if let Some(id) = target.filter(|id| *id != artifact.id) {
+                if !storage.contains(ARTIFACTS_TREE, id.to_string().as_bytes())? {
+                    problem = Some(format!("{} {} is not registered", link, id));
+                }
+            }
+        }
+        references.record(|| format!("artifact:{}", artifact.id), problem);
+    }
+
+    // Blocks: the chain as a whole, then each block on its own
+    let head = match storage.get(CHAIN_TREE, CHAIN_HEAD_KEY.as_bytes())? {
+        Some(value) => serde_json::from_slice(&value)?,
+        None => ChainHead::default(),
+    };
+    let verification = verify_blocks(storage.as_ref(), head)?;
+    let mut blocks = 0;
+    for entry in storage.iter(BLOCKS_TREE) {
+        let (_key, value) = entry?;
+        let block: Block = serde_json::from_slice(&value)?;
+        blocks += 1;
+        let subject = || format!("block:{}", block.height);
+
+        if let Some(root) = &block.merkle_root {
+            let problem = (hex::encode(merkle::events_root(&block.events)?) != *root)
+                .then(|| "merkle root does not match the block events".to_string());
+            roots.record(subject, problem);
+        }
+
+        let mut problem = None;
+        for sealed in &block.events {
+            match logged_event(storage.as_ref(), sequenced, sealed.id)? {
+                None => problem = Some(format!("sealed event {} is not in the log", sealed.id)),
+                Some(logged) if canonical::to_canonical_json(&logged)? != canonical::to_canonical_json(sealed)? => {
+                    problem = Some(format!("sealed event {} differs from the log", sealed.id))
+                }
+                Some(_) => continue,
+            }
+            break;
+        }
+        references.record(subject, problem);
+    }
+    // The walk stops at the first broken link, so the chain fails at most once
+    chain.checked = blocks;
+    if let Some(link) = verification.first_broken_link {
+        chain.passed = false;
+        chain.failed = 1;
+        chain.failures.push(AuditFailure {
+            subject: format!("block:{}", link.height),
+            reason: link.reason,
+        });
+    }
+
+    let checks = vec![signatures, sequence, chain, roots, dag, references];
+    Ok(AuditReport {
+        valid: checks.iter().all(|check| check.passed),
+        events: events.len() as u64,
+        artifacts,
+        blocks,
+        checks,
+    })
+}
+
+/// Every logged event with its log position, in log order. Databases written before
+/// sequence numbers are read from the legacy tree in timestamp order, numbered as the
+/// service would number them.
+fn logged_events(storage: &dyn Storage, sequenced: bool) -> Result<Vec<(u64, Event)>, ProvenanceError> {
+    if sequenced {
+        return storage
+            .iter(LOG_TREE)
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((decode_seq(&key)?, serde_json::from_slice(&value)?))
+            })
+            .collect();
+    }
+    let mut events = Vec::new();
+    for entry in storage.iter(LEGACY_EVENTS_TREE) {
+        let (_key, value) = entry?;
+        events.push(serde_json::from_slice::<Event>(&value)?);
+    }
+    events.sort_by_key(|event| (event.timestamp, event.id));
+    Ok((0..).zip(events).collect())
+}
+
+/// The logged event with `id`, if any.
+fn logged_event(storage: &dyn Storage, sequenced: bool, id: Uuid) -> Result<Option<Event>, ProvenanceError> {
+    let key = id.to_string();
+    let value = if sequenced {
+        match storage.get(EVENT_SEQS_TREE, key.as_bytes())? {
+            Some(seq) => storage.get(LOG_TREE, &seq)?,
+            None => None,
+        }
+    } else {
+        storage.get(LEGACY_EVENTS_TREE, key.as_bytes())?
+    };
+    Ok(value.map(|value| serde_json::from_slice(&value)).transpose()?)
+}
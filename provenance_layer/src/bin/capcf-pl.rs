@@ -0,0 +1,109 @@
+// capcf-pl: offline tools for Provenance Layer databases.
+//
+//   capcf-pl verify [--backend sled|sqlite] [--db PATH]
+//   capcf-pl verify-bundle FILE
+//
+// `verify` audits a database snapshot without starting the service or modifying the
+// database, and prints an `AuditReport` as JSON. The backend and path default to
+// `PL_STORAGE` and `PL_DB_PATH`, as for the service.
+// `verify-bundle` checks a provenance bundle (`-` reads it from stdin) and prints a
+// `BundleVerification` as JSON.
+// Exit status: 0 if every check passes, 1 if any fails, 2 if the input cannot be read.
+
+use provenance_layer::audit;
+use provenance_layer::bundle::{self, ProvenanceBundle};
+use provenance_layer::storage::StorageConfig;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: capcf-pl verify [--backend sled|sqlite] [--db PATH]\n       capcf-pl verify-bundle FILE";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("verify") => verify(&args[1..]),
+        Some("verify-bundle") => verify_bundle(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn verify(args: &[String]) -> ExitCode {
+    let config = match parse_config(args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let report = match config.open_read_only().and_then(audit::audit) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("failed to audit {}: {}", config.path.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    print_verdict(&report, report.valid)
+}
+
+fn verify_bundle(args: &[String]) -> ExitCode {
+    let path = match args {
+        [path] => path,
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let verification = match read_bundle(path).and_then(|bundle| bundle::verify_bundle(&bundle)) {
+        Ok(verification) => verification,
+        Err(e) => {
+            eprintln!("failed to verify {}: {}", path, e);
+            return ExitCode::from(2);
+        }
+    };
+    print_verdict(&verification, verification.valid)
+}
+
+fn read_bundle(path: &str) -> Result<ProvenanceBundle, provenance_layer::ProvenanceError> {
+    let mut bytes = Vec::new();
+    if path == "-" {
+        std::io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        bytes = std::fs::read(path)?;
+    }
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+fn print_verdict(result: &impl serde::Serialize, valid: bool) -> ExitCode {
+    // The exit status carries the verdict even if the reader closes the pipe early
+    let _ = writeln!(std::io::stdout(), "{}", serde_json::to_string_pretty(result).expect("result serializes"));
+    if valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn parse_config(args: &[String]) -> Result<StorageConfig, String> {
+    let mut config = StorageConfig::from_env().map_err(|e| e.to_string())?;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+        match arg.as_str() {
+            "--backend" => config.backend = value.parse().map_err(|e: provenance_layer::ProvenanceError| e.to_string())?,
+            "--db" => path = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    // A backend chosen on the command line gets that backend's default path
+    if let Some(path) = path {
+        config.path = path;
+    } else if std::env::var_os("PL_DB_PATH").is_none() {
+        config.path = StorageConfig::default_path(config.backend);
+    }
+    Ok(config)
+}
@@ -0,0 +1,152 @@
+// Content-addressed blob store for artifact content.
+// Bytes are hashed server-side while they stream in, so a stored blob is always named by
+// a_h = H(content(a)). Blobs live on disk under <root>/sha256/<aa>/<hash>; identical
+// content is stored once.
+
+use crate::ProvenanceError;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use uuid::Uuid;
+
+/// Result of storing a blob.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlobInfo {
+    /// Lowercase hex SHA-256 of the content.
+    pub content_hash: String,
+    pub size: u64,
+    /// False if identical content was already stored.
+    pub created: bool,
+}
+
+/// Content-addressed storage rooted at a directory.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+/// Whether `hash` is a well-formed lowercase hex SHA-256 digest.
+pub fn is_content_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+impl BlobStore {
+    /// Opens the store, creating its directories if needed.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, ProvenanceError> {
+        let root = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(root.join("sha256"))?;
+        std::fs::create_dir_all(root.join("tmp"))?;
+        Ok(Self { root })
+    }
+
+    /// Location of the blob named `hash`, whether or not it exists.
+    /// Returns None for anything that is not a content hash, so paths cannot escape the root.
+    pub fn path(&self, hash: &str) -> Option<PathBuf> {
+        is_content_hash(hash).then(|| self.root.join("sha256").join(&hash[..2]).join(hash))
+    }
+
+    /// Whether a blob named `hash` is stored.
+    pub fn contains(&self, hash: &str) -> bool {
+        self.path(hash).is_some_and(|p| p.is_file())
+    }
+
+    /// Starts a streaming upload.
+    pub async fn writer(&self) -> Result<BlobWriter, ProvenanceError> {
+        let tmp = self.root.join("tmp").join(Uuid::new_v4().to_string());
+        let file = tokio::fs::File::create(&tmp).await?;
+        Ok(BlobWriter {
+            store: self.clone(),
+            file,
+            tmp,
+            ctx: ring::digest::Context::new(&ring::digest::SHA256),
+            size: 0,
+        })
+    }
+
+    /// Stores `bytes` in one go.
+    pub async fn put(&self, bytes: &[u8]) -> Result<BlobInfo, ProvenanceError> {
+        let mut writer = self.writer().await?;
+        writer.write(bytes).await?;
+        writer.finish().await
+    }
+
+    /// Reads the blob named `hash` if it is stored and at most `limit` bytes long.
+    pub async fn read(&self, hash: &str, limit: u64) -> Result<Option<Vec<u8>>, ProvenanceError> {
+        let path = match self.path(hash) {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(None),
+        };
+        if tokio::fs::metadata(&path).await?.len() > limit {
+            return Ok(None);
+        }
+        Ok(Some(tokio::fs::read(path).await?))
+    }
+
+    /// Re-hashes the stored bytes and checks that they still match `hash`.
+    /// Returns false if no such blob is stored.
+    pub async fn verify(&self, hash: &str) -> Result<bool, ProvenanceError> {
+        let path = match self.path(hash) {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(false),
+        };
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut ctx = ring::digest::Context::new(&ring::digest::SHA256);
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            ctx.update(&buf[..n]);
+        }
+        Ok(hex::encode(ctx.finish()) == hash)
+    }
+}
+
+/// An upload in progress. Content is written to a temporary file and moved to its
+/// content address by `finish`; an upload dropped before then removes the file.
+pub struct BlobWriter {
+    store: BlobStore,
+    file: tokio::fs::File,
+    tmp: PathBuf,
+    ctx: ring::digest::Context,
+    size: u64,
+}
+
+impl BlobWriter {
+    /// Appends a chunk of content.
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<(), ProvenanceError> {
+        self.ctx.update(chunk);
+        self.size += chunk.len() as u64;
+        self.file.write_all(chunk).await?;
+        Ok(())
+    }
+
+    /// Completes the upload and names the blob by its hash.
+    pub async fn finish(mut self) -> Result<BlobInfo, ProvenanceError> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+        let content_hash = hex::encode(self.ctx.clone().finish());
+        let path = self
+            .store
+            .path(&content_hash)
+            .ok_or_else(|| ProvenanceError::BlobError("invalid content hash".to_string()))?;
+        let created = !path.is_file();
+        if created {
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            tokio::fs::rename(&self.tmp, &path).await?;
+        } else {
+            tokio::fs::remove_file(&self.tmp).await?;
+        }
+        Ok(BlobInfo { content_hash, size: self.size, created })
+    }
+}
+
+impl Drop for BlobWriter {
+    fn drop(&mut self) {
+        // Gone already once `finish` moved or removed it
+        let _ = std::fs::remove_file(&self.tmp);
+    }
+}
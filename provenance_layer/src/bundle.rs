@@ -0,0 +1,225 @@
+// Portable provenance bundles.
+// A bundle carries an artifact's lineage subgraph out of the Provenance Layer with
+// everything needed to check it elsewhere: the artifact records and their authorship
+// chains, the signed events, the key histories of the signers and of the log, inclusion
+// proofs tying each sealed event to its block, and proofs tying those blocks to a signed
+// checkpoint. `verify_bundle` checks a bundle with no access to a provenance_db.
+//
+// The keys travel inside the bundle, so verification alone shows that the bundle is
+// consistent and signed by the keys it names, each signature by the key valid at the
+// time it was made. A recipient who compares the log key in
+// the result with the one the origin publishes also knows which log sealed the events.
+
+use crate::audit::AuditFailure;
+use crate::keystore::{self, KeyRecord};
+use crate::*;
+use std::collections::{BTreeMap, HashMap};
+
+/// Format identifier written into every bundle.
+pub const BUNDLE_FORMAT: &str = "capcf-provenance-bundle/2";
+
+/// Self-contained provenance of an artifact, for delivery outside the Provenance Layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceBundle {
+    pub format: String,
+    /// Artifact whose lineage the bundle carries.
+    pub artifact_id: Uuid,
+    pub exported_at: DateTime<Utc>,
+    /// Records of the lineage artifacts and of the earlier versions they supersede,
+    /// with their authorship chains.
+    pub artifacts: Vec<Artifact>,
+    /// Signed events of the lineage subgraph, in log order.
+    pub events: Vec<Event>,
+    /// Key history of every signer: event actors, authorship signers and
+    /// CHECKPOINT_SIGNER.
+    pub key_histories: BTreeMap<String, Vec<KeyRecord>>,
+    /// Inclusion proof of each event that was sealed at export.
+    pub inclusion_proofs: Vec<InclusionProof>,
+    /// Checkpoint of the chain at export.
+    pub checkpoint: Checkpoint,
+    /// Proofs that the blocks named by `inclusion_proofs` are covered by `checkpoint`.
+    pub block_proofs: Vec<BlockProof>,
+}
+
+/// Proof that a block hash is the leaf at `height` of the checkpoint tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockProof {
+    pub height: u64,
+    pub block_hash: String,
+    /// Hex sibling hashes from the leaf up to the checkpoint root.
+    pub audit_path: Vec<String>,
+}
+
+/// Outcome of verifying a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVerification {
+    pub artifact_id: Uuid,
+    /// Whether every record, signature and proof in the bundle checks out.
+    pub valid: bool,
+    /// Events that were not sealed at export, so are backed by their signature only.
+    pub unsealed_events: Vec<Uuid>,
+    /// Whether the bundle is valid and every event is sealed.
+    pub complete: bool,
+    pub events: u64,
+    pub artifacts: u64,
+    /// Hex public key of the log that signed the checkpoint, as of the checkpoint.
+    pub log_public_key: Option<String>,
+    /// Every problem found; subjects are `bundle`, `checkpoint`, `block:<height>`,
+    /// `event:<id>` or `artifact:<id>`.
+    pub failures: Vec<AuditFailure>,
+}
+
+/// Result of importing a bundle.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleImportSummary {
+    pub verification: BundleVerification,
+    /// Artifacts stored from the bundle; those already registered are not listed.
+    pub artifacts: Vec<Uuid>,
+    /// The bundle's events as appended to this log, in log order.
+    pub events: Vec<Event>,
+}
+
+fn failure(subject: impl Into<String>, reason: impl Into<String>) -> AuditFailure {
+    AuditFailure {
+        subject: subject.into(),
+        reason: reason.into(),
+    }
+}
+
+/// Verifies `bundle` offline, using only the keys and proofs it carries.
+pub fn verify_bundle(bundle: &ProvenanceBundle) -> Result<BundleVerification, ProvenanceError> {
+    let mut failures = Vec::new();
+    if bundle.format != BUNDLE_FORMAT {
+        failures.push(failure("bundle", format!("unsupported format {:?}", bundle.format)));
+    }
+
+    for (signer, history) in &bundle.key_histories {
+        if history.iter().any(|key| key.actor != *signer || hex::decode(&key.public_key).is_err()) {
+            failures.push(failure("bundle", format!("key history of {} is malformed", signer)));
+        }
+    }
+    let history = |signer: &str| bundle.key_histories.get(signer).map(Vec::as_slice).unwrap_or_default();
+
+    // The checkpoint, and the blocks proven to be in it
+    let checkpoint = &bundle.checkpoint;
+    let log_key = keystore::key_at(history(CHECKPOINT_SIGNER), checkpoint.timestamp);
+    let problem = if history(CHECKPOINT_SIGNER).is_empty() {
+        Some("no public key for the log".to_string())
+    } else if checkpoint.signature.signer != CHECKPOINT_SIGNER {
+        Some("not signed by the log".to_string())
+    } else {
+        let payload = Checkpoint::signing_payload(checkpoint.tree_size, &checkpoint.root_hash, checkpoint.timestamp)?;
+        keystore::verify_with_history(history(CHECKPOINT_SIGNER), &payload, &checkpoint.signature, checkpoint.timestamp)
+            .reason
+    };
+    if let Some(reason) = problem {
+        failures.push(failure("checkpoint", reason));
+    }
+    let mut blocks = HashMap::new();
+    for proof in &bundle.block_proofs {
+        if proves_block(proof, checkpoint) {
+            blocks.insert(proof.height, proof.block_hash.as_str());
+        } else {
+            failures.push(failure(
+                format!("block:{}", proof.height),
+                "block is not covered by the checkpoint",
+            ));
+        }
+    }
+
+    // Artifacts: records and authorship chains
+    let mut artifacts = HashMap::new();
+    for artifact in &bundle.artifacts {
+        if artifacts.insert(artifact.id, artifact).is_some() {
+            failures.push(failure(format!("artifact:{}", artifact.id), "artifact appears more than once"));
+        }
+    }
+    if !artifacts.contains_key(&bundle.artifact_id) {
+        failures.push(failure("bundle", format!("artifact {} is not in the bundle", bundle.artifact_id)));
+    }
+    for artifact in &bundle.artifacts {
+        let subject = || format!("artifact:{}", artifact.id);
+        if !artifact.metadata_digest.is_empty() && artifact.metadata_digest != artifact.compute_metadata_digest()? {
+            failures.push(failure(subject(), "metadata digest does not match the metadata"));
+        }
+        for (link, target) in [("family", artifact.family_id), ("previous version", artifact.previous_version)] {
+            if let Some(id) = target.filter(|id| *id != artifact.id && !artifacts.contains_key(id)) {
+                failures.push(failure(subject(), format!("{} {} is not in the bundle", link, id)));
+            }
+        }
+        let authorship = artifact.verify_authorship_with(Vec::new(), |signer| Ok(history(signer).to_vec()))?;
+        if let (Some(index), Some(reason)) = (authorship.first_invalid, authorship.reason) {
+            failures.push(failure(subject(), format!("authorship entry {}: {}", index, reason)));
+        }
+    }
+
+    // Events: signatures, references and inclusion in a checkpointed block
+    let proofs: HashMap<Uuid, &InclusionProof> =
+        bundle.inclusion_proofs.iter().map(|proof| (proof.event_id, proof)).collect();
+    let mut unsealed_events = Vec::new();
+    for event in &bundle.events {
+        let subject = || format!("event:{}", event.id);
+        let problem = match &event.signature {
+            None => Some("event is unsigned".to_string()),
+            Some(signature) if signature.signer != event.actor => {
+                Some(format!("signed by {}, not by the actor", signature.signer))
+            }
+            Some(_) if history(&event.actor).is_empty() => Some(format!("no public key for {}", event.actor)),
+            Some(signature) => {
+                keystore::verify_with_history(history(&event.actor), &event.signing_payload()?, signature, event.timestamp)
+                    .reason
+            }
+        };
+        if let Some(reason) = problem {
+            failures.push(failure(subject(), reason));
+        }
+
+        let missing: Vec<String> = event
+            .in_artifacts
+            .iter()
+            .chain(&event.out_artifacts)
+            .filter(|id| !artifacts.contains_key(id))
+            .map(Uuid::to_string)
+            .collect();
+        if !missing.is_empty() {
+            failures.push(failure(subject(), format!("references artifacts not in the bundle: {}", missing.join(", "))));
+        }
+
+        match proofs.get(&event.id) {
+            None => unsealed_events.push(event.id),
+            Some(proof) if !merkle::verify_inclusion(event, proof)? => {
+                failures.push(failure(subject(), "inclusion proof does not verify"))
+            }
+            Some(proof) if blocks.get(&proof.block_height) != Some(&proof.block_hash.as_str()) => failures.push(
+                failure(subject(), format!("block {} is not proven to be in the checkpoint", proof.block_height)),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    let valid = failures.is_empty();
+    Ok(BundleVerification {
+        artifact_id: bundle.artifact_id,
+        valid,
+        complete: valid && unsealed_events.is_empty(),
+        unsealed_events,
+        events: bundle.events.len() as u64,
+        artifacts: bundle.artifacts.len() as u64,
+        log_public_key: log_key.map(|key| key.public_key.clone()),
+        failures,
+    })
+}
+
+/// Whether `proof` places its block hash in the checkpoint tree.
+fn proves_block(proof: &BlockProof, checkpoint: &Checkpoint) -> bool {
+    let path = match proof.audit_path.iter().map(hex::decode).collect::<Result<Vec<_>, _>>() {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    let leaf = match merkle::block_hash_leaf(&proof.block_hash) {
+        Ok(leaf) => leaf,
+        Err(_) => return false,
+    };
+    merkle::root_from_path(&leaf, proof.height, checkpoint.tree_size, &path).map(hex::encode)
+        == Some(checkpoint.root_hash.clone())
+}
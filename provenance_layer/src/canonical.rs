@@ -0,0 +1,135 @@
+// Canonical encoding for hashing and signing.
+// Implements the JSON Canonicalization Scheme (RFC 8785) so that digests over events,
+// blocks and artifact metadata can be reproduced byte-for-byte by verifiers in any language.
+// Test vectors live in tests/vectors/canonical.json.
+
+use crate::ProvenanceError;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Serializes `value` to its RFC 8785 canonical JSON form.
+pub fn to_canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>, ProvenanceError> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::new();
+    write_value(&mut out, &value)?;
+    Ok(out.into_bytes())
+}
+
+/// SHA-256 over the canonical JSON form of `value`.
+pub fn digest<T: Serialize>(value: &T) -> Result<Vec<u8>, ProvenanceError> {
+    let bytes = to_canonical_json(value)?;
+    Ok(ring::digest::digest(&ring::digest::SHA256, &bytes).as_ref().to_vec())
+}
+
+/// Hex-encoded SHA-256 over the canonical JSON form of `value`.
+pub fn digest_hex<T: Serialize>(value: &T) -> Result<String, ProvenanceError> {
+    Ok(hex::encode(digest(value)?))
+}
+
+fn write_value(out: &mut String, value: &Value) -> Result<(), ProvenanceError> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            // JCS numbers are IEEE 754 doubles, exactly as in ECMAScript.
+            let f = n.as_f64().ok_or_else(|| {
+                ProvenanceError::CanonicalizationError(format!("unrepresentable number {}", n))
+            })?;
+            write_number(out, f)?;
+        }
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Members are ordered by the UTF-16 code units of their names.
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, item)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Formats a double the way ECMAScript's Number.prototype.toString does.
+fn write_number(out: &mut String, f: f64) -> Result<(), ProvenanceError> {
+    if !f.is_finite() {
+        return Err(ProvenanceError::CanonicalizationError(format!(
+            "non-finite number {}",
+            f
+        )));
+    }
+    if f == 0.0 {
+        out.push('0');
+        return Ok(());
+    }
+    if f < 0.0 {
+        out.push('-');
+    }
+
+    // Rust's `{:e}` yields the shortest round-tripping digits, e.g. "1.2345e-7".
+    let sci = format!("{:e}", f.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat((-n) as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        let _ = write!(out, "e{}{}", if n - 1 < 0 { '-' } else { '+' }, (n - 1).abs());
+    }
+    Ok(())
+}
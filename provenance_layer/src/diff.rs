@@ -0,0 +1,169 @@
+// Structural diff of JSON values, and of artifact content.
+// Paths are JSON Pointers (RFC 6901); objects are compared member by member and
+// anything else (arrays, scalars) is compared as a whole. Content that is JSON on both
+// sides is diffed that way; other UTF-8 text is diffed line by line.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Kind of change at a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One difference between two JSON values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffEntry {
+    /// JSON Pointer to the member that differs.
+    pub path: String,
+    pub op: DiffOp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+}
+
+/// Differences that turn `from` into `to`, ordered by path.
+pub fn json_diff(from: &Value, to: &Value) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    diff_at(String::new(), from, to, &mut entries);
+    entries
+}
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn diff_at(path: String, from: &Value, to: &Value, entries: &mut Vec<DiffEntry>) {
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{}/{}", path, escape(key));
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_at(child, x, y, entries),
+                    (Some(x), None) => entries.push(DiffEntry {
+                        path: child,
+                        op: DiffOp::Removed,
+                        from: Some(x.clone()),
+                        to: None,
+                    }),
+                    (None, Some(y)) => entries.push(DiffEntry {
+                        path: child,
+                        op: DiffOp::Added,
+                        from: None,
+                        to: Some(y.clone()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (a, b) if a != b => entries.push(DiffEntry {
+            path,
+            op: DiffOp::Changed,
+            from: Some(a.clone()),
+            to: Some(b.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// Largest content `content_diff` is meant for; callers skip larger blobs.
+pub const MAX_CONTENT_BYTES: u64 = 1 << 20;
+
+/// Largest table the line diff fills in; beyond it the differing middle of the two
+/// texts is reported as removed and added wholesale.
+const MAX_LCS_CELLS: usize = 1 << 22;
+
+/// A line removed from the old text or added in the new one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineChange {
+    pub op: DiffOp,
+    /// 1-based line number, in the old text for removals and in the new one for additions.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Changes between two contents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum ContentDiff {
+    /// Both contents are JSON objects or arrays.
+    Json { changes: Vec<DiffEntry> },
+    /// Both contents are UTF-8 text.
+    Text { added: usize, removed: usize, lines: Vec<LineChange> },
+}
+
+impl ContentDiff {
+    /// Whether the contents are the same.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ContentDiff::Json { changes } => changes.is_empty(),
+            ContentDiff::Text { lines, .. } => lines.is_empty(),
+        }
+    }
+}
+
+/// Changes that turn content `from` into `to`; None unless both are JSON or both text.
+pub fn content_diff(from: &[u8], to: &[u8]) -> Option<ContentDiff> {
+    let structured = |bytes: &[u8]| serde_json::from_slice::<Value>(bytes).ok().filter(|v| v.is_object() || v.is_array());
+    if let (Some(a), Some(b)) = (structured(from), structured(to)) {
+        return Some(ContentDiff::Json { changes: json_diff(&a, &b) });
+    }
+    let text = |bytes| std::str::from_utf8(bytes).ok().filter(|text| !text.contains('\0'));
+    let (a, b) = (text(from)?, text(to)?);
+    let lines = text_diff(a, b);
+    let added = lines.iter().filter(|change| change.op == DiffOp::Added).count();
+    Some(ContentDiff::Text { added, removed: lines.len() - added, lines })
+}
+
+/// Lines removed from `from` and added in `to`, in order, from a longest common
+/// subsequence of their lines.
+pub fn text_diff(from: &str, to: &str) -> Vec<LineChange> {
+    let a: Vec<&str> = from.lines().collect();
+    let b: Vec<&str> = to.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let line = |op, index: usize, text: &str| LineChange { op, line: prefix + index + 1, text: text.to_string() };
+    let mut changes = Vec::new();
+    let (n, m) = (a_mid.len(), b_mid.len());
+    if (n + 1).saturating_mul(m + 1) > MAX_LCS_CELLS {
+        changes.extend(a_mid.iter().enumerate().map(|(i, text)| line(DiffOp::Removed, i, text)));
+        changes.extend(b_mid.iter().enumerate().map(|(j, text)| line(DiffOp::Added, j, text)));
+        return changes;
+    }
+    // lcs[i][j]: length of a longest common subsequence of a_mid[i..] and b_mid[j..]
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if a_mid[i] == b_mid[j] {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a_mid[i] == b_mid[j] {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+            changes.push(line(DiffOp::Removed, i, a_mid[i]));
+            i += 1;
+        } else {
+            changes.push(line(DiffOp::Added, j, b_mid[j]));
+            j += 1;
+        }
+    }
+    changes
+}
@@ -0,0 +1,148 @@
+// Secondary indexes over the event log.
+// One storage tree holds every index; keys are kind || len || value || time || event id, so
+// each index value is a prefix and events under it are ordered by time. Queries scan the
+// most selective index that applies and filter the remaining criteria per event.
+
+use crate::storage::{Batch, Storage};
+use crate::{Event, EventFilter, ProvenanceError, SortOrder};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const INDEX_TREE: &str = "event_index";
+
+const TIME: u8 = b't';
+const ACTOR: u8 = b'a';
+const OPERATION: u8 = b'o';
+const ARTIFACT: u8 = b'r';
+
+/// Length of a position (time || event id) at the end of every key.
+const POSITION_LEN: usize = 8 + 16;
+
+/// Event indexes by time, actor, operation and artifact (input or output).
+#[derive(Clone)]
+pub struct EventIndex {
+    storage: Arc<dyn Storage>,
+}
+
+fn time_key(t: DateTime<Utc>) -> [u8; 8] {
+    // Flip the sign bit so negative timestamps sort before positive ones
+    ((t.timestamp_micros() as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+fn prefix(kind: u8, value: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(3 + value.len() + POSITION_LEN);
+    key.push(kind);
+    if kind != TIME {
+        // A value too long for its length prefix is indexed by its hash, marked by the
+        // greatest length; callers check events against the filter anyway
+        match u16::try_from(value.len()) {
+            Ok(len) if len < u16::MAX => {
+                key.extend_from_slice(&len.to_be_bytes());
+                key.extend_from_slice(value);
+            }
+            _ => {
+                key.extend_from_slice(&u16::MAX.to_be_bytes());
+                key.extend_from_slice(ring::digest::digest(&ring::digest::SHA256, value).as_ref());
+            }
+        }
+    }
+    key
+}
+
+fn prefixes(event: &Event) -> Vec<Vec<u8>> {
+    let mut prefixes = vec![
+        prefix(TIME, &[]),
+        prefix(ACTOR, event.actor.as_bytes()),
+        prefix(OPERATION, event.operation.as_bytes()),
+    ];
+    for id in event.in_artifacts.iter().chain(&event.out_artifacts) {
+        let p = prefix(ARTIFACT, id.as_bytes());
+        if !prefixes.contains(&p) {
+            prefixes.push(p);
+        }
+    }
+    prefixes
+}
+
+/// Position of `event` in every index: its time and ID.
+pub fn position(event: &Event) -> Vec<u8> {
+    let mut pos = time_key(event.timestamp).to_vec();
+    pos.extend_from_slice(event.id.as_bytes());
+    pos
+}
+
+/// Opaque pagination cursor for the position after which a page starts.
+pub fn encode_cursor(position: &[u8]) -> String {
+    hex::encode(position)
+}
+
+/// Parses a cursor produced by `encode_cursor`.
+pub fn decode_cursor(cursor: &str) -> Result<Vec<u8>, ProvenanceError> {
+    match hex::decode(cursor) {
+        Ok(pos) if pos.len() == POSITION_LEN => Ok(pos),
+        _ => Err(ProvenanceError::InvalidCursor),
+    }
+}
+
+impl EventIndex {
+    pub fn open(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Adds the index entries of `event` to `batch`.
+    pub fn stage_event(batch: &mut Batch, event: &Event) {
+        let pos = position(event);
+        for mut key in prefixes(event) {
+            key.extend_from_slice(&pos);
+            batch.insert(INDEX_TREE, key, []);
+        }
+    }
+
+    /// Positions and IDs of candidate events for `filter`, in `order`, starting after
+    /// `cursor`. Only the chosen index and the time range are applied here; callers
+    /// must still check each event against the whole filter.
+    pub fn scan(
+        &self,
+        filter: &EventFilter,
+        order: SortOrder,
+        cursor: Option<&[u8]>,
+    ) -> impl Iterator<Item = Result<(Vec<u8>, Uuid), ProvenanceError>> + '_ {
+        let prefix = if let Some(id) = filter.artifact_id {
+            prefix(ARTIFACT, id.as_bytes())
+        } else if let Some(actor) = &filter.actor {
+            prefix(ACTOR, actor.as_bytes())
+        } else if let Some(operation) = &filter.event_type {
+            prefix(OPERATION, operation.as_bytes())
+        } else {
+            prefix(TIME, &[])
+        };
+
+        let with = |suffix: &[u8]| {
+            let mut key = prefix.clone();
+            key.extend_from_slice(suffix);
+            key
+        };
+        let mut lo = with(&filter.start_time.map(|t| time_key(t).to_vec()).unwrap_or_default());
+        // Keys hold microseconds, so the end bound is the next microsecond, exclusive
+        let mut hi = match filter.end_time.and_then(|t| t.checked_add_signed(Duration::microseconds(1))) {
+            Some(t) => with(&time_key(t)),
+            None => with(&[0xff; POSITION_LEN + 1]),
+        };
+        if let Some(cursor) = cursor {
+            match order {
+                SortOrder::Asc => lo = lo.max(with(&[cursor, &[0]].concat())),
+                SortOrder::Desc => hi = hi.min(with(cursor)),
+            }
+        }
+
+        let prefix_len = prefix.len();
+        let keys = self.storage.range(INDEX_TREE, &lo, Some(&hi), order == SortOrder::Desc);
+        keys.map(move |result| {
+            let (key, _) = result?;
+            let pos = key[prefix_len..].to_vec();
+            let id = Uuid::from_slice(&pos[8..]).map_err(|e| ProvenanceError::DatabaseError(e.to_string()))?;
+            Ok((pos, id))
+        })
+    }
+}
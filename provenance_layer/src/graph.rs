@@ -0,0 +1,342 @@
+// Provenance graph G_P = (A, E_P).
+// Forward and reverse adjacency over artifacts, with the events that created each edge,
+// and the lineage^{-} / lineage^{+} traversals defined in the formal model.
+// `StoredGraph` keeps the adjacency in storage; `ProvenanceGraph` is the in-memory form.
+
+use crate::storage::{Batch, Storage};
+use crate::{Event, LineageDirection, LineageEdge, ProvenanceError};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const CHILDREN_TREE: &str = "graph_children";
+const PARENTS_TREE: &str = "graph_parents";
+
+/// Artifacts and edges reached by a lineage traversal.
+#[derive(Debug, Clone, Default)]
+pub struct Subgraph {
+    pub ancestors: Vec<Uuid>,
+    pub descendants: Vec<Uuid>,
+    pub edges: Vec<LineageEdge>,
+}
+
+/// Read access to adjacency in G_P. Traversals are provided on top of it.
+pub trait GraphView {
+    /// (parent, event) pairs for every edge parent -> `artifact`.
+    fn parent_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError>;
+
+    /// (child, event) pairs for every edge `artifact` -> child.
+    fn child_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError>;
+
+    /// Immediate parents of `artifact`.
+    fn parents(&self, artifact: Uuid) -> Result<Vec<Uuid>, ProvenanceError> {
+        Ok(distinct(self.parent_edges(artifact)?))
+    }
+
+    /// Immediate children of `artifact`.
+    fn children(&self, artifact: Uuid) -> Result<Vec<Uuid>, ProvenanceError> {
+        Ok(distinct(self.child_edges(artifact)?))
+    }
+
+    /// Whether there is a path `from` ->* `to`.
+    fn reaches(&self, from: Uuid, to: Uuid) -> Result<bool, ProvenanceError> {
+        let mut stack = vec![from];
+        let mut seen = HashSet::new();
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return Ok(true);
+            }
+            if seen.insert(node) {
+                stack.extend(self.children(node)?);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether adding `event` would close a cycle; returns the offending edge.
+    fn find_cycle(&self, event: &Event) -> Result<Option<(Uuid, Uuid)>, ProvenanceError> {
+        for &out in &event.out_artifacts {
+            for &inp in &event.in_artifacts {
+                // inp -> out closes a cycle iff out already reaches inp
+                if inp == out || self.reaches(out, inp)? {
+                    return Ok(Some((inp, out)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Edges of a shortest path `from` ->* `to`, in path order; None if there is none.
+    fn path(&self, from: Uuid, to: Uuid) -> Result<Option<Vec<LineageEdge>>, ProvenanceError> {
+        let mut reached_by: HashMap<Uuid, (Uuid, Uuid)> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        let mut seen = HashSet::from([from]);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = Vec::new();
+                let mut child = to;
+                while let Some(&(parent, event_id)) = reached_by.get(&child) {
+                    path.push(LineageEdge { parent, child, event_id, change: None });
+                    child = parent;
+                }
+                path.reverse();
+                return Ok(Some(path));
+            }
+            for (child, event_id) in self.child_edges(node)? {
+                if seen.insert(child) {
+                    reached_by.insert(child, (node, event_id));
+                    queue.push_back(child);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Breadth-first lineage traversal from `artifact`, up to `max_depth` hops.
+    /// Backward collects lineage^{-}(a), forward collects lineage^{+}(a).
+    fn traverse(
+        &self,
+        artifact: Uuid,
+        direction: LineageDirection,
+        max_depth: Option<usize>,
+    ) -> Result<Subgraph, ProvenanceError> {
+        let mut subgraph = Subgraph::default();
+        if direction != LineageDirection::Forward {
+            subgraph.ancestors = walk(self, artifact, max_depth, true, &mut subgraph.edges)?;
+        }
+        if direction != LineageDirection::Backward {
+            subgraph.descendants = walk(self, artifact, max_depth, false, &mut subgraph.edges)?;
+        }
+        Ok(subgraph)
+    }
+}
+
+fn distinct(edges: Vec<(Uuid, Uuid)>) -> Vec<Uuid> {
+    let mut nodes: Vec<Uuid> = Vec::new();
+    for (node, _event) in edges {
+        if !nodes.contains(&node) {
+            nodes.push(node);
+        }
+    }
+    nodes
+}
+
+fn walk<G: GraphView + ?Sized>(
+    g: &G,
+    start: Uuid,
+    max_depth: Option<usize>,
+    backward: bool,
+    edges: &mut Vec<LineageEdge>,
+) -> Result<Vec<Uuid>, ProvenanceError> {
+    let mut reached = Vec::new();
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([(start, 0usize)]);
+    while let Some((node, depth)) = queue.pop_front() {
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        let next = if backward { g.parent_edges(node)? } else { g.child_edges(node)? };
+        for (other, event_id) in next {
+            let (parent, child) = if backward { (other, node) } else { (node, other) };
+            edges.push(LineageEdge { parent, child, event_id, change: None });
+            if seen.insert(other) {
+                reached.push(other);
+                queue.push_back((other, depth + 1));
+            }
+        }
+    }
+    Ok(reached)
+}
+
+/// In-memory provenance graph, built by replaying events.
+#[derive(Debug, Clone, Default)]
+pub struct ProvenanceGraph {
+    children: HashMap<Uuid, Vec<(Uuid, Uuid)>>,
+    parents: HashMap<Uuid, Vec<(Uuid, Uuid)>>,
+}
+
+impl ProvenanceGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the derivation edges in -> out of `event`.
+    pub fn add_event(&mut self, event: &Event) {
+        for &out in &event.out_artifacts {
+            for &inp in &event.in_artifacts {
+                let children = self.children.entry(inp).or_default();
+                if !children.contains(&(out, event.id)) {
+                    children.push((out, event.id));
+                    self.parents.entry(out).or_default().push((inp, event.id));
+                }
+            }
+        }
+    }
+}
+
+impl GraphView for ProvenanceGraph {
+    fn parent_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        Ok(self.parents.get(&artifact).cloned().unwrap_or_default())
+    }
+
+    fn child_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        Ok(self.children.get(&artifact).cloned().unwrap_or_default())
+    }
+}
+
+/// A graph restricted to the edges created by events that `keep` accepts.
+pub struct FilteredGraph<'a, G: ?Sized, F> {
+    graph: &'a G,
+    keep: F,
+}
+
+impl<'a, G: GraphView + ?Sized, F: Fn(Uuid) -> Result<bool, ProvenanceError>> FilteredGraph<'a, G, F> {
+    pub fn new(graph: &'a G, keep: F) -> Self {
+        Self { graph, keep }
+    }
+
+    fn filter(&self, edges: Vec<(Uuid, Uuid)>) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        let mut kept = Vec::new();
+        for (node, event_id) in edges {
+            if (self.keep)(event_id)? {
+                kept.push((node, event_id));
+            }
+        }
+        Ok(kept)
+    }
+}
+
+impl<G: GraphView + ?Sized, F: Fn(Uuid) -> Result<bool, ProvenanceError>> GraphView for FilteredGraph<'_, G, F> {
+    fn parent_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        self.filter(self.graph.parent_edges(artifact)?)
+    }
+
+    fn child_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        self.filter(self.graph.child_edges(artifact)?)
+    }
+}
+
+/// The edges of `base` together with those of `extra`, such as events staged for a
+/// batch that is not committed yet.
+pub struct LayeredGraph<'a, B: ?Sized, E: ?Sized> {
+    base: &'a B,
+    extra: &'a E,
+}
+
+impl<'a, B: GraphView + ?Sized, E: GraphView + ?Sized> LayeredGraph<'a, B, E> {
+    pub fn new(base: &'a B, extra: &'a E) -> Self {
+        Self { base, extra }
+    }
+}
+
+impl<B: GraphView + ?Sized, E: GraphView + ?Sized> GraphView for LayeredGraph<'_, B, E> {
+    fn parent_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        let mut edges = self.base.parent_edges(artifact)?;
+        edges.extend(self.extra.parent_edges(artifact)?);
+        Ok(edges)
+    }
+
+    fn child_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        let mut edges = self.base.child_edges(artifact)?;
+        edges.extend(self.extra.child_edges(artifact)?);
+        Ok(edges)
+    }
+}
+
+/// Adjacency lists keyed by (backward, artifact). `generation` changes on every
+/// invalidation so a scan racing with a write never caches stale edges.
+#[derive(Default)]
+struct AdjacencyCache {
+    generation: u64,
+    entries: HashMap<(bool, Uuid), Vec<(Uuid, Uuid)>>,
+}
+
+/// Provenance graph kept in storage.
+/// `graph_children` holds parent || child || event keys and `graph_parents` holds
+/// child || parent || event keys, so adjacency in either direction is a prefix scan.
+#[derive(Clone)]
+pub struct StoredGraph {
+    storage: Arc<dyn Storage>,
+    cache: Option<Arc<Mutex<AdjacencyCache>>>,
+    cache_capacity: usize,
+}
+
+impl StoredGraph {
+    /// Opens the graph. A `cache_capacity` of 0 disables the adjacency cache.
+    pub fn open(storage: Arc<dyn Storage>, cache_capacity: usize) -> Self {
+        Self {
+            storage,
+            cache: (cache_capacity > 0).then(|| Arc::new(Mutex::new(AdjacencyCache::default()))),
+            cache_capacity,
+        }
+    }
+
+    /// Adds the edges of `event` to `batch`.
+    pub fn stage_event(batch: &mut Batch, event: &Event) {
+        for &out in &event.out_artifacts {
+            for &inp in &event.in_artifacts {
+                batch.insert(CHILDREN_TREE, edge_key(inp, out, event.id), []);
+                batch.insert(PARENTS_TREE, edge_key(out, inp, event.id), []);
+            }
+        }
+    }
+
+    /// Drops cached adjacency touched by `event`; call after its batch commits.
+    pub fn invalidate(&self, event: &Event) {
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().unwrap();
+            cache.generation += 1;
+            for &id in event.in_artifacts.iter().chain(&event.out_artifacts) {
+                cache.entries.remove(&(false, id));
+                cache.entries.remove(&(true, id));
+            }
+        }
+    }
+
+    fn scan(&self, artifact: Uuid, backward: bool) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        let mut generation = 0;
+        if let Some(cache) = &self.cache {
+            let cache = cache.lock().unwrap();
+            if let Some(edges) = cache.entries.get(&(backward, artifact)) {
+                return Ok(edges.clone());
+            }
+            generation = cache.generation;
+        }
+        let tree = if backward { PARENTS_TREE } else { CHILDREN_TREE };
+        let mut edges = Vec::new();
+        for entry in self.storage.scan_prefix(tree, artifact.as_bytes()) {
+            let (key, _) = entry?;
+            let other = Uuid::from_slice(&key[16..32]).map_err(|e| ProvenanceError::DatabaseError(e.to_string()))?;
+            let event = Uuid::from_slice(&key[32..48]).map_err(|e| ProvenanceError::DatabaseError(e.to_string()))?;
+            edges.push((other, event));
+        }
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().unwrap();
+            if cache.generation == generation {
+                if cache.entries.len() >= self.cache_capacity {
+                    cache.entries.clear();
+                }
+                cache.entries.insert((backward, artifact), edges.clone());
+            }
+        }
+        Ok(edges)
+    }
+}
+
+impl GraphView for StoredGraph {
+    fn parent_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        self.scan(artifact, true)
+    }
+
+    fn child_edges(&self, artifact: Uuid) -> Result<Vec<(Uuid, Uuid)>, ProvenanceError> {
+        self.scan(artifact, false)
+    }
+}
+
+fn edge_key(from: Uuid, to: Uuid, event: Uuid) -> Vec<u8> {
+    let mut key = Vec::with_capacity(48);
+    key.extend_from_slice(from.as_bytes());
+    key.extend_from_slice(to.as_bytes());
+    key.extend_from_slice(event.as_bytes());
+    key
+}
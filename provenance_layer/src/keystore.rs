@@ -0,0 +1,387 @@
+// Keystore for actor signing keys.
+// Holds the current Ed25519 keypair of each actor, stored as a PKCS#8 document in the
+// `keys` tree, and the history of every public key the actor has held, so that a
+// signature is checked against the key that was valid when it was made.
+// Corresponds to Sig / Ver in the formal model.
+
+use crate::storage::{Batch, Storage};
+use crate::{ProvenanceError, Signature, RESERVED_ACTORS};
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const KEYS_TREE: &str = "keys";
+/// Actor || 0x00 || version (big-endian) -> KeyRecord.
+const KEY_HISTORY_TREE: &str = "key_history";
+
+/// Algorithm identifier recorded on every signature produced by the keystore.
+pub const SIGNATURE_ALGORITHM: &str = "Ed25519";
+
+/// One key an actor has held, and when it was valid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRecord {
+    pub actor: String,
+    /// 0 for the actor's first key, one more for each later registration or rotation.
+    pub version: u32,
+    pub algorithm: String,
+    /// Hex raw public key.
+    pub public_key: String,
+    pub valid_from: DateTime<Utc>,
+    /// When a rotation or revocation ended the key's validity; None while current.
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+    /// Set if the key was revoked rather than rotated out.
+    #[serde(default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revocation_reason: Option<String>,
+}
+
+/// A key change and the event recording it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyChange {
+    pub key: KeyRecord,
+    pub event: crate::Event,
+}
+
+/// Result of checking a signature against the signer's key history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureVerification {
+    pub signer: String,
+    pub valid: bool,
+    /// Version of the signer's key in effect at the signing time, if any.
+    pub key_version: Option<u32>,
+    /// Whether the signature is dated at or after the revocation of that key.
+    pub revoked: bool,
+    pub reason: Option<String>,
+}
+
+/// Per-actor Ed25519 keystore backed by a storage tree (actor -> PKCS#8 bytes).
+#[derive(Clone)]
+pub struct Keystore {
+    storage: Arc<dyn Storage>,
+}
+
+impl Keystore {
+    /// Opens the keystore inside the given storage.
+    pub fn open(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Generates a fresh keypair for `actor`. Fails if the actor already has a key.
+    /// Returns the public key.
+    pub fn generate(&self, actor: &str) -> Result<Vec<u8>, ProvenanceError> {
+        self.import(actor, &generate_pkcs8()?)
+    }
+
+    /// Imports an existing PKCS#8-encoded Ed25519 keypair for `actor`, valid from now.
+    /// Fails if the actor already has a key; an actor whose key was revoked may register
+    /// a new one. Returns the public key.
+    pub fn import(&self, actor: &str, pkcs8: &[u8]) -> Result<Vec<u8>, ProvenanceError> {
+        self.register(actor, pkcs8, Utc::now())?;
+        public_key_of(pkcs8)
+    }
+
+    /// Registers `pkcs8` as the key of `actor`, who must have none, valid from `at`.
+    pub fn register(&self, actor: &str, pkcs8: &[u8], at: DateTime<Utc>) -> Result<KeyRecord, ProvenanceError> {
+        let mut batch = Batch::new();
+        let record = self.stage_register(&mut batch, actor, pkcs8, at)?;
+        if !self.storage.commit(batch)? {
+            return Err(ProvenanceError::KeyExists(actor.to_string()));
+        }
+        self.storage.flush()?;
+        Ok(record)
+    }
+
+    /// Stages `register`, so that the key commits with the writes in `batch`. The batch
+    /// fails if another key is registered for `actor` first.
+    pub fn stage_register(
+        &self,
+        batch: &mut Batch,
+        actor: &str,
+        pkcs8: &[u8],
+        at: DateTime<Utc>,
+    ) -> Result<KeyRecord, ProvenanceError> {
+        if self.storage.contains(KEYS_TREE, actor.as_bytes())? {
+            return Err(ProvenanceError::KeyExists(actor.to_string()));
+        }
+        let history = self.history(actor)?;
+        let record = new_record(actor, history.len() as u32, pkcs8, at)?;
+        batch.expect(KEYS_TREE, actor, None);
+        batch.insert(KEYS_TREE, actor, pkcs8);
+        stage_record(batch, &record, true)?;
+        Ok(record)
+    }
+
+    /// Stages replacing the key of `actor` with `pkcs8` at `at`. The old key stays valid
+    /// for signatures dated before `at`. The batch fails if the key changes first.
+    /// Returns the new key's record.
+    pub fn stage_rotate(
+        &self,
+        batch: &mut Batch,
+        actor: &str,
+        pkcs8: &[u8],
+        at: DateTime<Utc>,
+    ) -> Result<KeyRecord, ProvenanceError> {
+        let (current, mut history) = self.current(actor)?;
+        let mut previous = history.pop().ok_or_else(|| ProvenanceError::KeyNotFound(actor.to_string()))?;
+        previous.valid_until = Some(at);
+        let record = new_record(actor, previous.version + 1, pkcs8, at)?;
+        batch.expect(KEYS_TREE, actor, Some(&current));
+        batch.insert(KEYS_TREE, actor, pkcs8);
+        stage_record(batch, &previous, false)?;
+        stage_record(batch, &record, true)?;
+        Ok(record)
+    }
+
+    /// Stages revoking the key of `actor` at `at`: signatures dated from then on are
+    /// rejected, and the actor cannot sign until a new key is registered. The batch fails
+    /// if the key changes first. Returns the revoked key's record.
+    pub fn stage_revoke(
+        &self,
+        batch: &mut Batch,
+        actor: &str,
+        at: DateTime<Utc>,
+        reason: Option<String>,
+    ) -> Result<KeyRecord, ProvenanceError> {
+        let (current, mut history) = self.current(actor)?;
+        let mut record = history.pop().ok_or_else(|| ProvenanceError::KeyNotFound(actor.to_string()))?;
+        record.valid_until = Some(at);
+        record.revoked_at = Some(at);
+        record.revocation_reason = reason;
+        batch.expect(KEYS_TREE, actor, Some(&current));
+        batch.remove(KEYS_TREE, actor);
+        stage_record(batch, &record, false)?;
+        Ok(record)
+    }
+
+    /// Every key `actor` has held, oldest first. A key registered before key history
+    /// was kept is reported as version 0, valid since the epoch.
+    pub fn history(&self, actor: &str) -> Result<Vec<KeyRecord>, ProvenanceError> {
+        let mut history = Vec::new();
+        for entry in self.storage.scan_prefix(KEY_HISTORY_TREE, &history_prefix(actor)) {
+            let (_key, value) = entry?;
+            history.push(serde_json::from_slice(&value)?);
+        }
+        if history.is_empty() {
+            if let Some(pkcs8) = self.storage.get(KEYS_TREE, actor.as_bytes())? {
+                history.push(new_record(actor, 0, &pkcs8, DateTime::UNIX_EPOCH)?);
+            }
+        }
+        Ok(history)
+    }
+
+    /// Stages `history`, the public keys `actor` held in another log, so that signatures
+    /// made there verify here. Private keys never travel. If the actor already has keys
+    /// here, nothing is staged and the history must name only those keys; returns false
+    /// if it does not.
+    pub fn stage_history(&self, batch: &mut Batch, actor: &str, history: &[KeyRecord]) -> Result<bool, ProvenanceError> {
+        let local = self.history(actor)?;
+        if local.is_empty() {
+            for record in history {
+                stage_record(batch, record, true)?;
+            }
+            return Ok(true);
+        }
+        Ok(history.iter().all(|record| {
+            local.get(record.version as usize).is_some_and(|key| {
+                key.actor == record.actor && key.public_key == record.public_key && key.valid_from == record.valid_from
+            })
+        }))
+    }
+
+    /// The current PKCS#8 document of `actor` and the actor's key history.
+    fn current(&self, actor: &str) -> Result<(Vec<u8>, Vec<KeyRecord>), ProvenanceError> {
+        let current = self
+            .storage
+            .get(KEYS_TREE, actor.as_bytes())?
+            .ok_or_else(|| ProvenanceError::KeyNotFound(actor.to_string()))?;
+        Ok((current, self.history(actor)?))
+    }
+
+    /// Loads the keypair registered for `actor`, if any.
+    pub fn load(&self, actor: &str) -> Result<Option<Ed25519KeyPair>, ProvenanceError> {
+        match self.storage.get(KEYS_TREE, actor.as_bytes())? {
+            Some(pkcs8) => Ed25519KeyPair::from_pkcs8(&pkcs8)
+                .map(Some)
+                .map_err(|e| ProvenanceError::KeyError(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Loads the keypair for `actor`, generating one on first use. An actor whose key
+    /// was revoked, or who is known only from keys imported with a bundle, gets no new
+    /// key until one is registered, and RESERVED_ACTORS only get the keys provisioned
+    /// for them.
+    pub fn load_or_generate(&self, actor: &str) -> Result<Ed25519KeyPair, ProvenanceError> {
+        if let Some(key_pair) = self.load(actor)? {
+            return Ok(key_pair);
+        }
+        match self.history(actor)?.pop() {
+            Some(record) if record.revoked_at.is_some() => return Err(ProvenanceError::KeyRevoked(actor.to_string())),
+            Some(_) => return Err(ProvenanceError::KeyNotFound(actor.to_string())),
+            None if RESERVED_ACTORS.contains(&actor) => return Err(ProvenanceError::KeyNotFound(actor.to_string())),
+            None => {}
+        }
+        self.provision(actor)
+    }
+
+    /// Loads the keypair for `actor`, generating one if the actor has none. This is how
+    /// the keys of RESERVED_ACTORS are created.
+    pub fn provision(&self, actor: &str) -> Result<Ed25519KeyPair, ProvenanceError> {
+        if let Some(key_pair) = self.load(actor)? {
+            return Ok(key_pair);
+        }
+        // Another writer may have won the race; either way a key now exists.
+        match self.generate(actor) {
+            Ok(_) | Err(ProvenanceError::KeyExists(_)) => {}
+            Err(e) => return Err(e),
+        }
+        self.load(actor)?
+            .ok_or_else(|| ProvenanceError::KeyNotFound(actor.to_string()))
+    }
+
+    /// Makes sure `actor` has a key, generating one on first use. A generated key is
+    /// valid from now, so a signing time must be taken after this.
+    pub fn ensure_key(&self, actor: &str) -> Result<(), ProvenanceError> {
+        self.load_or_generate(actor).map(drop)
+    }
+
+    /// Returns the public key registered for `actor`, if any.
+    pub fn public_key(&self, actor: &str) -> Result<Option<Vec<u8>>, ProvenanceError> {
+        Ok(self.load(actor)?.map(|kp| kp.public_key().as_ref().to_vec()))
+    }
+
+    /// Signs `data` with the key of `actor`, generating the key on first use. Use
+    /// `ensure_key` first when `data` carries a signing time.
+    pub fn sign(&self, actor: &str, data: &[u8]) -> Result<Signature, ProvenanceError> {
+        Ok(signature(actor, &self.load_or_generate(actor)?, data))
+    }
+
+    /// Verifies `signature` over `data` against the signer's key that was valid at
+    /// `signed_at`.
+    pub fn verify_at(
+        &self,
+        data: &[u8],
+        signature: &Signature,
+        signed_at: DateTime<Utc>,
+    ) -> Result<SignatureVerification, ProvenanceError> {
+        let history = self.history(&signature.signer)?;
+        Ok(verify_with_history(&history, data, signature, signed_at))
+    }
+}
+
+/// Generates a fresh PKCS#8-encoded Ed25519 keypair.
+pub fn generate_pkcs8() -> Result<Vec<u8>, ProvenanceError> {
+    let rng = SystemRandom::new();
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)
+        .map_err(|_| ProvenanceError::KeyError("key generation failed".to_string()))?;
+    Ok(pkcs8.as_ref().to_vec())
+}
+
+/// Signs `data` as `actor` with the PKCS#8-encoded keypair `pkcs8`, which need not be
+/// stored yet.
+pub fn sign_with(actor: &str, pkcs8: &[u8], data: &[u8]) -> Result<Signature, ProvenanceError> {
+    let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|e| ProvenanceError::KeyError(e.to_string()))?;
+    Ok(signature(actor, &key_pair, data))
+}
+
+fn signature(actor: &str, key_pair: &Ed25519KeyPair, data: &[u8]) -> Signature {
+    Signature {
+        signer: actor.to_string(),
+        signature: key_pair.sign(data).as_ref().to_vec(),
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+    }
+}
+
+/// Raw public key of a PKCS#8-encoded Ed25519 keypair.
+pub fn public_key_of(pkcs8: &[u8]) -> Result<Vec<u8>, ProvenanceError> {
+    let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|e| ProvenanceError::KeyError(e.to_string()))?;
+    Ok(key_pair.public_key().as_ref().to_vec())
+}
+
+fn new_record(actor: &str, version: u32, pkcs8: &[u8], at: DateTime<Utc>) -> Result<KeyRecord, ProvenanceError> {
+    Ok(KeyRecord {
+        actor: actor.to_string(),
+        version,
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+        public_key: hex::encode(public_key_of(pkcs8)?),
+        valid_from: at,
+        valid_until: None,
+        revoked_at: None,
+        revocation_reason: None,
+    })
+}
+
+fn history_prefix(actor: &str) -> Vec<u8> {
+    [actor.as_bytes(), &[0]].concat()
+}
+
+/// Stages `record` in its history slot; a `new` record must not replace another.
+fn stage_record(batch: &mut Batch, record: &KeyRecord, new: bool) -> Result<(), ProvenanceError> {
+    let key = [history_prefix(&record.actor), record.version.to_be_bytes().to_vec()].concat();
+    if new {
+        batch.expect(KEY_HISTORY_TREE, &key, None);
+    }
+    batch.insert(KEY_HISTORY_TREE, key, serde_json::to_vec(record)?);
+    Ok(())
+}
+
+/// The key in `history` in effect at `at`, whether or not it was still valid then.
+/// None if `at` is before the first key, so nothing can be backdated to before the
+/// signer had a key.
+pub fn key_at(history: &[KeyRecord], at: DateTime<Utc>) -> Option<&KeyRecord> {
+    history.iter().rev().find(|record| record.valid_from <= at)
+}
+
+/// Verifies `signature` over `data` against the key in `history` that was valid at
+/// `signed_at`. Usable offline, given a signer's key history.
+pub fn verify_with_history(
+    history: &[KeyRecord],
+    data: &[u8],
+    signature: &Signature,
+    signed_at: DateTime<Utc>,
+) -> SignatureVerification {
+    let mut verification = SignatureVerification {
+        signer: signature.signer.clone(),
+        valid: false,
+        key_version: None,
+        revoked: false,
+        reason: None,
+    };
+    let problem = match key_at(history, signed_at) {
+        None if history.is_empty() => Some("no key registered for signer"),
+        None => Some("signed before the signer had a key"),
+        Some(record) => {
+            verification.key_version = Some(record.version);
+            let ended = record.valid_until.is_some_and(|until| signed_at >= until);
+            if record.revoked_at.is_some_and(|revoked| signed_at >= revoked) {
+                verification.revoked = true;
+                Some("signed after the key was revoked")
+            } else if ended {
+                Some("signed after the key was rotated out")
+            } else {
+                match hex::decode(&record.public_key) {
+                    Ok(key) if verify_with_key(&key, data, signature) => None,
+                    _ => Some("signature does not verify"),
+                }
+            }
+        }
+    };
+    verification.valid = problem.is_none();
+    verification.reason = problem.map(str::to_string);
+    verification
+}
+
+/// Verifies an Ed25519 `signature` over `data` against a raw public key.
+/// Usable offline, without access to the keystore.
+pub fn verify_with_key(public_key: &[u8], data: &[u8], signature: &Signature) -> bool {
+    if signature.algorithm != SIGNATURE_ALGORITHM {
+        return false;
+    }
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(data, &signature.signature)
+        .is_ok()
+}
@@ -6,7 +6,21 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use std::collections::HashMap;
+
+pub mod anchor;
+pub mod audit;
+pub mod blobstore;
+pub mod bundle;
+pub mod canonical;
+pub mod diff;
+pub mod event_index;
+pub mod graph;
+pub mod keystore;
+pub mod merkle;
+pub mod prov;
+pub mod provenance_impl;
+pub mod storage;
+pub mod subscription;
 
 /// Represents an event in the append-only log.
 /// Corresponds to e = (id_e, t_e, actor_e, in_e, op_e, out_e, ctx_e, sig_e)
@@ -20,10 +34,55 @@ pub struct Event {
     pub out_artifacts: Vec<Uuid>,
     pub context: serde_json::Value,
     pub signature: Option<Signature>,
+    /// Position in the log: gap-free and strictly increasing from 0.
+    /// None for events logged before sequence numbers were assigned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    /// Where the event was logged before it was imported from another log; its
+    /// signature covers the sequence number it had there. None for events logged here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<EventOrigin>,
+}
+
+/// Position of an imported event in the log that signed it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventOrigin {
+    /// Sequence number there; None if that log predates sequence numbers.
+    pub seq: Option<u64>,
+    /// Hash of the block that sealed the event there, if it was sealed at export.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+}
+
+impl Event {
+    /// Digest that the actor signs: H(actor, in, op, out, ctx, id, t).
+    /// Corresponds to sig_e = Sig(actor, H(payload || id_e || t_e))
+    /// The payload is the canonical JSON (RFC 8785) of these fields, keyed by name,
+    /// plus `seq` for events that have a sequence number; for an imported event, the
+    /// one it had in the log that signed it.
+    pub fn signing_payload(&self) -> Result<Vec<u8>, ProvenanceError> {
+        let mut payload = serde_json::json!({
+            "actor": self.actor,
+            "in_artifacts": self.in_artifacts,
+            "operation": self.operation,
+            "out_artifacts": self.out_artifacts,
+            "context": self.context,
+            "id": self.id,
+            "timestamp": self.timestamp,
+        });
+        let seq = match &self.origin {
+            Some(origin) => origin.seq,
+            None => self.seq,
+        };
+        if let Some(seq) = seq {
+            payload["seq"] = seq.into();
+        }
+        canonical::digest(&payload)
+    }
 }
 
 /// Cryptographic signature for events and artifacts.
-/// Uses ring for ECDSA or similar.
+/// Ed25519 via ring; see `keystore`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Signature {
     pub signer: String,
@@ -40,7 +99,161 @@ pub struct Artifact {
     pub version: String,
     pub content_hash: String,
     pub metadata: serde_json::Value,
+    /// Hex SHA-256 of the canonical JSON of `metadata`, computed on registration.
+    #[serde(default)]
+    pub metadata_digest: String,
     pub registered_at: DateTime<Utc>,
+    /// Identity shared by all versions of a work: the ID of its first version.
+    /// Assigned on registration; None for artifacts registered before versioning.
+    #[serde(default)]
+    pub family_id: Option<Uuid>,
+    /// Version this one supersedes, if any.
+    #[serde(default)]
+    pub previous_version: Option<Uuid>,
+    /// Authorship chain chain(A) = [sig_author, sig_editor1, ..., sig_system], in signing order.
+    /// Appended to only through co-signing.
+    #[serde(default)]
+    pub authorship: Vec<AuthorshipEntry>,
+}
+
+impl Artifact {
+    /// Canonical digest of the artifact metadata (meta_a).
+    pub fn compute_metadata_digest(&self) -> Result<String, ProvenanceError> {
+        canonical::digest_hex(&self.metadata)
+    }
+
+    /// The record as it stood at `at`: None if it was registered later, otherwise with
+    /// only the authorship entries signed by then.
+    pub fn as_of(mut self, at: DateTime<Utc>) -> Option<Artifact> {
+        if self.registered_at > at {
+            return None;
+        }
+        let signed = self.authorship.iter().take_while(|entry| entry.signed_at <= at).count();
+        self.authorship.truncate(signed);
+        Some(self)
+    }
+
+    /// Hash the next authorship entry must link to.
+    pub fn authorship_head(&self) -> Result<String, ProvenanceError> {
+        match self.authorship.last() {
+            Some(entry) => entry.digest(),
+            None => Ok(GENESIS_HASH.to_string()),
+        }
+    }
+
+    /// Checks the authorship chain, each entry's link and its signature against the
+    /// signer's key valid at `signed_at` in the history returned by `key_history`, and
+    /// that every `required` actor has validly signed.
+    pub fn verify_authorship_with(
+        &self,
+        required: Vec<String>,
+        mut key_history: impl FnMut(&str) -> Result<Vec<keystore::KeyRecord>, ProvenanceError>,
+    ) -> Result<AuthorshipVerification, ProvenanceError> {
+        let mut signers = Vec::new();
+        let mut first_invalid = None;
+        let mut reason = None;
+        let mut previous = GENESIS_HASH.to_string();
+        for (i, entry) in self.authorship.iter().enumerate() {
+            let problem = if entry.previous != previous {
+                Some("entry does not link to the previous entry".to_string())
+            } else if entry.signature.signer != entry.signer {
+                Some("signature is by a different actor".to_string())
+            } else {
+                let payload = AuthorshipEntry::signing_payload(
+                    self,
+                    &entry.previous,
+                    &entry.signer,
+                    entry.role.as_deref(),
+                    entry.signed_at,
+                )?;
+                let history = key_history(&entry.signer)?;
+                keystore::verify_with_history(&history, &payload, &entry.signature, entry.signed_at).reason
+            };
+            if let Some(problem) = problem {
+                first_invalid = Some(i);
+                reason = Some(problem);
+                break;
+            }
+            signers.push(entry.signer.clone());
+            previous = entry.digest()?;
+        }
+
+        let missing: Vec<String> = required.into_iter().filter(|r| !signers.contains(r)).collect();
+        let valid = first_invalid.is_none();
+        Ok(AuthorshipVerification {
+            artifact_id: self.id,
+            valid,
+            signers,
+            first_invalid,
+            reason,
+            complete: valid && missing.is_empty(),
+            missing,
+        })
+    }
+}
+
+/// One signature in an artifact's authorship chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorshipEntry {
+    pub signer: String,
+    /// Free-form role of the signer, e.g. "author" or "editor".
+    #[serde(default)]
+    pub role: Option<String>,
+    pub signed_at: DateTime<Utc>,
+    /// Digest of the previous entry, or GENESIS_HASH for the first.
+    pub previous: String,
+    pub signature: Signature,
+}
+
+impl AuthorshipEntry {
+    /// Digest that the signer signs: H(artifact, a_h, previous, signer, role, t).
+    pub fn signing_payload(
+        artifact: &Artifact,
+        previous: &str,
+        signer: &str,
+        role: Option<&str>,
+        signed_at: DateTime<Utc>,
+    ) -> Result<Vec<u8>, ProvenanceError> {
+        canonical::digest(&serde_json::json!({
+            "artifact_id": artifact.id,
+            "content_hash": artifact.content_hash,
+            "previous": previous,
+            "signer": signer,
+            "role": role,
+            "signed_at": signed_at,
+        }))
+    }
+
+    /// Hex digest of the whole entry, which the next entry links to.
+    pub fn digest(&self) -> Result<String, ProvenanceError> {
+        canonical::digest_hex(self)
+    }
+}
+
+/// Result of checking an artifact's authorship chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorshipVerification {
+    pub artifact_id: Uuid,
+    pub valid: bool,
+    /// Signers whose entries verified, in chain order, up to the first bad entry.
+    pub signers: Vec<String>,
+    /// Index of the first entry that failed to verify, if any.
+    pub first_invalid: Option<usize>,
+    pub reason: Option<String>,
+    /// Required signers that have not (validly) signed.
+    pub missing: Vec<String>,
+    /// Whether the chain is valid and every required signer has signed.
+    pub complete: bool,
+}
+
+/// Metadata changes between two artifact versions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataDiff {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub from_version: String,
+    pub to_version: String,
+    pub changes: Vec<diff::DiffEntry>,
 }
 
 /// Versioned lineage tracking for artifacts.
@@ -50,7 +263,208 @@ pub struct Lineage {
     pub artifact_id: Uuid,
     pub parent_ids: Vec<Uuid>,
     pub child_ids: Vec<Uuid>,
+    /// Summaries of how the artifact was derived from its parents, one per line.
     pub changes: String,
+    /// lineage^{-}(a): every artifact with a path to `artifact_id`, within the depth limit.
+    #[serde(default)]
+    pub ancestors: Vec<Uuid>,
+    /// lineage^{+}(a): every artifact reachable from `artifact_id`, within the depth limit.
+    #[serde(default)]
+    pub descendants: Vec<Uuid>,
+    /// Derivation edges of the traversed subgraph.
+    #[serde(default)]
+    pub edges: Vec<LineageEdge>,
+    /// Events connecting the artifacts of the subgraph.
+    #[serde(default)]
+    pub events: Vec<Event>,
+    /// Sealed state the lineage was reconstructed from, for `as_of` queries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<Snapshot>,
+}
+
+/// A derivation edge parent -> child in G_P and the event that created it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageEdge {
+    pub parent: Uuid,
+    pub child: Uuid,
+    pub event_id: Uuid,
+    /// What the edge changed; described by lineage queries and diffs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<ChangeDescription>,
+}
+
+/// What a derivation changed, from the event that made it and the two artifacts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeDescription {
+    pub operation: String,
+    pub actor: String,
+    /// Context of the event: parameters, tool, prompt and the like.
+    pub context: serde_json::Value,
+    /// Metadata changes from parent to child.
+    pub metadata: Vec<diff::DiffEntry>,
+    /// Content changes, when both contents are stored and are text or JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<diff::ContentDiff>,
+    /// One line for people, e.g. `summarize by alice using gpt-4; content: +3 -1 lines`.
+    pub summary: String,
+}
+
+/// Context members named in a change summary, with the text that introduces them.
+const SUMMARY_CONTEXT: [(&str, &str); 4] =
+    [("tool", "using"), ("model", "using"), ("prompt", "with prompt"), ("parameters", "with")];
+
+/// Longest context value quoted in a change summary, in characters.
+const SUMMARY_VALUE_CHARS: usize = 60;
+
+impl ChangeDescription {
+    /// Describes `event` deriving `child` from `parent`, given their content diff.
+    pub fn new(event: &Event, parent: &Artifact, child: &Artifact, content: Option<diff::ContentDiff>) -> Self {
+        let metadata = diff::json_diff(&parent.metadata, &child.metadata);
+        let mut summary = format!("{} by {}", event.operation, event.actor);
+        let mut described = Vec::new();
+        for (key, intro) in SUMMARY_CONTEXT {
+            let value = match event.context.get(key) {
+                Some(serde_json::Value::String(text)) => text.clone(),
+                Some(serde_json::Value::Object(members)) => members
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => continue,
+            };
+            // `tool` and `model` both read "using"; name only the first
+            if described.contains(&intro) {
+                continue;
+            }
+            described.push(intro);
+            let mut quoted: String = value.chars().take(SUMMARY_VALUE_CHARS).collect();
+            if value.chars().count() > SUMMARY_VALUE_CHARS {
+                quoted.push('…');
+            }
+            summary.push_str(&format!(" {} {}", intro, quoted));
+        }
+        if !metadata.is_empty() {
+            let paths: Vec<&str> = metadata.iter().map(|entry| entry.path.as_str()).collect();
+            summary.push_str(&format!("; metadata: {}", paths.join(", ")));
+        }
+        match &content {
+            Some(content) if content.is_empty() => summary.push_str("; content unchanged"),
+            Some(diff::ContentDiff::Text { added, removed, .. }) => {
+                summary.push_str(&format!("; content: +{} -{} lines", added, removed))
+            }
+            Some(diff::ContentDiff::Json { changes }) => {
+                let paths: Vec<&str> = changes.iter().map(|entry| entry.path.as_str()).collect();
+                summary.push_str(&format!("; content: {}", paths.join(", ")))
+            }
+            None => {}
+        }
+        Self {
+            operation: event.operation.clone(),
+            actor: event.actor.clone(),
+            context: event.context.clone(),
+            metadata,
+            content,
+            summary,
+        }
+    }
+}
+
+/// Differences between two artifacts on a lineage path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactDiff {
+    pub from: Uuid,
+    pub to: Uuid,
+    /// Edges of a shortest derivation path between the two, from ancestor to
+    /// descendant, each with its change.
+    pub path: Vec<LineageEdge>,
+    /// Metadata changes from `from` to `to`.
+    pub metadata: Vec<diff::DiffEntry>,
+    /// Content changes from `from` to `to`, when both are stored and are text or JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<diff::ContentDiff>,
+}
+
+/// Direction of a lineage traversal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineageDirection {
+    /// lineage^{-}: trace back to origins.
+    Backward,
+    /// lineage^{+}: trace forward to derivatives.
+    Forward,
+    #[default]
+    Both,
+}
+
+/// Parameters of a lineage query.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LineageQuery {
+    #[serde(default)]
+    pub direction: LineageDirection,
+    /// Maximum number of hops from the artifact; unbounded when absent.
+    pub max_depth: Option<usize>,
+    /// Reconstruct the lineage from the sealed blocks up to this point.
+    #[serde(default)]
+    pub as_of: Option<AsOf>,
+}
+
+/// Point in the chain to answer a time-travel query at: a block height, or a time,
+/// standing for the last block sealed by then. Written as the height or an RFC 3339
+/// timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AsOf {
+    Height(u64),
+    Time(DateTime<Utc>),
+}
+
+impl std::fmt::Display for AsOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsOf::Height(height) => write!(f, "{}", height),
+            AsOf::Time(time) => write!(f, "{}", time.to_rfc3339()),
+        }
+    }
+}
+
+impl std::str::FromStr for AsOf {
+    type Err = ProvenanceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(height) = s.parse() {
+            return Ok(AsOf::Height(height));
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|time| AsOf::Time(time.with_timezone(&Utc)))
+            .map_err(|_| ProvenanceError::InvalidAsOf(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for AsOf {
+    type Error = ProvenanceError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<AsOf> for String {
+    fn from(as_of: AsOf) -> Self {
+        as_of.to_string()
+    }
+}
+
+/// Sealed state a time-travel query is answered from: the chain up to and including the
+/// block at `height`. Its events are provable by inclusion proofs into those blocks, and
+/// the blocks by a consistency proof from `tree_size` to a later checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub height: u64,
+    pub block_hash: String,
+    /// When the block was sealed; artifact records are taken as they stood then.
+    pub sealed_at: DateTime<Utc>,
+    /// Number of blocks in the snapshot, as a checkpoint tree size.
+    pub tree_size: u64,
 }
 
 /// Block for tamper-evidence.
@@ -58,19 +472,289 @@ pub struct Lineage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub id: Uuid,
+    /// Position in the chain; the first sealed block has height 0.
+    pub height: u64,
     pub events: Vec<Event>,
+    /// Hex Merkle root over `events`; absent on blocks sealed before roots were introduced.
+    #[serde(default)]
+    pub merkle_root: Option<String>,
     pub hash: String,
     pub previous_hash: String,
     pub created_at: DateTime<Utc>,
+    /// Receipts from external anchors for `hash`; obtained after sealing, so not hashed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchors: Vec<anchor::AnchorReceipt>,
+    /// Anchors whose last attempt at `hash` failed; cleared once they issue a receipt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchor_failures: Vec<anchor::AnchorFailure>,
+}
+
+/// `previous_hash` of the first block in the chain.
+pub const GENESIS_HASH: &str = "genesis";
+
+impl Block {
+    /// h_i = H(events_i || h_{i-1}), with events in canonical JSON form.
+    /// Used for blocks without a Merkle root.
+    pub fn compute_hash(events: &[Event], previous_hash: &str) -> Result<String, ProvenanceError> {
+        let mut data = canonical::to_canonical_json(&events)?;
+        data.extend_from_slice(previous_hash.as_bytes());
+        Ok(hex::encode(ring::digest::digest(&ring::digest::SHA256, &data)))
+    }
+
+    /// h_i = H(root_i || h_{i-1}), where root_i is the hex Merkle root over events_i.
+    /// The root commits to the events, so inclusion proofs can be checked against h_i.
+    pub fn compute_rooted_hash(merkle_root: &str, previous_hash: &str) -> String {
+        let data = format!("{}{}", merkle_root, previous_hash);
+        hex::encode(ring::digest::digest(&ring::digest::SHA256, data.as_bytes()))
+    }
+
+    /// Recomputes the hash this block should carry from its contents.
+    pub fn expected_hash(&self) -> Result<String, ProvenanceError> {
+        match &self.merkle_root {
+            Some(root) => Ok(Self::compute_rooted_hash(root, &self.previous_hash)),
+            None => Self::compute_hash(&self.events, &self.previous_hash),
+        }
+    }
+}
+
+/// Proof that an event is included in a sealed block.
+/// Lets a single event be checked against h_i without the rest of the block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub event_id: Uuid,
+    pub block_id: Uuid,
+    pub block_height: u64,
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    /// Hex sibling hashes from the leaf up to the root.
+    pub audit_path: Vec<String>,
+    pub merkle_root: String,
+    pub previous_hash: String,
+    pub block_hash: String,
+}
+
+/// Keystore actor whose key signs checkpoints.
+pub const CHECKPOINT_SIGNER: &str = "provenance-log";
+
+/// Keystore actor whose key signs key changes made on an actor's behalf. Its key is
+/// never generated on first use; provision it with `Keystore::provision`.
+pub const KEY_ADMIN: &str = "key-admin";
+
+/// Keystore actors that belong to the service itself. No caller may act as them, and
+/// their keys are provisioned explicitly rather than generated on first use.
+pub const RESERVED_ACTORS: [&str; 3] = [CHECKPOINT_SIGNER, KEY_ADMIN, anchor::LOCAL_TSA_ACTOR];
+
+/// Signed checkpoint of the chain, in the style of a Certificate Transparency signed
+/// tree head: the Merkle root over the hashes of the first `tree_size` sealed blocks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Number of sealed blocks covered.
+    pub tree_size: u64,
+    /// Hex Merkle root over the block hashes, in height order.
+    pub root_hash: String,
+    pub timestamp: DateTime<Utc>,
+    /// Signature by CHECKPOINT_SIGNER over `signing_payload`.
+    pub signature: Signature,
+}
+
+impl Checkpoint {
+    /// Digest that the log signs: H(tree_size, root_hash, timestamp).
+    pub fn signing_payload(tree_size: u64, root_hash: &str, timestamp: DateTime<Utc>) -> Result<Vec<u8>, ProvenanceError> {
+        canonical::digest(&serde_json::json!({
+            "tree_size": tree_size,
+            "root_hash": root_hash,
+            "timestamp": timestamp,
+        }))
+    }
+
+    /// Checks the checkpoint signature against the log's raw public key.
+    pub fn verify_signature(&self, public_key: &[u8]) -> Result<bool, ProvenanceError> {
+        let payload = Self::signing_payload(self.tree_size, &self.root_hash, self.timestamp)?;
+        Ok(keystore::verify_with_key(public_key, &payload, &self.signature))
+    }
+}
+
+/// Proof that the checkpoint tree of size `first` is a prefix of the one of size `second`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    pub first: u64,
+    pub second: u64,
+    /// Hex node hashes per RFC 9162, 2.1.4.
+    pub path: Vec<String>,
+}
+
+/// Current tip of the block chain, persisted alongside the blocks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainHead {
+    pub hash: String,
+    /// Number of sealed blocks.
+    pub height: u64,
+}
+
+impl Default for ChainHead {
+    fn default() -> Self {
+        Self {
+            hash: GENESIS_HASH.to_string(),
+            height: 0,
+        }
+    }
+}
+
+/// Result of walking the block chain.
+/// Corresponds to the block integrity invariant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    pub blocks_verified: u64,
+    pub head: ChainHead,
+    pub first_broken_link: Option<BrokenLink>,
+}
+
+/// The first block at which the chain fails to verify.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub height: u64,
+    pub block_id: Option<Uuid>,
+    pub reason: String,
+}
+
+/// Policy deciding when pending events are sealed into a block.
+/// A block is sealed as soon as any configured threshold is reached;
+/// with neither set, blocks are only sealed on demand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealingPolicy {
+    /// Seal once this many events are pending.
+    pub max_events: Option<usize>,
+    /// Seal once the oldest pending event is this many seconds old.
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for SealingPolicy {
+    fn default() -> Self {
+        Self {
+            max_events: Some(100),
+            max_age_secs: Some(60),
+        }
+    }
+}
+
+impl SealingPolicy {
+    /// Reads `PL_SEAL_MAX_EVENTS` and `PL_SEAL_MAX_AGE_SECS`, falling back to the defaults.
+    /// Setting a variable to `0` disables that threshold.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        Self {
+            max_events: match read("PL_SEAL_MAX_EVENTS") {
+                Some(0) => None,
+                Some(n) => Some(n as usize),
+                None => defaults.max_events,
+            },
+            max_age_secs: match read("PL_SEAL_MAX_AGE_SECS") {
+                Some(0) => None,
+                Some(n) => Some(n),
+                None => defaults.max_age_secs,
+            },
+        }
+    }
+
+    /// Whether `pending` events, the oldest logged at `oldest`, should be sealed at `now`.
+    pub fn should_seal(&self, pending: usize, oldest: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        if pending == 0 {
+            return false;
+        }
+        let too_many = self.max_events.is_some_and(|max| pending >= max);
+        let too_old = match (self.max_age_secs, oldest) {
+            (Some(max), Some(oldest)) => now.signed_duration_since(oldest).num_seconds() >= max as i64,
+            _ => false,
+        };
+        too_many || too_old
+    }
+}
+
+/// What the background sealer has done, so that failures to seal are visible.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SealerStatus {
+    /// Policy of the running sealer; None if no sealer was spawned.
+    pub policy: Option<SealingPolicy>,
+    pub last_sealed_height: Option<u64>,
+    pub last_sealed_at: Option<DateTime<Utc>>,
+    /// Why the last attempt to seal failed; cleared once a block is sealed again.
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// Failed attempts since the last block was sealed.
+    pub failures: u64,
 }
 
 /// Filter for querying events.
 /// Corresponds to EventFilter in formal model.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct EventFilter {
+    /// Operation of the event.
     pub event_type: Option<String>,
     pub start_time: Option<DateTime<Utc>>,
+    /// Inclusive upper bound on the event timestamp.
     pub end_time: Option<DateTime<Utc>>,
+    pub actor: Option<String>,
+    /// Artifact used or generated by the event.
+    pub artifact_id: Option<Uuid>,
+}
+
+impl EventFilter {
+    /// Whether `event` satisfies every criterion of the filter.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.event_type.as_ref().is_none_or(|op| event.operation == *op)
+            && self.start_time.is_none_or(|t| event.timestamp >= t)
+            && self.end_time.is_none_or(|t| event.timestamp <= t)
+            && self.actor.as_ref().is_none_or(|actor| event.actor == *actor)
+            && self.artifact_id.is_none_or(|id| {
+                event.in_artifacts.contains(&id) || event.out_artifacts.contains(&id)
+            })
+    }
+}
+
+/// Order of query results by event time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Paginated event query.
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub filter: EventFilter,
+    pub order: SortOrder,
+    /// Maximum number of events per page; None returns every match.
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    /// Only events sealed in the blocks up to this point.
+    pub as_of: Option<AsOf>,
+}
+
+/// One page of query results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPage {
+    pub events: Vec<Event>,
+    /// Cursor for the next page; None on the last page.
+    pub next_cursor: Option<String>,
+    /// For sequence-based reads, the `since_seq` to pass to continue tailing the log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_seq: Option<u64>,
+    /// Sealed state the page was read from, for `as_of` queries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<Snapshot>,
+}
+
+/// One page of the artifact registry, ordered by artifact ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactPage {
+    pub artifacts: Vec<Artifact>,
+    /// Cursor for the next page; None on the last page.
+    pub next_cursor: Option<String>,
 }
 
 /// Interface for the Provenance Service.
@@ -78,25 +762,147 @@ pub struct EventFilter {
 #[async_trait::async_trait]
 pub trait ProvenanceService: Send + Sync {
     /// Logs an event to the append-only log.
+    /// Returns the stored event with its assigned ID, timestamp and signature.
     /// Corresponds to createEvent + appendEvent
-    async fn log_event(&mut self, event: Event) -> Result<(), ProvenanceError>;
+    async fn log_event(&mut self, event: Event) -> Result<Event, ProvenanceError>;
 
     /// Registers a new artifact in the registry.
-    async fn register_artifact(&mut self, artifact: Artifact) -> Result<(), ProvenanceError>;
+    /// Returns the stored artifact with its assigned ID.
+    async fn register_artifact(&mut self, artifact: Artifact) -> Result<Artifact, ProvenanceError>;
+
+    /// Registers `artifact` as the next version after `previous`, which must be the
+    /// latest version of its family. The link is recorded as a "derive" event by `actor`.
+    /// Returns the stored artifact and the derive event.
+    async fn register_artifact_version(
+        &mut self,
+        previous: Uuid,
+        artifact: Artifact,
+        actor: &str,
+    ) -> Result<(Artifact, Event), ProvenanceError>;
+
+    /// All versions in the family of `artifact_id`, oldest first.
+    async fn list_versions(&self, artifact_id: Uuid) -> Result<Vec<Artifact>, ProvenanceError>;
+
+    /// Latest version in the family of `artifact_id`.
+    async fn latest_version(&self, artifact_id: Uuid) -> Result<Artifact, ProvenanceError>;
+
+    /// Metadata changes from artifact `from` to artifact `to`.
+    async fn diff_metadata(&self, from: Uuid, to: Uuid) -> Result<MetadataDiff, ProvenanceError>;
+
+    /// Appends `signer`'s signature over the artifact hash and the previous chain entry.
+    /// Returns the artifact with its extended authorship chain.
+    async fn cosign_artifact(
+        &mut self,
+        artifact_id: Uuid,
+        signer: &str,
+        role: Option<String>,
+    ) -> Result<Artifact, ProvenanceError>;
+
+    /// Verifies the authorship chain in order and reports which of `required`
+    /// have not signed.
+    async fn verify_authorship(
+        &self,
+        artifact_id: Uuid,
+        required: Vec<String>,
+    ) -> Result<AuthorshipVerification, ProvenanceError>;
+
+    /// Registers a key for `actor`, who has none or whose key was revoked, generating
+    /// one unless `pkcs8` is given. Logged as a `key_register` event signed by the new
+    /// key, or by KEY_ADMIN with `admin`, which needs the KEY_ADMIN key provisioned.
+    /// Callers that take `admin` from a request must authorize it themselves.
+    async fn register_key(&mut self, actor: &str, pkcs8: Option<Vec<u8>>, admin: bool) -> Result<keystore::KeyChange, ProvenanceError>;
+
+    /// Replaces the key of `actor`, generating one unless `pkcs8` is given. Logged as a
+    /// `key_rotate` event signed by the old key, or by KEY_ADMIN with `admin`; the old
+    /// key stays valid for signatures made before the event.
+    async fn rotate_key(&mut self, actor: &str, pkcs8: Option<Vec<u8>>, admin: bool) -> Result<keystore::KeyChange, ProvenanceError>;
+
+    /// Revokes the key of `actor`. Logged as a `key_revoke` event signed by the revoked
+    /// key, or by KEY_ADMIN with `admin`; signatures dated after the event are rejected.
+    async fn revoke_key(&mut self, actor: &str, reason: Option<String>, admin: bool) -> Result<keystore::KeyChange, ProvenanceError>;
+
+    /// Every key `actor` has held, oldest first.
+    async fn key_history(&self, actor: &str) -> Result<Vec<keystore::KeyRecord>, ProvenanceError>;
+
+    /// Verifies the cryptographic signature against the signer's key that was valid at
+    /// `signed_at`.
+    async fn verify_signature(
+        &self,
+        data: &[u8],
+        signature: &Signature,
+        signed_at: DateTime<Utc>,
+    ) -> Result<keystore::SignatureVerification, ProvenanceError>;
+
+    /// Looks up a single event by ID.
+    async fn get_event(&self, id: Uuid) -> Result<Option<Event>, ProvenanceError>;
+
+    /// Looks up a single artifact by ID.
+    async fn get_artifact(&self, id: Uuid) -> Result<Option<Artifact>, ProvenanceError>;
+
+    /// Looks up an artifact as it stood in the sealed state at `as_of`; None if it was
+    /// registered after that state was sealed.
+    async fn get_artifact_as_of(&self, id: Uuid, as_of: AsOf) -> Result<Option<Artifact>, ProvenanceError>;
 
-    /// Verifies the cryptographic signature.
-    async fn verify_signature(&self, data: &[u8], signature: &Signature) -> Result<bool, ProvenanceError>;
+    /// Lists registered artifacts in ID order, starting after `cursor`, up to `limit`.
+    async fn list_artifacts(&self, cursor: Option<String>, limit: Option<usize>) -> Result<ArtifactPage, ProvenanceError>;
 
-    /// Retrieves the lineage for a given artifact.
+    /// Retrieves the lineage subgraph for a given artifact. With `as_of`, G_P and the
+    /// registry are reconstructed from the sealed blocks up to that point.
     /// Corresponds to lineage^{-} and lineage^{+}
-    async fn get_lineage(&self, artifact_id: Uuid) -> Result<Lineage, ProvenanceError>;
+    async fn get_lineage(&self, artifact_id: Uuid, query: LineageQuery) -> Result<Lineage, ProvenanceError>;
+
+    /// Differences between two artifacts, one derived from the other, with the change
+    /// made by each derivation on a shortest path between them.
+    async fn diff_artifacts(&self, from: Uuid, to: Uuid) -> Result<ArtifactDiff, ProvenanceError>;
 
     /// Retrieves events from the log, optionally filtered.
     async fn get_events(&self, filter: Option<EventFilter>) -> Result<Vec<Event>, ProvenanceError>;
 
+    /// Retrieves one page of events matching `query`, using the secondary indexes.
+    /// With `as_of`, only events sealed in the blocks up to that point match.
+    async fn query_events(&self, query: EventQuery) -> Result<EventPage, ProvenanceError>;
+
+    /// Reads the log in sequence order from `since_seq` (inclusive), keeping events that
+    /// match `filter`, up to `limit` events. `next_seq` of the result continues the read.
+    async fn events_since(
+        &self,
+        since_seq: u64,
+        filter: EventFilter,
+        limit: Option<usize>,
+    ) -> Result<EventPage, ProvenanceError>;
+
     /// Creates a new block with pending events.
+    /// Returns None when there is nothing to seal.
     /// Corresponds to block creation for tamper-evidence.
-    async fn create_block(&mut self) -> Result<Block, ProvenanceError>;
+    async fn create_block(&mut self) -> Result<Option<Block>, ProvenanceError>;
+
+    /// Looks up a sealed block by ID.
+    async fn get_block(&self, id: Uuid) -> Result<Option<Block>, ProvenanceError>;
+
+    /// Sealed blocks in height order, starting at `from_height`, up to `limit`.
+    async fn list_blocks(&self, from_height: u64, limit: Option<usize>) -> Result<Vec<Block>, ProvenanceError>;
+
+    /// Anchors a sealed block with every configured anchor that holds no receipt for it
+    /// yet, e.g. after an anchor was unreachable at sealing time. Returns the block with
+    /// all of its receipts.
+    async fn anchor_block(&mut self, id: Uuid) -> Result<Block, ProvenanceError>;
+
+    /// Checks every anchor receipt of a sealed block against its hash.
+    async fn verify_anchors(&self, id: Uuid) -> Result<Vec<anchor::AnchorVerification>, ProvenanceError>;
+
+    /// Signed checkpoint over every sealed block.
+    async fn checkpoint(&self) -> Result<Checkpoint, ProvenanceError>;
+
+    /// Proves that the checkpoint tree of the first `first` blocks is a prefix of the
+    /// one of the first `second` blocks.
+    async fn consistency_proof(&self, first: u64, second: u64) -> Result<ConsistencyProof, ProvenanceError>;
+
+    /// Walks the blocks in height order and reports the first broken link.
+    /// Corresponds to the block integrity invariant.
+    async fn verify_chain(&self) -> Result<ChainVerification, ProvenanceError>;
+
+    /// Builds the Merkle inclusion proof tying an event to its sealed block.
+    async fn get_inclusion_proof(&self, event_id: Uuid) -> Result<InclusionProof, ProvenanceError>;
 }
 
 /// Errors that can occur in the Provenance Layer.
@@ -108,10 +914,76 @@ pub enum ProvenanceError {
     SignatureError,
     #[error("Artifact not found")]
     ArtifactNotFound,
+    #[error("Artifact is not the latest version of its family; latest is {latest}")]
+    NotLatestVersion { latest: Uuid },
+    #[error("Actor has already signed this artifact: {0}")]
+    AlreadySigned(String),
+    #[error("Content hash does not match any stored blob: {0}")]
+    ContentMismatch(String),
+    #[error("Blob store error: {0}")]
+    BlobError(String),
+    #[error("Unknown artifact referenced by event: {0}")]
+    UnknownArtifact(Uuid),
+    #[error("Derivation {from} -> {to} would create a cycle in the provenance graph")]
+    CycleDetected { from: Uuid, to: Uuid },
     #[error("Block creation failed")]
     BlockError,
+    #[error("Block not found")]
+    BlockNotFound,
+    #[error("Anchor error: {0}")]
+    AnchorError(String),
+    #[error("Log position {0} is already taken")]
+    AppendConflict(u64),
+    #[error("No consistency proof from tree size {first} to {second} in a log of {size} blocks")]
+    InvalidTreeSize { first: u64, second: u64, size: u64 },
+    #[error("Invalid pagination cursor")]
+    InvalidCursor,
+    #[error("No lineage path between artifacts {from} and {to}")]
+    NoLineagePath { from: Uuid, to: Uuid },
+    #[error("Invalid as_of: {0}")]
+    InvalidAsOf(String),
+    #[error("No block was sealed as of {0}")]
+    NotSealedAsOf(AsOf),
+    #[error("Event not found")]
+    EventNotFound,
+    #[error("Event is not sealed in a block yet")]
+    EventNotSealed,
+    #[error("No key registered for actor: {0}")]
+    KeyNotFound(String),
+    #[error("Actor already has a key: {0}")]
+    KeyExists(String),
+    #[error("Actor's key has been revoked: {0}")]
+    KeyRevoked(String),
+    #[error("Actor is reserved for the service's own keys: {0}")]
+    ReservedActor(String),
+    #[error("Key error: {0}")]
+    KeyError(String),
+    #[error("Canonicalization error: {0}")]
+    CanonicalizationError(String),
+    #[error("Invalid PROV document: {0}")]
+    InvalidProv(String),
+    #[error("Invalid provenance bundle: {0}")]
+    InvalidBundle(String),
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
-}
\ No newline at end of file
+}
+
+impl From<sled::Error> for ProvenanceError {
+    fn from(e: sled::Error) -> Self {
+        ProvenanceError::DatabaseError(e.to_string())
+    }
+}
+
+impl From<sled::transaction::TransactionError> for ProvenanceError {
+    fn from(e: sled::transaction::TransactionError) -> Self {
+        ProvenanceError::DatabaseError(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for ProvenanceError {
+    fn from(e: rusqlite::Error) -> Self {
+        ProvenanceError::DatabaseError(e.to_string())
+    }
+}
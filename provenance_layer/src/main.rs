@@ -2,22 +2,251 @@
 // This provides a REST API for logging events and registering artifacts.
 
 use axum::{
-    extract::{Json, Path},
+    extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    extract::{BodyStream, DefaultBodyLimit, Json, Multipart, Path, Query},
+    http::HeaderMap,
+    response::sse::{self, KeepAlive, Sse},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
+use futures_util::StreamExt;
 use std::net::SocketAddr;
 use serde_json::json;
 use provenance_layer::*;
 
-mod provenance_impl;
-
-use provenance_impl::SledProvenanceService;
+use provenance_layer::bundle::{self, ProvenanceBundle};
+use provenance_layer::provenance_impl::ProvenanceServiceImpl;
+use provenance_layer::subscription::{FeedCursor, Notification, Subscription, SubscriptionFilter};
 use std::sync::Arc;
 
+/// Request body for importing an existing actor keypair.
+#[derive(serde::Deserialize)]
+struct KeyImport {
+    /// Hex-encoded PKCS#8 Ed25519 keypair.
+    pkcs8: String,
+}
+
+/// Request body for rotating an actor's key.
+#[derive(serde::Deserialize)]
+struct KeyRotation {
+    /// Hex-encoded PKCS#8 Ed25519 keypair; generated if absent.
+    pkcs8: Option<String>,
+}
+
+/// Request body for revoking an actor's key.
+#[derive(serde::Deserialize)]
+struct KeyRevocation {
+    reason: Option<String>,
+}
+
+/// Default and maximum page sizes for `GET /events`.
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Query parameters for `GET /events`.
+#[derive(serde::Deserialize)]
+struct EventsQuery {
+    actor: Option<String>,
+    /// Artifact used or generated by the event.
+    artifact: Option<uuid::Uuid>,
+    operation: Option<String>,
+    /// Older name for `operation`.
+    event_type: Option<String>,
+    start_time: Option<chrono::DateTime<chrono::Utc>>,
+    end_time: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    order: SortOrder,
+    limit: Option<usize>,
+    cursor: Option<String>,
+    /// Read the log in sequence order from this position instead of by index.
+    since_seq: Option<u64>,
+    /// Only events sealed in the blocks up to this height or time.
+    as_of: Option<AsOf>,
+}
+
+/// Query parameters for `GET /artifacts/{id}`.
+#[derive(serde::Deserialize)]
+struct ArtifactQuery {
+    /// The record as it stood when the blocks up to this height or time were sealed.
+    as_of: Option<AsOf>,
+}
+
+/// Query parameters for `GET /artifacts`.
+#[derive(serde::Deserialize)]
+struct ArtifactsQuery {
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+/// Query parameters for `GET /blocks`.
+#[derive(serde::Deserialize)]
+struct BlocksQuery {
+    #[serde(default)]
+    from_height: u64,
+    limit: Option<usize>,
+}
+
+/// Query parameters for `GET /checkpoint/consistency`.
+#[derive(serde::Deserialize)]
+struct ConsistencyQuery {
+    first: u64,
+    /// Defaults to the size of the latest checkpoint.
+    second: Option<u64>,
+}
+
+/// Request body for registering a new version of an artifact.
+#[derive(serde::Deserialize)]
+struct VersionRegistration {
+    artifact: Artifact,
+    /// Actor recorded on the implicit derive event.
+    actor: String,
+}
+
+/// Request body for co-signing an artifact.
+#[derive(serde::Deserialize)]
+struct Cosign {
+    signer: String,
+    role: Option<String>,
+}
+
+/// Query parameters for authorship verification.
+#[derive(serde::Deserialize)]
+struct AuthorshipQuery {
+    /// Comma-separated actors who must have signed.
+    required: Option<String>,
+}
+
+/// Query parameters for PROV export.
+#[derive(serde::Deserialize)]
+struct ProvExportQuery {
+    /// `json` (PROV-JSON, default) or `turtle` (PROV-O).
+    format: Option<String>,
+    #[serde(default)]
+    direction: LineageDirection,
+    max_depth: Option<usize>,
+    event_type: Option<String>,
+    start_time: Option<chrono::DateTime<chrono::Utc>>,
+    end_time: Option<chrono::DateTime<chrono::Utc>>,
+    /// Export the lineage as of this block height or time.
+    as_of: Option<AsOf>,
+}
+
+/// Query parameters for `GET /feed` and `GET /feed/ws`.
+#[derive(serde::Deserialize)]
+struct FeedQuery {
+    actor: Option<String>,
+    /// Artifact used or generated by the event.
+    artifact: Option<uuid::Uuid>,
+    operation: Option<String>,
+    /// Comma-separated kinds to send: `event`, `block`. Both by default.
+    types: Option<String>,
+    /// Replay the log from this sequence number (inclusive) before following it.
+    since_seq: Option<u64>,
+    /// Replay the chain from this block height before following it.
+    from_height: Option<u64>,
+}
+
+impl FeedQuery {
+    fn filter(&self) -> Result<SubscriptionFilter, String> {
+        let mut filter = SubscriptionFilter {
+            filter: EventFilter {
+                event_type: self.operation.clone(),
+                actor: self.actor.clone(),
+                artifact_id: self.artifact,
+                ..EventFilter::default()
+            },
+            ..SubscriptionFilter::default()
+        };
+        if let Some(types) = &self.types {
+            filter.events = false;
+            filter.blocks = false;
+            for kind in types.split(',') {
+                match kind.trim() {
+                    "event" => filter.events = true,
+                    "block" => filter.blocks = true,
+                    other => return Err(format!("unknown notification type: {}", other)),
+                }
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Reason for a close frame, cut to the 123 bytes a frame can carry.
+fn close_reason(e: &ProvenanceError) -> String {
+    let mut reason = e.to_string();
+    let mut end = reason.len().min(123);
+    while !reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    reason.truncate(end);
+    reason
+}
+
+/// Sends each notification of `subscription` as a JSON text message, with the cursor to
+/// resume from, until either side goes away. A failed subscription closes the socket
+/// with an error frame saying why.
+async fn send_feed(mut socket: WebSocket, mut subscription: Subscription) {
+    loop {
+        tokio::select! {
+            next = subscription.next() => {
+                let notification = match next {
+                    Some(Ok(notification)) => notification,
+                    Some(Err(e)) => {
+                        let frame = CloseFrame { code: close_code::ERROR, reason: close_reason(&e).into() };
+                        let _ = socket.send(Message::Close(Some(frame))).await;
+                        break;
+                    }
+                    None => break,
+                };
+                let mut message = json!(notification);
+                message["cursor"] = json!(subscription.cursor());
+                if socket.send(Message::Text(message.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            // Clients have nothing to send; pings are answered by the socket itself
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Response body for a key change: the key in effect afterwards and the event logged.
+fn key_change_body(change: &keystore::KeyChange) -> serde_json::Value {
+    json!({
+        "actor": change.key.actor,
+        "algorithm": change.key.algorithm,
+        "public_key": change.key.public_key,
+        "key": change.key,
+        "event": change.event,
+    })
+}
+
+/// Renders events and artifacts in the requested PROV serialization.
+fn prov_response(format: Option<&str>, events: &[Event], artifacts: &[Artifact]) -> Response {
+    match format.unwrap_or("json") {
+        "json" => (
+            [(axum::http::header::CONTENT_TYPE, "application/json")],
+            prov::to_prov_json(events, artifacts).to_string(),
+        )
+            .into_response(),
+        "turtle" => (
+            [(axum::http::header::CONTENT_TYPE, "text/turtle")],
+            prov::to_prov_turtle(events, artifacts),
+        )
+            .into_response(),
+        _ => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "format must be json or turtle"}))).into_response(),
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let service = Arc::new(SledProvenanceService::new().await.expect("Failed to initialize service"));
+    let service = Arc::new(ProvenanceServiceImpl::new().await.expect("Failed to initialize service"));
+    service.spawn_sealer(SealingPolicy::from_env());
 
     let app = Router::new()
         .route("/events", post({
@@ -25,7 +254,10 @@ async fn main() {
             move |Json(payload): Json<Event>| async move {
                 let mut svc = service.as_ref().clone();
                 match svc.log_event(payload).await {
-                    Ok(_) => (axum::http::StatusCode::OK, Json(json!({"status": "event logged"}))),
+                    Ok(event) => (axum::http::StatusCode::OK, Json(json!(event))),
+                    Err(e @ ProvenanceError::UnknownArtifact(_)) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": e.to_string()}))),
+                    Err(e @ (ProvenanceError::KeyRevoked(_) | ProvenanceError::ReservedActor(_))) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(e @ ProvenanceError::CycleDetected { .. }) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
                     Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to log event"}))),
                 }
             }
@@ -35,18 +267,89 @@ async fn main() {
             move |Json(payload): Json<Artifact>| async move {
                 let mut svc = service.as_ref().clone();
                 match svc.register_artifact(payload).await {
-                    Ok(_) => (axum::http::StatusCode::OK, Json(json!({"status": "artifact registered"}))),
+                    Ok(artifact) => (axum::http::StatusCode::OK, Json(json!(artifact))),
+                    Err(ProvenanceError::ContentMismatch(hash)) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": "content_hash does not match any uploaded blob", "content_hash": hash}))),
                     Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to register artifact"}))),
                 }
             }
         }))
         .route("/events", get({
             let service = service.clone();
-            move || async move {
+            move |Query(params): Query<EventsQuery>| async move {
                 let svc = service.as_ref();
-                match svc.get_events(None).await {
-                    Ok(events) => (axum::http::StatusCode::OK, Json(events)),
-                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(vec![])),
+                let filter = EventFilter {
+                    event_type: params.operation.or(params.event_type),
+                    start_time: params.start_time,
+                    end_time: params.end_time,
+                    actor: params.actor,
+                    artifact_id: params.artifact,
+                };
+                let limit = Some(params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE));
+                let page = match (params.since_seq, params.as_of) {
+                    (Some(_), Some(_)) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "since_seq and as_of cannot be combined"}))),
+                    (Some(since_seq), None) => svc.events_since(since_seq, filter, limit).await,
+                    (None, as_of) => {
+                        let query = EventQuery { filter, order: params.order, limit, cursor: params.cursor, as_of };
+                        svc.query_events(query).await
+                    }
+                };
+                match page {
+                    Ok(page) => (axum::http::StatusCode::OK, Json(json!(page))),
+                    Err(ProvenanceError::InvalidCursor) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid cursor"}))),
+                    Err(e @ ProvenanceError::NotSealedAsOf(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to query events"}))),
+                }
+            }
+        }))
+        .route("/feed", get({
+            let service = service.clone();
+            move |Query(query): Query<FeedQuery>, headers: HeaderMap| async move {
+                let filter = match query.filter() {
+                    Ok(filter) => filter,
+                    Err(e) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+                };
+                // A reconnecting EventSource resumes after the last message it received
+                let (since_seq, from_height) = match headers.get("last-event-id") {
+                    None => (query.since_seq, query.from_height),
+                    Some(id) => match id.to_str().ok().and_then(|id| id.parse::<FeedCursor>().ok()) {
+                        Some(cursor) => (Some(cursor.seq), Some(cursor.height)),
+                        None => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid Last-Event-ID"}))).into_response(),
+                    },
+                };
+                let subscription = match service.subscribe(filter, since_seq, from_height) {
+                    Ok(subscription) => subscription,
+                    Err(_) => return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to subscribe"}))).into_response(),
+                };
+                // A failed subscription ends the stream with an `error` message saying why
+                let stream = futures_util::stream::unfold(Some(subscription), |subscription| async move {
+                    let mut subscription = subscription?;
+                    let notification = match subscription.next().await? {
+                        Ok(notification) => notification,
+                        Err(e) => {
+                            let event = sse::Event::default().event("error").json_data(json!({"error": e.to_string()}));
+                            return Some((event, None));
+                        }
+                    };
+                    let event = sse::Event::default().event(notification.kind()).id(subscription.cursor().to_string());
+                    let event = match &notification {
+                        Notification::Event(event_record) => event.json_data(event_record),
+                        Notification::Block(block) => event.json_data(block),
+                    };
+                    Some((event, Some(subscription)))
+                });
+                Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+            }
+        }))
+        .route("/feed/ws", get({
+            let service = service.clone();
+            move |Query(query): Query<FeedQuery>, upgrade: WebSocketUpgrade| async move {
+                let filter = match query.filter() {
+                    Ok(filter) => filter,
+                    Err(e) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+                };
+                match service.subscribe(filter, query.since_seq, query.from_height) {
+                    Ok(subscription) => upgrade.on_upgrade(|socket| send_feed(socket, subscription)),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to subscribe"}))).into_response(),
                 }
             }
         }))
@@ -54,51 +357,563 @@ async fn main() {
             let service = service.clone();
             move |Path(id): Path<String>| async move {
                 let svc = service.as_ref();
-                match svc.get_events(Some(id)).await {
-                    Ok(events) => {
-                        if events.is_empty() {
-                            (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "event not found"})))
-                        } else {
-                            (axum::http::StatusCode::OK, Json(events[0].clone()))
-                        }
-                    },
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid event id"}))),
+                };
+                match svc.get_event(id).await {
+                    Ok(Some(event)) => (axum::http::StatusCode::OK, Json(json!(event))),
+                    Ok(None) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "event not found"}))),
                     Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to get event"}))),
                 }
             }
         }))
-        .route("/artifacts/:id", get({
+        .route("/events/:id/proof", get({
             let service = service.clone();
             move |Path(id): Path<String>| async move {
                 let svc = service.as_ref();
-                match svc.get_artifacts(Some(id)).await {
-                    Ok(artifacts) => {
-                        if artifacts.is_empty() {
-                            (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"})))
-                        } else {
-                            (axum::http::StatusCode::OK, Json(artifacts[0].clone()))
-                        }
-                    },
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid event id"}))),
+                };
+                match svc.get_inclusion_proof(id).await {
+                    Ok(proof) => (axum::http::StatusCode::OK, Json(json!(proof))),
+                    Err(ProvenanceError::EventNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "event not found"}))),
+                    Err(ProvenanceError::EventNotSealed) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "event is not sealed in a block yet"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to build inclusion proof"}))),
+                }
+            }
+        }))
+        .route("/events/:id/signature", get({
+            let service = service.clone();
+            move |Path(id): Path<String>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid event id"}))),
+                };
+                let event = match svc.get_event(id).await {
+                    Ok(Some(event)) => event,
+                    Ok(None) => return (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "event not found"}))),
+                    Err(_) => return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to get event"}))),
+                };
+                let (signature, payload) = match (&event.signature, event.signing_payload()) {
+                    (Some(signature), Ok(payload)) => (signature, payload),
+                    (None, _) => return (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": "event is unsigned"}))),
+                    (_, Err(_)) => return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to verify signature"}))),
+                };
+                match svc.verify_signature(&payload, signature, event.timestamp).await {
+                    Ok(verification) => (axum::http::StatusCode::OK, Json(json!(verification))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to verify signature"}))),
+                }
+            }
+        }))
+        .route("/artifacts", get({
+            let service = service.clone();
+            move |Query(params): Query<ArtifactsQuery>| async move {
+                let svc = service.as_ref();
+                let limit = Some(params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE));
+                match svc.list_artifacts(params.cursor, limit).await {
+                    Ok(page) => (axum::http::StatusCode::OK, Json(json!(page))),
+                    Err(ProvenanceError::InvalidCursor) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid cursor"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to list artifacts"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id", get({
+            let service = service.clone();
+            move |Path(id): Path<String>, Query(query): Query<ArtifactQuery>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                let artifact = match query.as_of {
+                    Some(as_of) => svc.get_artifact_as_of(id, as_of).await,
+                    None => svc.get_artifact(id).await,
+                };
+                match artifact {
+                    Ok(Some(artifact)) => (axum::http::StatusCode::OK, Json(json!(artifact))),
+                    Ok(None) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(e @ ProvenanceError::NotSealedAsOf(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
                     Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to get artifact"}))),
                 }
             }
         }))
         .route("/artifacts/:id/lineage", get({
+            let service = service.clone();
+            move |Path(id): Path<String>, Query(query): Query<LineageQuery>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.get_lineage(id, query).await {
+                    Ok(lineage) => (axum::http::StatusCode::OK, Json(json!(lineage))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(e @ ProvenanceError::NotSealedAsOf(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to get lineage"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/versions", post({
+            let service = service.clone();
+            move |Path(id): Path<String>, Json(payload): Json<VersionRegistration>| async move {
+                let mut svc = service.as_ref().clone();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.register_artifact_version(id, payload.artifact, &payload.actor).await {
+                    Ok((artifact, event)) => (axum::http::StatusCode::OK, Json(json!({"artifact": artifact, "event": event}))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(ProvenanceError::NotLatestVersion { latest }) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "artifact is not the latest version", "latest": latest}))),
+                    Err(ProvenanceError::ContentMismatch(hash)) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": "content_hash does not match any uploaded blob", "content_hash": hash}))),
+                    Err(e @ (ProvenanceError::KeyRevoked(_) | ProvenanceError::ReservedActor(_))) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to register version"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/versions", get({
+            let service = service.clone();
+            move |Path(id): Path<String>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.list_versions(id).await {
+                    Ok(versions) => (axum::http::StatusCode::OK, Json(json!(versions))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to list versions"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/versions/latest", get({
+            let service = service.clone();
+            move |Path(id): Path<String>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.latest_version(id).await {
+                    Ok(artifact) => (axum::http::StatusCode::OK, Json(json!(artifact))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to fetch latest version"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/diff/:other", get({
+            let service = service.clone();
+            move |Path((id, other)): Path<(String, String)>| async move {
+                let svc = service.as_ref();
+                let (id, other) = match (uuid::Uuid::parse_str(&id), uuid::Uuid::parse_str(&other)) {
+                    (Ok(id), Ok(other)) => (id, other),
+                    _ => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.diff_metadata(id, other).await {
+                    Ok(diff) => (axum::http::StatusCode::OK, Json(json!(diff))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to diff artifacts"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/lineage/diff/:other", get({
+            let service = service.clone();
+            move |Path((id, other)): Path<(String, String)>| async move {
+                let svc = service.as_ref();
+                let (id, other) = match (uuid::Uuid::parse_str(&id), uuid::Uuid::parse_str(&other)) {
+                    (Ok(id), Ok(other)) => (id, other),
+                    _ => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.diff_artifacts(id, other).await {
+                    Ok(diff) => (axum::http::StatusCode::OK, Json(json!(diff))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(e @ ProvenanceError::NoLineagePath { .. }) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to diff artifacts"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/cosign", post({
+            let service = service.clone();
+            move |Path(id): Path<String>, Json(payload): Json<Cosign>| async move {
+                let mut svc = service.as_ref().clone();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.cosign_artifact(id, &payload.signer, payload.role).await {
+                    Ok(artifact) => (axum::http::StatusCode::OK, Json(json!(artifact))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(ProvenanceError::AlreadySigned(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "signer has already signed this artifact"}))),
+                    Err(e @ (ProvenanceError::KeyRevoked(_) | ProvenanceError::ReservedActor(_))) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to co-sign artifact"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/authorship", get({
+            let service = service.clone();
+            move |Path(id): Path<String>, Query(query): Query<AuthorshipQuery>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                let required = query
+                    .required
+                    .map(|r| r.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+                    .unwrap_or_default();
+                match svc.verify_authorship(id, required).await {
+                    Ok(verification) => (axum::http::StatusCode::OK, Json(json!(verification))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to verify authorship"}))),
+                }
+            }
+        }))
+        .route("/artifacts/:id/content", get({
             let service = service.clone();
             move |Path(id): Path<String>| async move {
                 let svc = service.as_ref();
-                match svc.get_lineage(id).await {
-                    Ok(lineage) => (axum::http::StatusCode::OK, Json(lineage)),
-                    Err(_) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "lineage not found"}))),
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))).into_response(),
+                };
+                let artifact = match svc.get_artifact(id).await {
+                    Ok(Some(artifact)) => artifact,
+                    Ok(None) => return (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))).into_response(),
+                    Err(_) => return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to read artifact"}))).into_response(),
+                };
+                let file = match svc.blobs().path(&artifact.content_hash) {
+                    Some(path) => tokio::fs::File::open(path).await.ok(),
+                    None => None,
+                };
+                match file {
+                    Some(file) => (
+                        [
+                            (axum::http::header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                            (axum::http::header::ETAG, format!("\"{}\"", artifact.content_hash)),
+                        ],
+                        axum::body::StreamBody::new(tokio_util::io::ReaderStream::new(file)),
+                    )
+                        .into_response(),
+                    None => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact content not stored"}))).into_response(),
+                }
+            }
+        }))
+        .route("/artifacts/:id/prov", get({
+            let service = service.clone();
+            move |Path(id): Path<String>, Query(query): Query<ProvExportQuery>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))).into_response(),
+                };
+                let lineage = LineageQuery { direction: query.direction, max_depth: query.max_depth, as_of: query.as_of };
+                match svc.lineage_records(id, lineage).await {
+                    Ok((events, artifacts)) => prov_response(query.format.as_deref(), &events, &artifacts),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))).into_response(),
+                    Err(e @ ProvenanceError::NotSealedAsOf(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))).into_response(),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to export lineage"}))).into_response(),
+                }
+            }
+        }))
+        .route("/artifacts/:id/bundle", get({
+            let service = service.clone();
+            move |Path(id): Path<String>, Query(query): Query<LineageQuery>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid artifact id"}))),
+                };
+                match svc.export_bundle(id, query).await {
+                    Ok(bundle) => (axum::http::StatusCode::OK, Json(json!(bundle))),
+                    Err(ProvenanceError::ArtifactNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "artifact not found"}))),
+                    Err(e @ ProvenanceError::NotSealedAsOf(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to export bundle"}))),
+                }
+            }
+        }))
+        .route("/prov", get({
+            let service = service.clone();
+            move |Query(query): Query<ProvExportQuery>| async move {
+                let svc = service.as_ref();
+                let filter = EventFilter {
+                    event_type: query.event_type,
+                    start_time: query.start_time,
+                    end_time: query.end_time,
+                    ..EventFilter::default()
+                };
+                match svc.range_records(filter).await {
+                    Ok((events, artifacts)) => prov_response(query.format.as_deref(), &events, &artifacts),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to export events"}))).into_response(),
+                }
+            }
+        }))
+        .route("/prov", post({
+            let service = service.clone();
+            move |Json(payload): Json<serde_json::Value>| async move {
+                let mut svc = service.as_ref().clone();
+                match svc.import_prov(&payload).await {
+                    Ok(summary) => (axum::http::StatusCode::OK, Json(json!(summary))),
+                    Err(ProvenanceError::InvalidProv(reason)) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": reason}))),
+                    Err(ProvenanceError::ContentMismatch(hash)) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": "entity content has not been uploaded", "content_hash": hash}))),
+                    Err(ProvenanceError::CycleDetected { from, to }) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "derivation would create a cycle", "from": from, "to": to}))),
+                    Err(e @ ProvenanceError::ReservedActor(_)) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to import PROV document"}))),
                 }
             }
         }))
+        .route("/bundles", post({
+            let service = service.clone();
+            move |Json(bundle): Json<ProvenanceBundle>| async move {
+                let mut svc = service.as_ref().clone();
+                match svc.import_bundle(&bundle).await {
+                    Ok(summary) => (axum::http::StatusCode::OK, Json(json!(summary))),
+                    Err(ProvenanceError::InvalidBundle(reason)) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": reason}))),
+                    Err(ProvenanceError::NotLatestVersion { latest }) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "a version in the bundle conflicts with a registered version", "latest": latest}))),
+                    Err(ProvenanceError::CycleDetected { from, to }) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "derivation would create a cycle", "from": from, "to": to}))),
+                    Err(e @ ProvenanceError::ReservedActor(_)) => (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to import bundle"}))),
+                }
+            }
+        }))
+        .route("/bundles/verify", post(|Json(bundle): Json<ProvenanceBundle>| async move {
+            match bundle::verify_bundle(&bundle) {
+                Ok(verification) => (axum::http::StatusCode::OK, Json(json!(verification))),
+                Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to verify bundle"}))),
+            }
+        }))
+        .route("/blobs", post({
+            let service = service.clone();
+            move |mut body: BodyStream| async move {
+                let result = async {
+                    let mut writer = service.blobs().writer().await?;
+                    while let Some(chunk) = body.next().await {
+                        let chunk = chunk.map_err(|e| ProvenanceError::BlobError(e.to_string()))?;
+                        writer.write(&chunk).await?;
+                    }
+                    writer.finish().await
+                };
+                match result.await {
+                    Ok(blob) => (axum::http::StatusCode::OK, Json(json!(blob))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to store blob"}))),
+                }
+            }
+        }).layer(DefaultBodyLimit::disable()))
+        .route("/blobs/multipart", post({
+            let service = service.clone();
+            move |mut form: Multipart| async move {
+                let result = async {
+                    let mut blobs = Vec::new();
+                    while let Some(mut field) = form.next_field().await.map_err(|e| ProvenanceError::BlobError(e.to_string()))? {
+                        let name = field.name().map(str::to_string);
+                        let file_name = field.file_name().map(str::to_string);
+                        let mut writer = service.blobs().writer().await?;
+                        while let Some(chunk) = field.chunk().await.map_err(|e| ProvenanceError::BlobError(e.to_string()))? {
+                            writer.write(&chunk).await?;
+                        }
+                        blobs.push(json!({"field": name, "file_name": file_name, "blob": writer.finish().await?}));
+                    }
+                    Ok::<_, ProvenanceError>(blobs)
+                };
+                match result.await {
+                    Ok(blobs) => (axum::http::StatusCode::OK, Json(json!(blobs))),
+                    Err(ProvenanceError::BlobError(reason)) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": reason}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to store blobs"}))),
+                }
+            }
+        }).layer(DefaultBodyLimit::disable()))
         .route("/blocks", get({
+            let service = service.clone();
+            move |Query(params): Query<BlocksQuery>| async move {
+                let svc = service.as_ref();
+                let limit = Some(params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE));
+                match svc.list_blocks(params.from_height, limit).await {
+                    Ok(blocks) => (axum::http::StatusCode::OK, Json(json!(blocks))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to list blocks"}))),
+                }
+            }
+        }))
+        .route("/blocks", post({
             let service = service.clone();
             move || async move {
+                let mut svc = service.as_ref().clone();
+                match svc.create_block().await {
+                    Ok(Some(block)) => (axum::http::StatusCode::OK, Json(json!(block))),
+                    Ok(None) => (axum::http::StatusCode::OK, Json(json!({"status": "no pending events"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to create block"}))),
+                }
+            }
+        }))
+        .route("/blocks/sealer", get({
+            let service = service.clone();
+            move || async move { Json(json!(service.sealer_status())) }
+        }))
+        .route("/blocks/:id", get({
+            let service = service.clone();
+            move |Path(id): Path<String>| async move {
                 let svc = service.as_ref();
-                match svc.get_blocks().await {
-                    Ok(blocks) => (axum::http::StatusCode::OK, Json(blocks)),
-                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(vec![])),
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid block id"}))),
+                };
+                match svc.get_block(id).await {
+                    Ok(Some(block)) => (axum::http::StatusCode::OK, Json(json!(block))),
+                    Ok(None) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "block not found"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to get block"}))),
+                }
+            }
+        }))
+        .route("/blocks/:id/anchor", post({
+            let service = service.clone();
+            move |Path(id): Path<String>| async move {
+                let mut svc = service.as_ref().clone();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid block id"}))),
+                };
+                match svc.anchor_block(id).await {
+                    Ok(block) => (axum::http::StatusCode::OK, Json(json!(block))),
+                    Err(ProvenanceError::BlockNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "block not found"}))),
+                    Err(ProvenanceError::AnchorError(e)) => (axum::http::StatusCode::BAD_GATEWAY, Json(json!({"error": e}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to anchor block"}))),
+                }
+            }
+        }))
+        .route("/blocks/:id/anchors", get({
+            let service = service.clone();
+            move |Path(id): Path<String>| async move {
+                let svc = service.as_ref();
+                let id = match uuid::Uuid::parse_str(&id) {
+                    Ok(id) => id,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid block id"}))),
+                };
+                match svc.verify_anchors(id).await {
+                    Ok(report) => (axum::http::StatusCode::OK, Json(json!(report))),
+                    Err(ProvenanceError::BlockNotFound) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "block not found"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to verify anchors"}))),
+                }
+            }
+        }))
+        .route("/blocks/verify", get({
+            let service = service.clone();
+            move || async move {
+                let svc = service.as_ref();
+                match svc.verify_chain().await {
+                    Ok(report) => (axum::http::StatusCode::OK, Json(json!(report))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to verify chain"}))),
+                }
+            }
+        }))
+        .route("/checkpoint", get({
+            let service = service.clone();
+            move || async move {
+                let svc = service.as_ref();
+                match svc.checkpoint().await {
+                    Ok(checkpoint) => (axum::http::StatusCode::OK, Json(json!(checkpoint))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to sign checkpoint"}))),
+                }
+            }
+        }))
+        .route("/checkpoint/consistency", get({
+            let service = service.clone();
+            move |Query(params): Query<ConsistencyQuery>| async move {
+                let svc = service.as_ref();
+                let second = match params.second {
+                    Some(second) => Ok(second),
+                    None => svc.checkpoint().await.map(|c| c.tree_size),
+                };
+                let proof = match second {
+                    Ok(second) => svc.consistency_proof(params.first, second).await,
+                    Err(e) => Err(e),
+                };
+                match proof {
+                    Ok(proof) => (axum::http::StatusCode::OK, Json(json!(proof))),
+                    Err(e @ ProvenanceError::InvalidTreeSize { .. }) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to build consistency proof"}))),
+                }
+            }
+        }))
+        .route("/actors/:actor/keys", get({
+            let service = service.clone();
+            move |Path(actor): Path<String>| async move {
+                match service.keystore().public_key(&actor) {
+                    Ok(Some(public_key)) => (axum::http::StatusCode::OK, Json(json!({"actor": actor, "algorithm": keystore::SIGNATURE_ALGORITHM, "public_key": hex::encode(public_key)}))),
+                    Ok(None) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "no key registered for actor"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to load key"}))),
+                }
+            }
+        }))
+        .route("/actors/:actor/keys", post({
+            let service = service.clone();
+            move |Path(actor): Path<String>| async move {
+                let mut svc = service.as_ref().clone();
+                // Over HTTP, key changes are signed by the actor's own key; signing them
+                // as KEY_ADMIN is left to the library, behind the operator's own checks
+                match svc.register_key(&actor, None, false).await {
+                    Ok(change) => (axum::http::StatusCode::OK, Json(key_change_body(&change))),
+                    Err(e @ ProvenanceError::ReservedActor(_)) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(ProvenanceError::KeyExists(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "actor already has a key"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to generate key"}))),
+                }
+            }
+        }))
+        .route("/actors/:actor/keys/import", post({
+            let service = service.clone();
+            move |Path(actor): Path<String>, Json(payload): Json<KeyImport>| async move {
+                let pkcs8 = match hex::decode(&payload.pkcs8) {
+                    Ok(pkcs8) => pkcs8,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "pkcs8 must be hex-encoded"}))),
+                };
+                let mut svc = service.as_ref().clone();
+                match svc.register_key(&actor, Some(pkcs8), false).await {
+                    Ok(change) => (axum::http::StatusCode::OK, Json(key_change_body(&change))),
+                    Err(e @ ProvenanceError::ReservedActor(_)) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(ProvenanceError::KeyExists(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": "actor already has a key"}))),
+                    Err(ProvenanceError::KeyError(_)) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid PKCS#8 Ed25519 key"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to import key"}))),
+                }
+            }
+        }))
+        .route("/actors/:actor/keys/rotate", post({
+            let service = service.clone();
+            move |Path(actor): Path<String>, Json(payload): Json<KeyRotation>| async move {
+                let pkcs8 = match payload.pkcs8.as_deref().map(hex::decode).transpose() {
+                    Ok(pkcs8) => pkcs8,
+                    Err(_) => return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "pkcs8 must be hex-encoded"}))),
+                };
+                let mut svc = service.as_ref().clone();
+                match svc.rotate_key(&actor, pkcs8, false).await {
+                    Ok(change) => (axum::http::StatusCode::OK, Json(key_change_body(&change))),
+                    Err(e @ ProvenanceError::ReservedActor(_)) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(ProvenanceError::KeyNotFound(_)) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "no key registered for actor"}))),
+                    Err(e @ ProvenanceError::KeyRevoked(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
+                    Err(ProvenanceError::KeyError(_)) => (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "invalid PKCS#8 Ed25519 key"}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to rotate key"}))),
+                }
+            }
+        }))
+        .route("/actors/:actor/keys/revoke", post({
+            let service = service.clone();
+            move |Path(actor): Path<String>, Json(payload): Json<KeyRevocation>| async move {
+                let mut svc = service.as_ref().clone();
+                match svc.revoke_key(&actor, payload.reason, false).await {
+                    Ok(change) => (axum::http::StatusCode::OK, Json(key_change_body(&change))),
+                    Err(e @ ProvenanceError::ReservedActor(_)) => (axum::http::StatusCode::FORBIDDEN, Json(json!({"error": e.to_string()}))),
+                    Err(ProvenanceError::KeyNotFound(_)) => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "no key registered for actor"}))),
+                    Err(e @ ProvenanceError::KeyRevoked(_)) => (axum::http::StatusCode::CONFLICT, Json(json!({"error": e.to_string()}))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to revoke key"}))),
+                }
+            }
+        }))
+        .route("/actors/:actor/keys/history", get({
+            let service = service.clone();
+            move |Path(actor): Path<String>| async move {
+                match service.key_history(&actor).await {
+                    Ok(history) if history.is_empty() => (axum::http::StatusCode::NOT_FOUND, Json(json!({"error": "no key registered for actor"}))),
+                    Ok(history) => (axum::http::StatusCode::OK, Json(json!(history))),
+                    Err(_) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "failed to load key history"}))),
                 }
             }
         }));
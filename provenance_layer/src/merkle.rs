@@ -0,0 +1,231 @@
+// Merkle trees over block events, and over the chain of blocks itself.
+// Follows the RFC 6962 / RFC 9162 tree shape and domain separation, so that a single
+// event can be proven to belong to a sealed block without revealing the other events,
+// and a checkpoint of the chain can be proven to extend an earlier one.
+
+use crate::{canonical, Block, Checkpoint, ConsistencyProof, Event, InclusionProof, ProvenanceError};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    let mut ctx = ring::digest::Context::new(&ring::digest::SHA256);
+    for part in parts {
+        ctx.update(part);
+    }
+    ctx.finish().as_ref().to_vec()
+}
+
+/// Leaf hash of an event: H(0x00 || canonical(event)).
+pub fn leaf_hash(event: &Event) -> Result<Vec<u8>, ProvenanceError> {
+    let bytes = canonical::to_canonical_json(event)?;
+    Ok(sha256(&[&[LEAF_PREFIX], &bytes]))
+}
+
+/// Leaf hash of a sealed block in the checkpoint tree: H(0x00 || block hash).
+pub fn block_leaf_hash(block: &Block) -> Result<Vec<u8>, ProvenanceError> {
+    block_hash_leaf(&block.hash)
+}
+
+/// Leaf hash in the checkpoint tree of the block with hex hash `hash`.
+pub fn block_hash_leaf(hash: &str) -> Result<Vec<u8>, ProvenanceError> {
+    let hash = hex::decode(hash).map_err(|_| ProvenanceError::BlockError)?;
+    Ok(sha256(&[&[LEAF_PREFIX], &hash]))
+}
+
+/// Interior node hash: H(0x01 || left || right).
+pub fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    sha256(&[&[NODE_PREFIX], left, right])
+}
+
+/// Largest power of two strictly smaller than `n` (n > 1).
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Merkle tree hash over already-hashed leaves. The empty tree hashes to H().
+pub fn root(leaves: &[Vec<u8>]) -> Vec<u8> {
+    match leaves.len() {
+        0 => sha256(&[]),
+        1 => leaves[0].clone(),
+        n => {
+            let k = split_point(n);
+            node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+        }
+    }
+}
+
+/// Merkle root over a block's events.
+pub fn events_root(events: &[Event]) -> Result<Vec<u8>, ProvenanceError> {
+    let leaves = events.iter().map(leaf_hash).collect::<Result<Vec<_>, _>>()?;
+    Ok(root(&leaves))
+}
+
+/// Audit path for the leaf at `index`, ordered from the leaf towards the root.
+pub fn audit_path(leaves: &[Vec<u8>], index: usize) -> Vec<Vec<u8>> {
+    let n = leaves.len();
+    if n <= 1 || index >= n {
+        return Vec::new();
+    }
+    let k = split_point(n);
+    if index < k {
+        let mut path = audit_path(&leaves[..k], index);
+        path.push(root(&leaves[k..]));
+        path
+    } else {
+        let mut path = audit_path(&leaves[k..], index - k);
+        path.push(root(&leaves[..k]));
+        path
+    }
+}
+
+/// Recomputes the root from a leaf and its audit path (RFC 9162, 2.1.3.2).
+/// Returns None if the path does not fit a tree of `tree_size` leaves.
+pub fn root_from_path(leaf: &[u8], index: u64, tree_size: u64, path: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if index >= tree_size {
+        return None;
+    }
+    let mut fnode = index;
+    let mut snode = tree_size - 1;
+    let mut r = leaf.to_vec();
+    for p in path {
+        if snode == 0 {
+            return None;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            r = node_hash(p, &r);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    if snode != 0 {
+        return None;
+    }
+    Some(r)
+}
+
+/// Checks offline that `event` is included in the block described by `proof`.
+/// Verifies the audit path against the Merkle root and the root against the block hash;
+/// the event signature is checked separately with `keystore::verify_with_key`.
+pub fn verify_inclusion(event: &Event, proof: &InclusionProof) -> Result<bool, ProvenanceError> {
+    if event.id != proof.event_id {
+        return Ok(false);
+    }
+    let path = match proof
+        .audit_path
+        .iter()
+        .map(hex::decode)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(path) => path,
+        Err(_) => return Ok(false),
+    };
+    let leaf = leaf_hash(event)?;
+    let root = match root_from_path(&leaf, proof.leaf_index, proof.tree_size, &path) {
+        Some(root) => hex::encode(root),
+        None => return Ok(false),
+    };
+    Ok(root == proof.merkle_root
+        && Block::compute_rooted_hash(&proof.merkle_root, &proof.previous_hash) == proof.block_hash)
+}
+
+/// Consistency proof between the tree of the first `first` leaves and the tree of all
+/// `leaves` (RFC 9162, 2.1.4.1). Empty when `first` is 0 or covers every leaf.
+pub fn consistency_path(leaves: &[Vec<u8>], first: usize) -> Vec<Vec<u8>> {
+    if first == 0 || first >= leaves.len() {
+        return Vec::new();
+    }
+    subproof(first, leaves, true)
+}
+
+fn subproof(m: usize, leaves: &[Vec<u8>], complete: bool) -> Vec<Vec<u8>> {
+    let n = leaves.len();
+    if m == n {
+        return if complete { Vec::new() } else { vec![root(leaves)] };
+    }
+    let k = split_point(n);
+    if m <= k {
+        let mut path = subproof(m, &leaves[..k], complete);
+        path.push(root(&leaves[k..]));
+        path
+    } else {
+        let mut path = subproof(m - k, &leaves[k..], false);
+        path.push(root(&leaves[..k]));
+        path
+    }
+}
+
+/// Checks that a tree of `first` leaves with root `first_root` is a prefix of a tree of
+/// `second` leaves with root `second_root` (RFC 9162, 2.1.4.2).
+pub fn verify_consistency(first: u64, second: u64, first_root: &[u8], second_root: &[u8], path: &[Vec<u8>]) -> bool {
+    if first > second {
+        return false;
+    }
+    if first == second {
+        return path.is_empty() && first_root == second_root;
+    }
+    if first == 0 {
+        // Every tree extends the empty tree
+        return path.is_empty();
+    }
+    if path.is_empty() {
+        return false;
+    }
+    let mut path: Vec<&[u8]> = path.iter().map(Vec::as_slice).collect();
+    if first.is_power_of_two() {
+        path.insert(0, first_root);
+    }
+    let mut fnode = first - 1;
+    let mut snode = second - 1;
+    while fnode & 1 == 1 {
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    let mut fr = path[0].to_vec();
+    let mut sr = path[0].to_vec();
+    for c in &path[1..] {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    fr == first_root && sr == second_root && snode == 0
+}
+
+/// Checks offline that checkpoint `new` extends checkpoint `old` according to `proof`:
+/// the log only grew between them and no sealed block was rewritten. Checkpoint
+/// signatures are checked separately with `Checkpoint::verify_signature`.
+pub fn verify_checkpoints(old: &Checkpoint, new: &Checkpoint, proof: &ConsistencyProof) -> bool {
+    if proof.first != old.tree_size || proof.second != new.tree_size {
+        return false;
+    }
+    let decode = |hash: &str| hex::decode(hash).ok();
+    let (Some(old_root), Some(new_root)) = (decode(&old.root_hash), decode(&new.root_hash)) else {
+        return false;
+    };
+    let Some(path) = proof.path.iter().map(|h| decode(h)).collect::<Option<Vec<_>>>() else {
+        return false;
+    };
+    verify_consistency(old.tree_size, new.tree_size, &old_root, &new_root, &path)
+}
@@ -0,0 +1,473 @@
+// W3C PROV mapping for interoperability with archives and rights partners.
+// Artifacts map to prov:Entity, events to prov:Activity and actors to prov:Agent;
+// event inputs and outputs become used / wasGeneratedBy / wasDerivedFrom relations.
+// Exports PROV-JSON and PROV-O (Turtle); imports PROV-JSON.
+
+use crate::{Artifact, Event, ProvenanceError};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Namespace for CAPCF-specific attributes.
+pub const CAPCF_NS: &str = "urn:capcf:ns#";
+const ARTIFACT_NS: &str = "urn:capcf:artifact:";
+const EVENT_NS: &str = "urn:capcf:event:";
+const AGENT_NS: &str = "urn:capcf:agent:";
+
+/// Percent-encodes everything outside the unreserved IRI characters.
+fn encode_local(name: &str) -> String {
+    let mut out = String::new();
+    for b in name.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.' {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "%{:02X}", b);
+        }
+    }
+    out
+}
+
+fn decode_local(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(b) = u8::from_str_radix(&String::from_utf8_lossy(&bytes[i + 1..i + 3]), 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn artifact_qn(id: Uuid) -> String {
+    format!("artifact:{}", id)
+}
+
+fn event_qn(id: Uuid) -> String {
+    format!("event:{}", id)
+}
+
+fn agent_qn(actor: &str) -> String {
+    format!("agent:{}", encode_local(actor))
+}
+
+fn typed(value: impl Into<String>, ty: &str) -> Value {
+    json!({ "$": value.into(), "type": ty })
+}
+
+fn time(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+}
+
+/// Serializes events and the artifacts they touch as a PROV-JSON document.
+pub fn to_prov_json(events: &[Event], artifacts: &[Artifact]) -> Value {
+    let mut entity = Map::new();
+    let mut activity = Map::new();
+    let mut agent = Map::new();
+    let mut used = Map::new();
+    let mut generated = Map::new();
+    let mut associated = Map::new();
+    let mut derived = Map::new();
+
+    for artifact in artifacts {
+        entity.insert(
+            artifact_qn(artifact.id),
+            json!({
+                "prov:label": artifact.name,
+                "capcf:version": artifact.version,
+                "capcf:contentHash": artifact.content_hash,
+                "capcf:metadata": artifact.metadata.to_string(),
+                "capcf:registeredAt": typed(time(&artifact.registered_at), "xsd:dateTime"),
+            }),
+        );
+    }
+
+    for event in events {
+        let ev = event_qn(event.id);
+        let mut attrs = json!({
+            "prov:startTime": time(&event.timestamp),
+            "prov:endTime": time(&event.timestamp),
+            "capcf:operation": event.operation,
+            "capcf:context": event.context.to_string(),
+        });
+        if let Some(sig) = &event.signature {
+            attrs["capcf:signature"] = json!(hex::encode(&sig.signature));
+            attrs["capcf:signatureAlgorithm"] = json!(sig.algorithm);
+        }
+        activity.insert(ev.clone(), attrs);
+
+        let ag = agent_qn(&event.actor);
+        agent.entry(ag.clone()).or_insert_with(|| json!({ "prov:label": event.actor }));
+        associated.insert(
+            format!("_:assoc-{}", event.id),
+            json!({ "prov:activity": ev, "prov:agent": ag }),
+        );
+        for (i, inp) in event.in_artifacts.iter().enumerate() {
+            used.insert(
+                format!("_:used-{}-{}", event.id, i),
+                json!({ "prov:activity": ev, "prov:entity": artifact_qn(*inp) }),
+            );
+        }
+        for (i, out) in event.out_artifacts.iter().enumerate() {
+            generated.insert(
+                format!("_:gen-{}-{}", event.id, i),
+                json!({ "prov:entity": artifact_qn(*out), "prov:activity": ev, "prov:time": time(&event.timestamp) }),
+            );
+            for (j, inp) in event.in_artifacts.iter().enumerate() {
+                derived.insert(
+                    format!("_:deriv-{}-{}-{}", event.id, i, j),
+                    json!({
+                        "prov:generatedEntity": artifact_qn(*out),
+                        "prov:usedEntity": artifact_qn(*inp),
+                        "prov:activity": ev,
+                    }),
+                );
+            }
+        }
+    }
+
+    let mut doc = Map::new();
+    doc.insert(
+        "prefix".to_string(),
+        json!({
+            "capcf": CAPCF_NS,
+            "artifact": ARTIFACT_NS,
+            "event": EVENT_NS,
+            "agent": AGENT_NS,
+        }),
+    );
+    for (key, section) in [
+        ("entity", entity),
+        ("activity", activity),
+        ("agent", agent),
+        ("used", used),
+        ("wasGeneratedBy", generated),
+        ("wasAssociatedWith", associated),
+        ("wasDerivedFrom", derived),
+    ] {
+        if !section.is_empty() {
+            doc.insert(key.to_string(), Value::Object(section));
+        }
+    }
+    Value::Object(doc)
+}
+
+fn turtle_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Serializes events and the artifacts they touch as PROV-O in Turtle.
+pub fn to_prov_turtle(events: &[Event], artifacts: &[Artifact]) -> String {
+    let mut out = String::new();
+    out.push_str("@prefix prov: <http://www.w3.org/ns/prov#> .\n");
+    out.push_str("@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n");
+    out.push_str("@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .\n");
+    let _ = writeln!(out, "@prefix capcf: <{}> .", CAPCF_NS);
+
+    for artifact in artifacts {
+        let _ = write!(
+            out,
+            "\n<{}{}> a prov:Entity ;\n    rdfs:label {} ;\n    capcf:version {} ;\n    capcf:contentHash {} ;\n    capcf:metadata {} ;\n    capcf:registeredAt {}^^xsd:dateTime .\n",
+            ARTIFACT_NS,
+            artifact.id,
+            turtle_string(&artifact.name),
+            turtle_string(&artifact.version),
+            turtle_string(&artifact.content_hash),
+            turtle_string(&artifact.metadata.to_string()),
+            turtle_string(&time(&artifact.registered_at)),
+        );
+    }
+
+    let mut agents = BTreeMap::new();
+    for event in events {
+        let agent = format!("<{}{}>", AGENT_NS, encode_local(&event.actor));
+        agents.insert(agent.clone(), event.actor.clone());
+        let t = format!("{}^^xsd:dateTime", turtle_string(&time(&event.timestamp)));
+        let _ = write!(
+            out,
+            "\n<{}{}> a prov:Activity ;\n    prov:startedAtTime {} ;\n    prov:endedAtTime {} ;\n    prov:wasAssociatedWith {} ;\n    capcf:operation {} ;\n    capcf:context {}",
+            EVENT_NS,
+            event.id,
+            t,
+            t,
+            agent,
+            turtle_string(&event.operation),
+            turtle_string(&event.context.to_string()),
+        );
+        if let Some(sig) = &event.signature {
+            let _ = write!(
+                out,
+                " ;\n    capcf:signature {} ;\n    capcf:signatureAlgorithm {}",
+                turtle_string(&hex::encode(&sig.signature)),
+                turtle_string(&sig.algorithm),
+            );
+        }
+        for inp in &event.in_artifacts {
+            let _ = write!(out, " ;\n    prov:used <{}{}>", ARTIFACT_NS, inp);
+        }
+        out.push_str(" .\n");
+
+        for out_id in &event.out_artifacts {
+            let _ = write!(out, "<{}{}> prov:wasGeneratedBy <{}{}>", ARTIFACT_NS, out_id, EVENT_NS, event.id);
+            for inp in &event.in_artifacts {
+                let _ = write!(out, " ;\n    prov:wasDerivedFrom <{}{}>", ARTIFACT_NS, inp);
+            }
+            out.push_str(" .\n");
+        }
+    }
+
+    for (agent, actor) in agents {
+        let _ = write!(out, "\n{} a prov:Agent ;\n    rdfs:label {} .\n", agent, turtle_string(&actor));
+    }
+    out
+}
+
+/// An entity read from a PROV-JSON document.
+#[derive(Debug, Clone)]
+pub struct ImportedEntity {
+    /// Qualified name in the source document.
+    pub prov_id: String,
+    /// Set when the entity names an artifact of this service (`artifact:<uuid>`).
+    pub artifact_id: Option<Uuid>,
+    /// Artifact to register when `artifact_id` is unknown to the registry.
+    pub artifact: Artifact,
+}
+
+/// An activity read from a PROV-JSON document, with its inputs and outputs
+/// still given as qualified entity names.
+#[derive(Debug, Clone)]
+pub struct ImportedActivity {
+    pub prov_id: String,
+    /// Set when the activity names an event of this service (`event:<uuid>`).
+    pub event_id: Option<Uuid>,
+    pub actor: String,
+    pub operation: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub context: Value,
+    pub used: Vec<String>,
+    pub generated: Vec<String>,
+}
+
+/// Contents of a PROV-JSON document, ready to be registered and logged.
+#[derive(Debug, Clone, Default)]
+pub struct ProvImport {
+    pub entities: Vec<ImportedEntity>,
+    /// Activities ordered by start time, so derivations are logged after their inputs.
+    pub activities: Vec<ImportedActivity>,
+}
+
+fn invalid(msg: impl Into<String>) -> ProvenanceError {
+    ProvenanceError::InvalidProv(msg.into())
+}
+
+/// Reads a plain or typed (`{"$": ..., "type": ...}`) PROV-JSON literal as a string.
+fn literal(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(o) => o.get("$").and_then(Value::as_str).map(str::to_string),
+        Value::Array(items) => literal(items.first()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn section<'a>(doc: &'a Value, name: &str) -> Result<Vec<(&'a String, &'a Value)>, ProvenanceError> {
+    match doc.get(name) {
+        None => Ok(Vec::new()),
+        Some(Value::Object(map)) => Ok(map.iter().collect()),
+        Some(_) => Err(invalid(format!("`{}` must be an object", name))),
+    }
+}
+
+fn reference(record: &Value, key: &str) -> Result<String, ProvenanceError> {
+    record
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| invalid(format!("relation is missing `{}`", key)))
+}
+
+fn activity_mut<'a>(
+    activities: &'a mut BTreeMap<String, ImportedActivity>,
+    id: &str,
+) -> Result<&'a mut ImportedActivity, ProvenanceError> {
+    activities
+        .get_mut(id)
+        .ok_or_else(|| invalid(format!("unknown activity {}", id)))
+}
+
+/// Parses a PROV-JSON document into entities and activities.
+/// Entities named `artifact:<uuid>` refer back to artifacts of this service; any other
+/// entity becomes a new artifact. Each activity becomes one event, and so does each
+/// `wasDerivedFrom` relation that names no activity.
+pub fn parse_prov_json(doc: &Value) -> Result<ProvImport, ProvenanceError> {
+    if !doc.is_object() {
+        return Err(invalid("document must be a JSON object"));
+    }
+    let mut import = ProvImport::default();
+    let mut known: HashSet<String> = HashSet::new();
+
+    let mut add_entity = |import: &mut ProvImport, prov_id: &str, attrs: Option<&Value>| {
+        if !known.insert(prov_id.to_string()) {
+            return;
+        }
+        let artifact_id = prov_id.strip_prefix("artifact:").and_then(|id| Uuid::parse_str(id).ok());
+        let attr = |key: &str| literal(attrs.and_then(|a| a.get(key)));
+        let metadata = attr("capcf:metadata")
+            .and_then(|m| serde_json::from_str(&m).ok())
+            .unwrap_or_else(|| json!({}));
+        import.entities.push(ImportedEntity {
+            prov_id: prov_id.to_string(),
+            artifact_id,
+            artifact: Artifact {
+                id: Uuid::nil(),
+                name: attr("prov:label").unwrap_or_else(|| prov_id.to_string()),
+                version: attr("capcf:version").unwrap_or_else(|| "1".to_string()),
+                content_hash: attr("capcf:contentHash").unwrap_or_default(),
+                metadata: json!({ "prov:id": prov_id, "imported": metadata }),
+                metadata_digest: String::new(),
+                registered_at: Utc::now(),
+                family_id: None,
+                previous_version: None,
+                authorship: Vec::new(),
+            },
+        });
+    };
+
+    for (id, attrs) in section(doc, "entity")? {
+        add_entity(&mut import, id, Some(attrs));
+    }
+
+    let mut activities: BTreeMap<String, ImportedActivity> = BTreeMap::new();
+    for (id, attrs) in section(doc, "activity")? {
+        let started_at = literal(attrs.get("prov:startTime"))
+            .map(|t| {
+                DateTime::parse_from_rfc3339(&t)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|_| invalid(format!("activity {} has an invalid prov:startTime", id)))
+            })
+            .transpose()?;
+        let context = literal(attrs.get("capcf:context"))
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_else(|| json!({}));
+        let operation = literal(attrs.get("capcf:operation"))
+            .or_else(|| literal(attrs.get("prov:type")))
+            .unwrap_or_else(|| "prov:Activity".to_string());
+        activities.insert(
+            id.clone(),
+            ImportedActivity {
+                prov_id: id.clone(),
+                event_id: id.strip_prefix("event:").and_then(|id| Uuid::parse_str(id).ok()),
+                actor: String::new(),
+                operation,
+                started_at,
+                context,
+                used: Vec::new(),
+                generated: Vec::new(),
+            },
+        );
+    }
+
+    let agents: BTreeMap<String, String> = section(doc, "agent")?
+        .into_iter()
+        .map(|(id, attrs)| {
+            let label = literal(attrs.get("prov:label"))
+                .unwrap_or_else(|| decode_local(id.strip_prefix("agent:").unwrap_or(id)));
+            (id.clone(), label)
+        })
+        .collect();
+
+    for (_, record) in section(doc, "wasAssociatedWith")? {
+        let act = reference(record, "prov:activity")?;
+        let ag = reference(record, "prov:agent")?;
+        let a = activity_mut(&mut activities, &act)?;
+        a.actor = agents
+            .get(&ag)
+            .cloned()
+            .unwrap_or_else(|| decode_local(ag.strip_prefix("agent:").unwrap_or(&ag)));
+    }
+    for (_, record) in section(doc, "used")? {
+        let act = reference(record, "prov:activity")?;
+        let ent = reference(record, "prov:entity")?;
+        add_entity(&mut import, &ent, None);
+        let a = activity_mut(&mut activities, &act)?;
+        if !a.used.contains(&ent) {
+            a.used.push(ent);
+        }
+    }
+    for (_, record) in section(doc, "wasGeneratedBy")? {
+        let act = reference(record, "prov:activity")?;
+        let ent = reference(record, "prov:entity")?;
+        add_entity(&mut import, &ent, None);
+        let a = activity_mut(&mut activities, &act)?;
+        if !a.generated.contains(&ent) {
+            a.generated.push(ent);
+        }
+    }
+    // A derivation through an activity means the activity used one entity and generated
+    // the other; one without an activity becomes an activity of its own
+    for (id, record) in section(doc, "wasDerivedFrom")? {
+        let generated = reference(record, "prov:generatedEntity")?;
+        let used = reference(record, "prov:usedEntity")?;
+        add_entity(&mut import, &generated, None);
+        add_entity(&mut import, &used, None);
+        let a = match record.get("prov:activity") {
+            Some(_) => activity_mut(&mut activities, &reference(record, "prov:activity")?)?,
+            None => activities.entry(id.clone()).or_insert_with(|| ImportedActivity {
+                prov_id: id.clone(),
+                event_id: None,
+                actor: String::new(),
+                operation: "prov:wasDerivedFrom".to_string(),
+                started_at: None,
+                context: json!({}),
+                used: Vec::new(),
+                generated: Vec::new(),
+            }),
+        };
+        if !a.used.contains(&used) {
+            a.used.push(used);
+        }
+        if !a.generated.contains(&generated) {
+            a.generated.push(generated);
+        }
+    }
+
+    let mut activities: Vec<_> = activities.into_values().collect();
+    for a in &mut activities {
+        if a.actor.is_empty() {
+            a.actor = "prov-import".to_string();
+        }
+    }
+    activities.sort_by_key(|a| a.started_at);
+    import.activities = activities;
+    Ok(import)
+}
+
+/// Outcome of importing a PROV-JSON document.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProvImportSummary {
+    /// Artifact each entity of the document was resolved or registered as.
+    pub artifacts: BTreeMap<String, Uuid>,
+    /// Events logged for the document's activities, in log order.
+    pub events: Vec<Event>,
+}
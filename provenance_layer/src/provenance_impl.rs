@@ -1,209 +1,1709 @@
-// Implementation of ProvenanceService using Sled for storage.
+// Implementation of ProvenanceService over a pluggable storage backend.
 // Corresponds to State_PL = (E, A, G_P, B)
 
+use crate::anchor::{self, Anchor, AnchorVerification};
+use crate::blobstore::BlobStore;
+use crate::bundle::{self, BlockProof, BundleImportSummary, ProvenanceBundle};
+use crate::event_index::{self, EventIndex};
+use crate::graph::{FilteredGraph, GraphView, LayeredGraph, ProvenanceGraph, StoredGraph};
+use crate::keystore::{self, KeyChange, KeyRecord, Keystore, SignatureVerification};
+use crate::prov::{self, ProvImportSummary};
+use crate::storage::{Batch, Storage, StorageConfig};
+use crate::subscription::{self, FeedCursor, Notification, Subscription, SubscriptionFilter};
+use crate::*;
 use async_trait::async_trait;
-use sled::{Db, Tree};
+use chrono::Utc;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use provenance_layer::*;
-use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
-use ring::rand::SystemRandom;
-
-pub struct SledProvenanceService {
-    db: Arc<Db>,
-    events_tree: Tree,
-    artifacts_tree: Tree,
-    blocks_tree: Tree,
-    graph: Arc<Mutex<HashMap<Uuid, Vec<Uuid>>>>, // Simple adjacency list for G_P
+use tokio::sync::{broadcast, Mutex, Notify};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ProvenanceServiceImpl {
+    storage: Arc<dyn Storage>,
+    keystore: Keystore,
+    blobs: BlobStore, // Artifact content, addressed by SHA-256
+    graph: StoredGraph, // Forward and reverse adjacency for G_P
+    event_index: EventIndex, // Events by time, actor, operation and artifact
     pending_events: Arc<Mutex<Vec<Event>>>,
-    last_block_hash: Arc<Mutex<String>>,
+    chain_head: Arc<Mutex<ChainHead>>,
+    seal_notify: Arc<Notify>, // Wakes the sealer when events are logged
+    anchors: Vec<Arc<dyn Anchor>>, // External witnesses for sealed block hashes
+    notifications: broadcast::Sender<Notification>, // Live feed of logged events and sealed blocks
+    sealer: Arc<std::sync::Mutex<SealerStatus>>, // Outcome of the background sealer's last attempts
 }
 
-impl Clone for SledProvenanceService {
-    fn clone(&self) -> Self {
-        Self {
-            db: self.db.clone(),
-            events_tree: self.db.open_tree("events").unwrap(),
-            artifacts_tree: self.db.open_tree("artifacts").unwrap(),
-            blocks_tree: self.db.open_tree("blocks").unwrap(),
-            graph: self.graph.clone(),
-            pending_events: self.pending_events.clone(),
-            last_block_hash: self.last_block_hash.clone(),
-        }
-    }
+/// Registrations and log entries staged to be committed in one batch. What the batch
+/// adds is kept aside so that later entries are validated against it too.
+#[derive(Default)]
+struct Append {
+    batch: Batch,
+    next_seq: u64,
+    artifacts: HashSet<Uuid>, // Artifacts the batch registers
+    versions: HashMap<Vec<u8>, Uuid>, // Family version slots the batch fills
+    edges: ProvenanceGraph, // Derivation edges of the batch's events
+    events: Vec<Event>,
 }
 
-impl SledProvenanceService {
+/// Sequence number -> event; the append-only log.
+pub(crate) const LOG_TREE: &str = "log";
+/// Event id -> sequence number.
+pub(crate) const EVENT_SEQS_TREE: &str = "event_seqs";
+pub(crate) const ARTIFACTS_TREE: &str = "artifacts";
+/// Height -> block.
+pub(crate) const BLOCKS_TREE: &str = "blocks";
+/// Block id -> height.
+const BLOCK_IDS_TREE: &str = "block_ids";
+/// Height || anchor name -> anchor receipt for the block.
+const BLOCK_ANCHORS_TREE: &str = "block_anchors";
+/// Height || anchor name -> last failure of an anchor with no receipt for the block.
+const BLOCK_ANCHOR_FAILURES_TREE: &str = "block_anchor_failures";
+/// Height -> leaf hash of the block in the checkpoint tree.
+const BLOCK_LEAVES_TREE: &str = "block_leaves";
+/// Tree size -> signed checkpoint.
+const CHECKPOINTS_TREE: &str = "checkpoints";
+/// Chain head and migration markers.
+pub(crate) const CHAIN_TREE: &str = "chain";
+/// Event id -> height of the block sealing it.
+const EVENT_BLOCKS_TREE: &str = "event_blocks";
+/// Log order -> id of an event not yet sealed.
+const PENDING_TREE: &str = "pending";
+/// Family id || version index -> artifact id.
+const FAMILIES_TREE: &str = "families";
+/// Event ID in an imported bundle -> ID of the event logged for it.
+const BUNDLE_EVENTS_TREE: &str = "bundle_events";
+/// Event id -> event, as written before sequence numbers.
+pub(crate) const LEGACY_EVENTS_TREE: &str = "events";
+
+/// Key of the persisted chain head in the `chain` tree.
+pub(crate) const CHAIN_HEAD_KEY: &str = "head";
+/// Marker in the `chain` tree set once the log is keyed by sequence number. Holds the
+/// number of events migrated, which carry no `seq`; empty if set before it was counted.
+pub(crate) const LOG_SEQUENCED_KEY: &str = "log_sequenced";
+/// Marker in the `chain` tree set once unsealed events are tracked in the `pending` tree.
+const PENDING_TRACKED_KEY: &str = "pending_tracked";
+/// Marker in the `chain` tree set once every event's edges are stored in the graph trees.
+const GRAPH_INDEXED_KEY: &str = "graph_indexed";
+/// Marker in the `chain` tree set once every event is in the secondary indexes.
+const EVENTS_INDEXED_KEY: &str = "events_indexed";
+/// Marker in the `chain` tree set once every block is in the `block_ids` tree.
+const BLOCKS_INDEXED_KEY: &str = "blocks_indexed";
+/// Marker in the `chain` tree set once every block has a checkpoint tree leaf.
+const BLOCK_LEAVES_KEY: &str = "block_leaves_indexed";
+/// Default blob store directory; override with `PL_BLOB_DIR`.
+const DEFAULT_BLOB_DIR: &str = "provenance_blobs";
+/// Default number of adjacency lists kept in memory; `PL_GRAPH_CACHE_SIZE=0` disables caching.
+const DEFAULT_GRAPH_CACHE_SIZE: usize = 10_000;
+
+impl ProvenanceServiceImpl {
+    /// Opens the service with the storage backend, directories and anchors given by the
+    /// environment.
     pub async fn new() -> Result<Self, ProvenanceError> {
-        let db = sled::open("provenance_db")?;
-        let events_tree = db.open_tree("events")?;
-        let artifacts_tree = db.open_tree("artifacts")?;
-        let blocks_tree = db.open_tree("blocks")?;
-        let graph = Arc::new(Mutex::new(HashMap::new()));
-        let pending_events = Arc::new(Mutex::new(Vec::new()));
-        let last_block_hash = Arc::new(Mutex::new("genesis".to_string()));
-
-        // Load existing graph from events
-        let mut g = graph.lock().await;
-        for result in events_tree.iter() {
-            let (_key, value) = result?;
-            let event: Event = serde_json::from_slice(&value)?;
-            for &out in &event.out_artifacts {
-                g.entry(out).or_insert(Vec::new());
-                for &inp in &event.in_artifacts {
-                    if let Some(children) = g.get_mut(&inp) {
-                        if !children.contains(&out) {
-                            children.push(out);
-                        }
-                    }
+        let storage = StorageConfig::from_env()?.open()?;
+        let blobs = BlobStore::open(std::env::var("PL_BLOB_DIR").unwrap_or_else(|_| DEFAULT_BLOB_DIR.to_string()))?;
+        let cache_size = std::env::var("PL_GRAPH_CACHE_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_GRAPH_CACHE_SIZE);
+        let mut service = Self::open(storage, blobs, cache_size).await?;
+        for anchor in anchor::from_env(&service.keystore)? {
+            service = service.with_anchor(anchor);
+        }
+        Ok(service)
+    }
+
+    /// Opens the service on `storage`, migrating data written by older versions and
+    /// recovering events that were logged but not sealed.
+    pub async fn open(
+        storage: Arc<dyn Storage>,
+        blobs: BlobStore,
+        graph_cache_size: usize,
+    ) -> Result<Self, ProvenanceError> {
+        let keystore = Keystore::open(storage.clone());
+
+        // Databases written before sequence numbers kept events keyed by ID alone;
+        // number them once in timestamp order. The stored bytes are kept unchanged
+        // so blocks that already seal them still verify.
+        if !storage.contains(CHAIN_TREE, LOG_SEQUENCED_KEY.as_bytes())? {
+            let mut legacy = Vec::new();
+            for entry in storage.iter(LEGACY_EVENTS_TREE) {
+                let (_key, value) = entry?;
+                let event: Event = serde_json::from_slice(&value)?;
+                legacy.push((event.timestamp, event.id, value));
+            }
+            legacy.sort_by_key(|(timestamp, id, _)| (*timestamp, *id));
+            // Start over if an earlier attempt was interrupted
+            storage.drop_tree(LOG_TREE)?;
+            storage.drop_tree(EVENT_SEQS_TREE)?;
+            let migrated = legacy.len() as u64;
+            for (seq, (_, id, value)) in legacy.into_iter().enumerate() {
+                let seq = (seq as u64).to_be_bytes();
+                let mut batch = Batch::new();
+                batch.insert(LOG_TREE, seq, value);
+                batch.insert(EVENT_SEQS_TREE, id.to_string(), seq);
+                storage.commit(batch)?;
+            }
+            storage.insert(CHAIN_TREE, LOG_SEQUENCED_KEY, migrated.to_be_bytes())?;
+            storage.flush()?;
+            storage.drop_tree(LEGACY_EVENTS_TREE)?;
+        }
+
+        // Blocks sealed before they could be looked up by ID get indexed once
+        if !storage.contains(CHAIN_TREE, BLOCKS_INDEXED_KEY.as_bytes())? {
+            for entry in storage.iter(BLOCKS_TREE) {
+                let (height, value) = entry?;
+                let block: Block = serde_json::from_slice(&value)?;
+                storage.insert(BLOCK_IDS_TREE, block.id.as_bytes(), height)?;
+            }
+            storage.insert(CHAIN_TREE, BLOCKS_INDEXED_KEY, [])?;
+            storage.flush()?;
+        }
+
+        // Blocks sealed before checkpoints existed get their leaves once
+        if !storage.contains(CHAIN_TREE, BLOCK_LEAVES_KEY.as_bytes())? {
+            for entry in storage.iter(BLOCKS_TREE) {
+                let (height, value) = entry?;
+                let block: Block = serde_json::from_slice(&value)?;
+                storage.insert(BLOCK_LEAVES_TREE, height, merkle::block_leaf_hash(&block)?)?;
+            }
+            storage.insert(CHAIN_TREE, BLOCK_LEAVES_KEY, [])?;
+            storage.flush()?;
+        }
+
+        // Resume the chain where the last run left it
+        let head = match storage.get(CHAIN_TREE, CHAIN_HEAD_KEY.as_bytes())? {
+            Some(value) => serde_json::from_slice(&value)?,
+            None => ChainHead::default(),
+        };
+        let chain_head = Arc::new(Mutex::new(head));
+
+        // Databases written before pending events were tracked durably may hold
+        // events that never made it into a block; queue them once.
+        if !storage.contains(CHAIN_TREE, PENDING_TRACKED_KEY.as_bytes())? {
+            for entry in storage.iter(EVENT_SEQS_TREE) {
+                let (key, _seq) = entry?;
+                if !storage.contains(EVENT_BLOCKS_TREE, &key)? {
+                    storage.insert(PENDING_TREE, storage.generate_id()?.to_be_bytes(), key)?;
                 }
             }
+            storage.insert(CHAIN_TREE, PENDING_TRACKED_KEY, [])?;
+            storage.flush()?;
+        }
+
+        // Recover events logged but not yet sealed, in log order
+        let mut recovered = Vec::new();
+        for entry in storage.iter(PENDING_TREE) {
+            let (_seq, id) = entry?;
+            let event = load_event(storage.as_ref(), &id)?.ok_or_else(|| {
+                ProvenanceError::DatabaseError("pending event missing from the event log".to_string())
+            })?;
+            recovered.push(event);
+        }
+        let pending_events = Arc::new(Mutex::new(recovered));
+
+        // Databases written before the graph was stored on disk get indexed once
+        let graph = StoredGraph::open(storage.clone(), graph_cache_size);
+        if !storage.contains(CHAIN_TREE, GRAPH_INDEXED_KEY.as_bytes())? {
+            for entry in storage.iter(LOG_TREE) {
+                let (_seq, value) = entry?;
+                let event: Event = serde_json::from_slice(&value)?;
+                let mut batch = Batch::new();
+                StoredGraph::stage_event(&mut batch, &event);
+                storage.commit(batch)?;
+            }
+            storage.insert(CHAIN_TREE, GRAPH_INDEXED_KEY, [])?;
+            storage.flush()?;
         }
 
+        // Events logged before the secondary indexes existed get indexed once
+        let event_index = EventIndex::open(storage.clone());
+        if !storage.contains(CHAIN_TREE, EVENTS_INDEXED_KEY.as_bytes())? {
+            for entry in storage.iter(LOG_TREE) {
+                let (_seq, value) = entry?;
+                let event: Event = serde_json::from_slice(&value)?;
+                let mut batch = Batch::new();
+                EventIndex::stage_event(&mut batch, &event);
+                storage.commit(batch)?;
+            }
+            storage.insert(CHAIN_TREE, EVENTS_INDEXED_KEY, [])?;
+            storage.flush()?;
+        }
+
+        // The log key exists before the first checkpoint it signs
+        keystore.provision(CHECKPOINT_SIGNER)?;
+
         Ok(Self {
-            db: Arc::new(db),
-            events_tree,
-            artifacts_tree,
-            blocks_tree,
+            storage,
+            keystore,
+            blobs,
             graph,
+            event_index,
             pending_events,
-            last_block_hash,
+            chain_head,
+            seal_notify: Arc::new(Notify::new()),
+            anchors: Vec::new(),
+            notifications: broadcast::channel(subscription::CHANNEL_CAPACITY).0,
+            sealer: Arc::new(std::sync::Mutex::new(SealerStatus::default())),
         })
     }
+
+    /// Adds an anchor that every newly sealed block is submitted to.
+    pub fn with_anchor(mut self, anchor: Arc<dyn Anchor>) -> Self {
+        self.anchors.push(anchor);
+        self
+    }
 }
 
 #[async_trait]
-impl ProvenanceService for SledProvenanceService {
-    async fn log_event(&mut self, mut event: Event) -> Result<(), ProvenanceError> {
+impl ProvenanceService for ProvenanceServiceImpl {
+    async fn log_event(&mut self, event: Event) -> Result<Event, ProvenanceError> {
+        check_actor(&event.actor)?;
+        // The pending lock serializes writers, so validation and the append are atomic
+        let mut pending = self.pending_events.lock().await;
+        self.append_event(&mut pending, event)
+    }
+
+    async fn register_artifact(&mut self, mut artifact: Artifact) -> Result<Artifact, ProvenanceError> {
+        self.prepare_artifact(&mut artifact).await?;
+        artifact.family_id = Some(artifact.id);
+        artifact.previous_version = None;
+        self.store_version(&artifact, 0, None)?;
+        Ok(artifact)
+    }
+
+    async fn register_artifact_version(
+        &mut self,
+        previous: Uuid,
+        mut artifact: Artifact,
+        actor: &str,
+    ) -> Result<(Artifact, Event), ProvenanceError> {
+        check_actor(actor)?;
+        self.prepare_artifact(&mut artifact).await?;
+        // The pending lock keeps the version and its derive event in log order with
+        // other writes; both are committed in one batch, so neither exists without the other
+        let mut pending = self.pending_events.lock().await;
+        let prev = self.get_artifact(previous).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        let family = prev.family_id.unwrap_or(prev.id);
+        let versions = self.family_versions(family)?;
+        if let Some(&latest) = versions.last() {
+            if latest != prev.id {
+                return Err(ProvenanceError::NotLatestVersion { latest });
+            }
+        }
+
+        artifact.family_id = Some(family);
+        artifact.previous_version = Some(prev.id);
+        // Artifacts registered before versioning are the first version of their own family
+        let index = versions.len().max(1) as u64;
+        let mut append = self.begin_append()?;
+        let seq = append.next_seq;
+        if !self.stage_version(&mut append, &artifact, index, Some(prev.id))? {
+            return Err(self.not_latest(family)?);
+        }
+        let event = Event {
+            id: Uuid::nil(),
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            in_artifacts: vec![prev.id],
+            operation: "derive".to_string(),
+            out_artifacts: vec![artifact.id],
+            context: serde_json::json!({
+                "family_id": family,
+                "from_version": prev.version,
+                "to_version": artifact.version,
+            }),
+            signature: None,
+            seq: None,
+            origin: None,
+        };
+        let event = self.sign_event(&append, event)?;
+        let event = self.stage_event(&mut append, event)?;
+        if !self.commit_append(&mut pending, append)? {
+            return match self.not_latest(family)? {
+                ProvenanceError::NotLatestVersion { latest } if latest != prev.id => {
+                    Err(ProvenanceError::NotLatestVersion { latest })
+                }
+                _ => Err(ProvenanceError::AppendConflict(seq)),
+            };
+        }
+        Ok((artifact, event))
+    }
+
+    async fn list_versions(&self, artifact_id: Uuid) -> Result<Vec<Artifact>, ProvenanceError> {
+        let artifact = self.get_artifact(artifact_id).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        let ids = self.family_versions(artifact.family_id.unwrap_or(artifact.id))?;
+        if ids.is_empty() {
+            return Ok(vec![artifact]);
+        }
+        let mut versions = Vec::with_capacity(ids.len());
+        for id in ids {
+            versions.push(self.get_artifact(id).await?.ok_or_else(|| {
+                ProvenanceError::DatabaseError("artifact version missing from the registry".to_string())
+            })?);
+        }
+        Ok(versions)
+    }
+
+    async fn latest_version(&self, artifact_id: Uuid) -> Result<Artifact, ProvenanceError> {
+        self.list_versions(artifact_id)
+            .await?
+            .pop()
+            .ok_or(ProvenanceError::ArtifactNotFound)
+    }
+
+    async fn diff_metadata(&self, from: Uuid, to: Uuid) -> Result<MetadataDiff, ProvenanceError> {
+        let a = self.get_artifact(from).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        let b = self.get_artifact(to).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        Ok(MetadataDiff {
+            from,
+            to,
+            changes: diff::json_diff(&a.metadata, &b.metadata),
+            from_version: a.version,
+            to_version: b.version,
+        })
+    }
+
+    async fn cosign_artifact(
+        &mut self,
+        artifact_id: Uuid,
+        signer: &str,
+        role: Option<String>,
+    ) -> Result<Artifact, ProvenanceError> {
+        check_actor(signer)?;
+        let key = artifact_id.to_string();
+        loop {
+            let current = self
+                .storage
+                .get(ARTIFACTS_TREE, key.as_bytes())?
+                .ok_or(ProvenanceError::ArtifactNotFound)?;
+            let mut artifact: Artifact = serde_json::from_slice(&current)?;
+            if artifact.authorship.iter().any(|entry| entry.signer == signer) {
+                return Err(ProvenanceError::AlreadySigned(signer.to_string()));
+            }
+
+            let previous = artifact.authorship_head()?;
+            self.keystore.ensure_key(signer)?;
+            let signed_at = Utc::now();
+            let payload = AuthorshipEntry::signing_payload(&artifact, &previous, signer, role.as_deref(), signed_at)?;
+            let signature = self.keystore.sign(signer, &payload)?;
+            artifact.authorship.push(AuthorshipEntry {
+                signer: signer.to_string(),
+                role: role.clone(),
+                signed_at,
+                previous,
+                signature,
+            });
+
+            // A co-signer that appended concurrently wins; link to its entry and retry
+            let mut batch = Batch::new();
+            batch.expect(ARTIFACTS_TREE, &key, Some(&current));
+            batch.insert(ARTIFACTS_TREE, &key, serde_json::to_vec(&artifact)?);
+            if self.storage.commit(batch)? {
+                self.storage.flush()?;
+                return Ok(artifact);
+            }
+        }
+    }
+
+    async fn verify_authorship(
+        &self,
+        artifact_id: Uuid,
+        required: Vec<String>,
+    ) -> Result<AuthorshipVerification, ProvenanceError> {
+        let artifact = self.get_artifact(artifact_id).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        artifact.verify_authorship_with(required, |signer| self.keystore.history(signer))
+    }
+
+    async fn verify_signature(
+        &self,
+        data: &[u8],
+        signature: &Signature,
+        signed_at: DateTime<Utc>,
+    ) -> Result<SignatureVerification, ProvenanceError> {
+        self.keystore.verify_at(data, signature, signed_at)
+    }
+
+    async fn register_key(&mut self, actor: &str, pkcs8: Option<Vec<u8>>, admin: bool) -> Result<KeyChange, ProvenanceError> {
+        check_actor(actor)?;
+        let pkcs8 = match pkcs8 {
+            Some(pkcs8) => pkcs8,
+            None => keystore::generate_pkcs8()?,
+        };
+        let mut pending = self.pending_events.lock().await;
+        // The key commits with the event recording it, and signs it unless the admin does
+        let mut append = self.begin_append()?;
+        let key = self.keystore.stage_register(&mut append.batch, actor, &pkcs8, Utc::now())?;
+        let signer = if admin { None } else { Some(&pkcs8[..]) };
+        let event = self.sign_event_with(&append, key_event(&key, "key_register", admin), signer)?;
+        self.commit_key_change(&mut pending, append, event, key, ProvenanceError::KeyExists(actor.to_string()))
+    }
+
+    async fn rotate_key(&mut self, actor: &str, pkcs8: Option<Vec<u8>>, admin: bool) -> Result<KeyChange, ProvenanceError> {
+        check_actor(actor)?;
+        let pkcs8 = match pkcs8 {
+            Some(pkcs8) => pkcs8,
+            None => keystore::generate_pkcs8()?,
+        };
+        let public_key = keystore::public_key_of(&pkcs8)?;
+        let mut pending = self.pending_events.lock().await;
+        let current = self.current_key(actor)?;
+        let mut event = key_event(&current, "key_rotate", admin);
+        event.context["next_public_key"] = hex::encode(public_key).into();
+        let mut append = self.begin_append()?;
+        let event = self.sign_event(&append, event)?;
+        // The old key signed the rotation, so it stays valid up to just after the event
+        let key = self.keystore.stage_rotate(&mut append.batch, actor, &pkcs8, key_change_time(&event))?;
+        self.commit_key_change(&mut pending, append, event, key, key_conflict(actor))
+    }
+
+    async fn revoke_key(&mut self, actor: &str, reason: Option<String>, admin: bool) -> Result<KeyChange, ProvenanceError> {
+        check_actor(actor)?;
+        let mut pending = self.pending_events.lock().await;
+        let mut current = self.current_key(actor)?;
+        current.revocation_reason = reason.clone();
+        let mut append = self.begin_append()?;
+        let event = self.sign_event(&append, key_event(&current, "key_revoke", admin))?;
+        let key = self.keystore.stage_revoke(&mut append.batch, actor, key_change_time(&event), reason)?;
+        self.commit_key_change(&mut pending, append, event, key, key_conflict(actor))
+    }
+
+    async fn key_history(&self, actor: &str) -> Result<Vec<KeyRecord>, ProvenanceError> {
+        self.keystore.history(actor)
+    }
+
+    async fn get_event(&self, id: Uuid) -> Result<Option<Event>, ProvenanceError> {
+        load_event(self.storage.as_ref(), id.to_string().as_bytes())
+    }
+
+    async fn get_artifact(&self, id: Uuid) -> Result<Option<Artifact>, ProvenanceError> {
+        match self.storage.get(ARTIFACTS_TREE, id.to_string().as_bytes())? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_artifact_as_of(&self, id: Uuid, as_of: AsOf) -> Result<Option<Artifact>, ProvenanceError> {
+        let snapshot = self.snapshot(Some(as_of))?;
+        self.artifact_record(id, snapshot.as_ref()).await
+    }
+
+    async fn list_artifacts(&self, cursor: Option<String>, limit: Option<usize>) -> Result<ArtifactPage, ProvenanceError> {
+        // Artifacts are keyed by ID, so the cursor is the last ID of the previous page
+        let start = match cursor {
+            Some(cursor) => {
+                let id = Uuid::parse_str(&cursor).map_err(|_| ProvenanceError::InvalidCursor)?;
+                [id.to_string().as_bytes(), &[0]].concat()
+            }
+            None => Vec::new(),
+        };
+        let mut artifacts: Vec<Artifact> = Vec::new();
+        for result in self.storage.range(ARTIFACTS_TREE, &start, None, false) {
+            if limit.is_some_and(|limit| artifacts.len() == limit) {
+                let next_cursor = artifacts.last().map(|a| a.id.to_string());
+                return Ok(ArtifactPage { artifacts, next_cursor });
+            }
+            let (_key, value) = result?;
+            artifacts.push(serde_json::from_slice(&value)?);
+        }
+        Ok(ArtifactPage { artifacts, next_cursor: None })
+    }
+
+    async fn get_lineage(&self, artifact_id: Uuid, query: LineageQuery) -> Result<Lineage, ProvenanceError> {
+        let direction = query.direction;
+        let mut lineage = self.lineage(artifact_id, query).await?;
+        let snapshot = lineage.as_of.clone();
+        self.describe_edges(&mut lineage.edges, snapshot.as_ref()).await?;
+
+        // A forward traversal has no edges into the artifact, so describe those separately
+        let mut derivations: Vec<LineageEdge> =
+            lineage.edges.iter().filter(|edge| edge.child == artifact_id).cloned().collect();
+        if direction == LineageDirection::Forward {
+            let height = snapshot.as_ref().map(|snapshot| snapshot.height);
+            for (parent, event_id) in self.graph.parent_edges(artifact_id)? {
+                if height.map_or(Ok(true), |height| self.sealed_by(event_id, height))? {
+                    derivations.push(LineageEdge { parent, child: artifact_id, event_id, change: None });
+                }
+            }
+            self.describe_edges(&mut derivations, snapshot.as_ref()).await?;
+        }
+        let summaries: Vec<String> = derivations
+            .into_iter()
+            .filter_map(|edge| edge.change.map(|change| change.summary))
+            .collect();
+        lineage.changes = summaries.join("\n");
+        Ok(lineage)
+    }
+
+    async fn diff_artifacts(&self, from: Uuid, to: Uuid) -> Result<ArtifactDiff, ProvenanceError> {
+        let a = self.get_artifact(from).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        let b = self.get_artifact(to).await?.ok_or(ProvenanceError::ArtifactNotFound)?;
+        let mut path = match self.graph.path(from, to)? {
+            Some(path) => path,
+            None => self.graph.path(to, from)?.ok_or(ProvenanceError::NoLineagePath { from, to })?,
+        };
+        self.describe_edges(&mut path, None).await?;
+        let content = match (
+            self.blobs.read(&a.content_hash, diff::MAX_CONTENT_BYTES).await?,
+            self.blobs.read(&b.content_hash, diff::MAX_CONTENT_BYTES).await?,
+        ) {
+            (Some(x), Some(y)) => diff::content_diff(&x, &y),
+            _ => None,
+        };
+        Ok(ArtifactDiff {
+            from,
+            to,
+            path,
+            metadata: diff::json_diff(&a.metadata, &b.metadata),
+            content,
+        })
+    }
+
+    async fn get_events(&self, filter: Option<EventFilter>) -> Result<Vec<Event>, ProvenanceError> {
+        let query = EventQuery { filter: filter.unwrap_or_default(), ..EventQuery::default() };
+        Ok(self.query_events(query).await?.events)
+    }
+
+    async fn query_events(&self, query: EventQuery) -> Result<EventPage, ProvenanceError> {
+        let cursor = query.cursor.as_deref().map(event_index::decode_cursor).transpose()?;
+        let snapshot = self.snapshot(query.as_of)?;
+        let mut events = Vec::new();
+        let mut last_position: Option<Vec<u8>> = None;
+        for result in self.event_index.scan(&query.filter, query.order, cursor.as_deref()) {
+            let (position, id) = result?;
+            let event = self.get_event(id).await?.ok_or_else(|| {
+                ProvenanceError::DatabaseError("indexed event missing from the event log".to_string())
+            })?;
+            if !query.filter.matches(&event) {
+                continue;
+            }
+            if let Some(snapshot) = &snapshot {
+                if !self.sealed_by(event.id, snapshot.height)? {
+                    continue;
+                }
+            }
+            if query.limit.is_some_and(|limit| events.len() == limit) {
+                // A further match exists, so the page ends at the previous event
+                let next_cursor = last_position.as_deref().map(event_index::encode_cursor);
+                return Ok(EventPage { events, next_cursor, next_seq: None, as_of: snapshot });
+            }
+            events.push(event);
+            last_position = Some(position);
+        }
+        Ok(EventPage { events, next_cursor: None, next_seq: None, as_of: snapshot })
+    }
+
+    async fn events_since(
+        &self,
+        since_seq: u64,
+        filter: EventFilter,
+        limit: Option<usize>,
+    ) -> Result<EventPage, ProvenanceError> {
+        let mut events = Vec::new();
+        let mut next_seq = since_seq;
+        for result in self.storage.range(LOG_TREE, &since_seq.to_be_bytes(), None, false) {
+            if limit.is_some_and(|limit| events.len() == limit) {
+                break;
+            }
+            let (key, value) = result?;
+            next_seq = decode_seq(&key)? + 1;
+            let event: Event = serde_json::from_slice(&value)?;
+            if filter.matches(&event) {
+                events.push(event);
+            }
+        }
+        Ok(EventPage { events, next_cursor: None, next_seq: Some(next_seq), as_of: None })
+    }
+
+    async fn create_block(&mut self) -> Result<Option<Block>, ProvenanceError> {
+        let mut pending = self.pending_events.lock().await;
+        if pending.is_empty() {
+            return Ok(None);
+        }
+
+        let mut head = self.chain_head.lock().await;
+        let events = pending.clone();
+        let merkle_root = hex::encode(merkle::events_root(&events)?);
+        let hash = Block::compute_rooted_hash(&merkle_root, &head.hash);
+
+        let block = Block {
+            id: Uuid::new_v4(),
+            height: head.height,
+            events,
+            merkle_root: Some(merkle_root),
+            hash: hash.clone(),
+            previous_hash: head.hash.clone(),
+            created_at: Utc::now(),
+            anchors: Vec::new(),
+            anchor_failures: Vec::new(),
+        };
+        let new_head = ChainHead {
+            hash,
+            height: head.height + 1,
+        };
+
+        // Blocks are keyed by big-endian height so iteration follows the chain
+        let key = block.height.to_be_bytes();
+        let value = serde_json::to_vec(&block)?;
+        let head_value = serde_json::to_vec(&new_head)?;
+        // The pending lock is held, so the pending tree holds exactly these events
+        let mut batch = Batch::new();
+        batch.insert(BLOCKS_TREE, key, value);
+        batch.insert(BLOCK_IDS_TREE, block.id.as_bytes(), key);
+        batch.insert(BLOCK_LEAVES_TREE, key, merkle::block_leaf_hash(&block)?);
+        batch.insert(CHAIN_TREE, CHAIN_HEAD_KEY, head_value);
+        for event in &block.events {
+            batch.insert(EVENT_BLOCKS_TREE, event.id.to_string(), key);
+        }
+        for entry in self.storage.iter(PENDING_TREE) {
+            let (seq, _id) = entry?;
+            batch.remove(PENDING_TREE, seq);
+        }
+        self.storage.commit(batch)?;
+        self.storage.flush()?;
+
+        pending.clear();
+        *head = new_head;
+        let _ = self.notifications.send(Notification::Block(block.clone()));
+        drop(head);
+        drop(pending);
+
+        // The block is sealed either way; an anchor that fails is recorded on it and
+        // can be retried later
+        let mut block = block;
+        self.anchor_sealed(&mut block).await?;
+
+        Ok(Some(block))
+    }
+
+    async fn get_block(&self, id: Uuid) -> Result<Option<Block>, ProvenanceError> {
+        let height = match self.storage.get(BLOCK_IDS_TREE, id.as_bytes())? {
+            Some(height) => height,
+            None => return Ok(None),
+        };
+        match self.storage.get(BLOCKS_TREE, &height)? {
+            Some(value) => Ok(Some(self.load_block(&height, &value)?)),
+            None => Err(ProvenanceError::DatabaseError("indexed block missing from the chain".to_string())),
+        }
+    }
+
+    async fn list_blocks(&self, from_height: u64, limit: Option<usize>) -> Result<Vec<Block>, ProvenanceError> {
+        let mut blocks = Vec::new();
+        for result in self.storage.range(BLOCKS_TREE, &from_height.to_be_bytes(), None, false) {
+            if limit.is_some_and(|limit| blocks.len() == limit) {
+                break;
+            }
+            let (key, value) = result?;
+            blocks.push(self.load_block(&key, &value)?);
+        }
+        Ok(blocks)
+    }
+
+    async fn anchor_block(&mut self, id: Uuid) -> Result<Block, ProvenanceError> {
+        let mut block = self.get_block(id).await?.ok_or(ProvenanceError::BlockNotFound)?;
+        match self.anchor_sealed(&mut block).await? {
+            Some(e) => Err(e),
+            None => Ok(block),
+        }
+    }
+
+    async fn verify_anchors(&self, id: Uuid) -> Result<Vec<AnchorVerification>, ProvenanceError> {
+        let block = self.get_block(id).await?.ok_or(ProvenanceError::BlockNotFound)?;
+        Ok(block
+            .anchors
+            .iter()
+            .map(|receipt| anchor::verify_receipt(&block.hash, receipt))
+            .collect())
+    }
+
+    async fn checkpoint(&self) -> Result<Checkpoint, ProvenanceError> {
+        let leaves = self.block_leaves()?;
+        let tree_size = leaves.len() as u64;
+        let key = tree_size.to_be_bytes();
+        // One checkpoint per tree size; later requests get the one already signed
+        if let Some(value) = self.storage.get(CHECKPOINTS_TREE, &key)? {
+            return Ok(serde_json::from_slice(&value)?);
+        }
+
+        let root_hash = hex::encode(merkle::root(&leaves));
+        let timestamp = Utc::now();
+        let payload = Checkpoint::signing_payload(tree_size, &root_hash, timestamp)?;
+        let checkpoint = Checkpoint {
+            tree_size,
+            root_hash,
+            timestamp,
+            signature: self.keystore.sign(CHECKPOINT_SIGNER, &payload)?,
+        };
+        let mut batch = Batch::new();
+        batch.expect(CHECKPOINTS_TREE, key, None);
+        batch.insert(CHECKPOINTS_TREE, key, serde_json::to_vec(&checkpoint)?);
+        if self.storage.commit(batch)? {
+            self.storage.flush()?;
+            return Ok(checkpoint);
+        }
+        // A concurrent request signed this size first
+        let value = self.storage.get(CHECKPOINTS_TREE, &key)?.ok_or(ProvenanceError::BlockError)?;
+        Ok(serde_json::from_slice(&value)?)
+    }
+
+    async fn consistency_proof(&self, first: u64, second: u64) -> Result<ConsistencyProof, ProvenanceError> {
+        let leaves = self.block_leaves()?;
+        let size = leaves.len() as u64;
+        if first > second || second > size {
+            return Err(ProvenanceError::InvalidTreeSize { first, second, size });
+        }
+        let path = merkle::consistency_path(&leaves[..second as usize], first as usize);
+        Ok(ConsistencyProof {
+            first,
+            second,
+            path: path.iter().map(hex::encode).collect(),
+        })
+    }
+
+    async fn verify_chain(&self) -> Result<ChainVerification, ProvenanceError> {
+        let head = self.chain_head.lock().await.clone();
+        verify_blocks(self.storage.as_ref(), head)
+    }
+
+    async fn get_inclusion_proof(&self, event_id: Uuid) -> Result<InclusionProof, ProvenanceError> {
+        let key = event_id.to_string();
+        let height = match self.storage.get(EVENT_BLOCKS_TREE, key.as_bytes())? {
+            Some(height) => height,
+            None if self.storage.contains(EVENT_SEQS_TREE, key.as_bytes())? => {
+                return Err(ProvenanceError::EventNotSealed)
+            }
+            None => return Err(ProvenanceError::EventNotFound),
+        };
+        let value = self.storage.get(BLOCKS_TREE, &height)?.ok_or(ProvenanceError::BlockError)?;
+        let block: Block = serde_json::from_slice(&value)?;
+        let merkle_root = block.merkle_root.clone().ok_or(ProvenanceError::BlockError)?;
+        let index = block
+            .events
+            .iter()
+            .position(|e| e.id == event_id)
+            .ok_or(ProvenanceError::BlockError)?;
+        let leaves = block
+            .events
+            .iter()
+            .map(merkle::leaf_hash)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(InclusionProof {
+            event_id,
+            block_id: block.id,
+            block_height: block.height,
+            leaf_index: index as u64,
+            tree_size: leaves.len() as u64,
+            audit_path: merkle::audit_path(&leaves, index).iter().map(hex::encode).collect(),
+            merkle_root,
+            previous_hash: block.previous_hash,
+            block_hash: block.hash,
+        })
+    }
+}
+
+impl ProvenanceServiceImpl {
+    /// Assigns the event its id, time and sequence number, signs it with the actor's key
+    /// and appends it to the log. The caller holds the pending lock, so key changes made
+    /// under it take effect between events.
+    fn append_event(&self, pending: &mut Vec<Event>, event: Event) -> Result<Event, ProvenanceError> {
+        let mut append = self.begin_append()?;
+        let seq = append.next_seq;
+        let event = self.sign_event(&append, event)?;
+        let event = self.stage_event(&mut append, event)?;
+        if !self.commit_append(pending, append)? {
+            return Err(ProvenanceError::AppendConflict(seq));
+        }
+        Ok(event)
+    }
+
+    /// Starts staging writes that append to the log after its last event. The caller
+    /// holds the pending lock.
+    fn begin_append(&self) -> Result<Append, ProvenanceError> {
+        let next_seq = match self.storage.last(LOG_TREE)? {
+            Some((key, _)) => decode_seq(&key)? + 1,
+            None => 0,
+        };
+        Ok(Append { next_seq, ..Append::default() })
+    }
+
+    /// Enforces referential integrity and the DAG invariant for a new event, against the
+    /// log and what `append` stages, then assigns its id, time and the next sequence
+    /// number and signs it. The event must be staged next.
+    fn sign_event(&self, append: &Append, event: Event) -> Result<Event, ProvenanceError> {
+        self.sign_event_with(append, event, None)
+    }
+
+    /// As `sign_event`, but with the keypair `pkcs8` instead of the actor's stored key
+    /// if given: a key the actor registers signs the registration it commits with.
+    fn sign_event_with(&self, append: &Append, mut event: Event, pkcs8: Option<&[u8]>) -> Result<Event, ProvenanceError> {
         event.id = Uuid::new_v4();
+        self.validate_event(append, &event)?;
+        if pkcs8.is_none() {
+            self.keystore.ensure_key(&event.actor)?;
+        }
         event.timestamp = Utc::now();
+        event.seq = Some(append.next_seq);
+        event.origin = None;
 
-        // Mock signature for now (use ring in production)
-        event.signature = Some(Signature {
-            signer: event.actor.clone(),
-            signature: vec![0; 64], // Placeholder
-            algorithm: "Ed25519".to_string(),
+        // Sign H(actor, in, op, out, ctx, id, t, seq) with the actor's key
+        let payload = event.signing_payload()?;
+        event.signature = Some(match pkcs8 {
+            Some(pkcs8) => keystore::sign_with(&event.actor, pkcs8, &payload)?,
+            None => self.keystore.sign(&event.actor, &payload)?,
         });
+        Ok(event)
+    }
 
+    /// Stages `event` at the next sequence number. The event, its unsealed marker, its
+    /// graph edges and its index entries are committed together, so a crash before the
+    /// next block cannot leave it out of the chain.
+    fn stage_event(&self, append: &mut Append, mut event: Event) -> Result<Event, ProvenanceError> {
+        let seq = append.next_seq;
+        event.seq = Some(seq);
         let key = event.id.to_string();
         let value = serde_json::to_vec(&event)?;
-        self.events_tree.insert(key, value)?;
-        self.events_tree.flush()?;
-
-        // Update graph
-        let mut g = self.graph.lock().await;
-        for &out in &event.out_artifacts {
-            g.entry(out).or_insert(Vec::new());
-            for &inp in &event.in_artifacts {
-                if let Some(children) = g.get_mut(&inp) {
-                    if !children.contains(&out) {
-                        children.push(out);
+        let seq_key = seq.to_be_bytes();
+        let pending_key = self.storage.generate_id()?.to_be_bytes();
+        let batch = &mut append.batch;
+        // Compare-and-swap against an empty slot: log entries are never replaced
+        batch.expect(LOG_TREE, seq_key, None);
+        batch.expect(EVENT_SEQS_TREE, &key, None);
+        batch.insert(LOG_TREE, seq_key, &value);
+        batch.insert(EVENT_SEQS_TREE, &key, seq_key);
+        batch.insert(PENDING_TREE, pending_key, &key);
+        StoredGraph::stage_event(batch, &event);
+        EventIndex::stage_event(batch, &event);
+        append.edges.add_event(&event);
+        append.next_seq += 1;
+        append.events.push(event.clone());
+        Ok(event)
+    }
+
+    /// Commits everything `append` staged and queues its events for the next block.
+    /// Returns false, having written nothing, if the log or a slot it expected changed.
+    fn commit_append(&self, pending: &mut Vec<Event>, append: Append) -> Result<bool, ProvenanceError> {
+        if !self.storage.commit(append.batch)? {
+            return Ok(false);
+        }
+        self.storage.flush()?;
+        if append.events.is_empty() {
+            return Ok(true);
+        }
+        for event in append.events {
+            self.graph.invalidate(&event);
+            // Add to pending for block
+            pending.push(event.clone());
+            // Sent under the pending lock, so subscribers see the log order
+            let _ = self.notifications.send(Notification::Event(event));
+        }
+        self.seal_notify.notify_one();
+        Ok(true)
+    }
+
+    /// The lineage subgraph of `artifact_id`, without change descriptions.
+    async fn lineage(&self, artifact_id: Uuid, query: LineageQuery) -> Result<Lineage, ProvenanceError> {
+        let snapshot = self.snapshot(query.as_of)?;
+        if self.artifact_record(artifact_id, snapshot.as_ref()).await?.is_none() {
+            return Err(ProvenanceError::ArtifactNotFound);
+        }
+        // As of a snapshot, G_P has only the edges of events sealed in it
+        let height = snapshot.as_ref().map(|snapshot| snapshot.height);
+        let graph = FilteredGraph::new(&self.graph, |event_id| match height {
+            Some(height) => self.sealed_by(event_id, height),
+            None => Ok(true),
+        });
+        let subgraph = graph.traverse(artifact_id, query.direction, query.max_depth)?;
+        let parent_ids = graph.parents(artifact_id)?;
+        let child_ids = graph.children(artifact_id)?;
+
+        let mut events: Vec<Event> = Vec::new();
+        for edge in &subgraph.edges {
+            if events.iter().any(|e| e.id == edge.event_id) {
+                continue;
+            }
+            if let Some(event) = self.get_event(edge.event_id).await? {
+                events.push(event);
+            }
+        }
+        events.sort_by_key(|e| e.timestamp);
+
+        Ok(Lineage {
+            artifact_id,
+            parent_ids,
+            child_ids,
+            changes: String::new(),
+            ancestors: subgraph.ancestors,
+            descendants: subgraph.descendants,
+            edges: subgraph.edges,
+            events,
+            as_of: snapshot,
+        })
+    }
+
+    /// Fills in what each edge changed, from its event and the artifacts' metadata and
+    /// content. Edges whose event or artifacts are missing are left undescribed.
+    async fn describe_edges(&self, edges: &mut [LineageEdge], snapshot: Option<&Snapshot>) -> Result<(), ProvenanceError> {
+        // Records and content are read once per call, however many edges share them
+        let mut artifacts: HashMap<Uuid, Option<Artifact>> = HashMap::new();
+        let mut contents: HashMap<String, Option<Vec<u8>>> = HashMap::new();
+        for edge in edges.iter_mut() {
+            let Some(event) = self.get_event(edge.event_id).await? else {
+                continue;
+            };
+            for id in [edge.parent, edge.child] {
+                if let Entry::Vacant(slot) = artifacts.entry(id) {
+                    slot.insert(self.artifact_record(id, snapshot).await?);
+                }
+            }
+            let (Some(parent), Some(child)) = (&artifacts[&edge.parent], &artifacts[&edge.child]) else {
+                continue;
+            };
+            for hash in [&parent.content_hash, &child.content_hash] {
+                if let Entry::Vacant(slot) = contents.entry(hash.clone()) {
+                    slot.insert(self.blobs.read(hash, diff::MAX_CONTENT_BYTES).await?);
+                }
+            }
+            let content = match (&contents[&parent.content_hash], &contents[&child.content_hash]) {
+                (Some(from), Some(to)) => diff::content_diff(from, to),
+                _ => None,
+            };
+            edge.change = Some(ChangeDescription::new(&event, parent, child, content));
+        }
+        Ok(())
+    }
+
+    /// Resolves `as_of` to the sealed state it names; None for the live state.
+    fn snapshot(&self, as_of: Option<AsOf>) -> Result<Option<Snapshot>, ProvenanceError> {
+        let Some(as_of) = as_of else {
+            return Ok(None);
+        };
+        let sealed = match self.storage.last(BLOCKS_TREE)? {
+            Some((key, _)) => decode_height(&key)? + 1,
+            None => 0,
+        };
+        let height = match as_of {
+            AsOf::Height(height) if height < sealed => height,
+            AsOf::Height(_) => return Err(ProvenanceError::NotSealedAsOf(as_of)),
+            AsOf::Time(time) => {
+                // Blocks are sealed in height order, so the first one sealed after `time`
+                // can be found by bisection
+                let (mut lo, mut hi) = (0, sealed);
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    if self.sealed_block(mid)?.created_at <= time {
+                        lo = mid + 1;
+                    } else {
+                        hi = mid;
                     }
                 }
+                lo.checked_sub(1).ok_or(ProvenanceError::NotSealedAsOf(as_of))?
             }
+        };
+        let block = self.sealed_block(height)?;
+        Ok(Some(Snapshot {
+            height,
+            block_hash: block.hash,
+            sealed_at: block.created_at,
+            tree_size: height + 1,
+        }))
+    }
+
+    /// The block at `height`, without its anchor receipts.
+    fn sealed_block(&self, height: u64) -> Result<Block, ProvenanceError> {
+        let value = self.storage.get(BLOCKS_TREE, &height.to_be_bytes())?.ok_or(ProvenanceError::BlockNotFound)?;
+        Ok(serde_json::from_slice(&value)?)
+    }
+
+    /// Whether `event_id` is sealed in a block at or below `height`.
+    fn sealed_by(&self, event_id: Uuid, height: u64) -> Result<bool, ProvenanceError> {
+        match self.storage.get(EVENT_BLOCKS_TREE, event_id.to_string().as_bytes())? {
+            Some(sealed_at) => Ok(decode_height(&sealed_at)? <= height),
+            None => Ok(false),
         }
+    }
 
-        // Add to pending for block
-        let mut pending = self.pending_events.lock().await;
-        pending.push(event);
+    /// The artifact record, as it stood when `snapshot` was sealed if one is given.
+    async fn artifact_record(&self, id: Uuid, snapshot: Option<&Snapshot>) -> Result<Option<Artifact>, ProvenanceError> {
+        let artifact = self.get_artifact(id).await?;
+        Ok(match snapshot {
+            Some(snapshot) => artifact.and_then(|artifact| artifact.as_of(snapshot.sealed_at)),
+            None => artifact,
+        })
+    }
+
+    /// History record of the key `actor` currently holds.
+    /// Appends `event` with the key change staged in `append`, so that neither commits
+    /// without the other. Keys also change outside the pending lock, when provisioned,
+    /// so a commit that fails with the log unchanged fails with `conflict`.
+    fn commit_key_change(
+        &self,
+        pending: &mut Vec<Event>,
+        mut append: Append,
+        event: Event,
+        key: KeyRecord,
+        conflict: ProvenanceError,
+    ) -> Result<KeyChange, ProvenanceError> {
+        let seq = append.next_seq;
+        let event = self.stage_event(&mut append, event)?;
+        if !self.commit_append(pending, append)? {
+            if self.begin_append()?.next_seq != seq {
+                return Err(ProvenanceError::AppendConflict(seq));
+            }
+            return Err(conflict);
+        }
+        Ok(KeyChange { key, event })
+    }
+
+    fn current_key(&self, actor: &str) -> Result<KeyRecord, ProvenanceError> {
+        match self.keystore.history(actor)?.pop() {
+            Some(key) if key.revoked_at.is_some() => Err(ProvenanceError::KeyRevoked(actor.to_string())),
+            Some(key) => Ok(key),
+            None => Err(ProvenanceError::KeyNotFound(actor.to_string())),
+        }
+    }
 
+    fn validate_event(&self, append: &Append, event: &Event) -> Result<(), ProvenanceError> {
+        for &id in event.in_artifacts.iter().chain(&event.out_artifacts) {
+            if !append.artifacts.contains(&id) && !self.storage.contains(ARTIFACTS_TREE, id.to_string().as_bytes())? {
+                return Err(ProvenanceError::UnknownArtifact(id));
+            }
+        }
+        if let Some((from, to)) = LayeredGraph::new(&self.graph, &append.edges).find_cycle(event)? {
+            return Err(ProvenanceError::CycleDetected { from, to });
+        }
         Ok(())
     }
 
-    async fn register_artifact(&mut self, mut artifact: Artifact) -> Result<(), ProvenanceError> {
+    /// Checks the content hash against the blob store and assigns ID, time and digest.
+    async fn prepare_artifact(&self, artifact: &mut Artifact) -> Result<(), ProvenanceError> {
+        // a_h must be the hash of content actually held in the blob store
+        if !self.blobs.verify(&artifact.content_hash).await? {
+            return Err(ProvenanceError::ContentMismatch(artifact.content_hash.clone()));
+        }
         artifact.id = Uuid::new_v4();
         artifact.registered_at = Utc::now();
-
-        let key = artifact.id.to_string();
-        let value = serde_json::to_vec(&artifact)?;
-        self.artifacts_tree.insert(key, value)?;
-        self.artifacts_tree.flush()?;
+        artifact.metadata_digest = artifact.compute_metadata_digest()?;
+        artifact.authorship.clear();
         Ok(())
     }
 
-    async fn verify_signature(&self, data: &[u8], signature: &Signature) -> Result<bool, ProvenanceError> {
-        // Mock verification
-        Ok(signature.signature.len() == 64)
+    /// Decodes a stored block and attaches its anchor receipts and failures.
+    fn load_block(&self, height: &[u8], value: &[u8]) -> Result<Block, ProvenanceError> {
+        let mut block: Block = serde_json::from_slice(value)?;
+        for entry in self.storage.scan_prefix(BLOCK_ANCHORS_TREE, height) {
+            let (_key, receipt) = entry?;
+            block.anchors.push(serde_json::from_slice(&receipt)?);
+        }
+        for entry in self.storage.scan_prefix(BLOCK_ANCHOR_FAILURES_TREE, height) {
+            let (_key, failure) = entry?;
+            block.anchor_failures.push(serde_json::from_slice(&failure)?);
+        }
+        Ok(block)
     }
 
-    async fn get_lineage(&self, artifact_id: Uuid) -> Result<Lineage, ProvenanceError> {
-        let g = self.graph.lock().await;
-        let parents = self.find_parents(&g, artifact_id).await;
-        let children = g.get(&artifact_id).cloned().unwrap_or_default();
-        Ok(Lineage {
-            artifact_id,
-            parent_ids: parents,
-            child_ids: children,
-            changes: "Version update".to_string(),
-        })
+    /// Anchors a sealed block with every anchor that has no receipt for it yet. Receipts
+    /// and failures are stored on the block as they come; the first anchor failure is
+    /// returned, and only a storage error fails the call.
+    async fn anchor_sealed(&self, block: &mut Block) -> Result<Option<ProvenanceError>, ProvenanceError> {
+        let mut failure = None;
+        for anchor in &self.anchors {
+            if block.anchors.iter().any(|receipt| receipt.anchor == anchor.name()) {
+                continue;
+            }
+            let key = [&block.height.to_be_bytes()[..], anchor.name().as_bytes()].concat();
+            let result = anchor.anchor(block).await;
+            block.anchor_failures.retain(|failed| failed.anchor != anchor.name());
+            match result {
+                Ok(receipt) => {
+                    let mut batch = Batch::default();
+                    batch.insert(BLOCK_ANCHORS_TREE, &key, serde_json::to_vec(&receipt)?);
+                    batch.remove(BLOCK_ANCHOR_FAILURES_TREE, &key);
+                    self.storage.commit(batch)?;
+                    block.anchors.push(receipt);
+                }
+                Err(e) => {
+                    let failed = anchor::AnchorFailure {
+                        anchor: anchor.name().to_string(),
+                        failed_at: Utc::now(),
+                        error: e.to_string(),
+                    };
+                    self.storage.insert(BLOCK_ANCHOR_FAILURES_TREE, key, serde_json::to_vec(&failed)?)?;
+                    block.anchor_failures.push(failed);
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        self.storage.flush()?;
+        Ok(failure)
     }
 
-    async fn get_events(&self, filter: Option<EventFilter>) -> Result<Vec<Event>, ProvenanceError> {
-        let mut events = Vec::new();
-        for result in self.events_tree.iter() {
+    /// Checkpoint tree leaves of every sealed block, in height order.
+    fn block_leaves(&self) -> Result<Vec<Vec<u8>>, ProvenanceError> {
+        self.storage
+            .iter(BLOCK_LEAVES_TREE)
+            .map(|entry| entry.map(|(_height, leaf)| leaf))
+            .collect()
+    }
+
+    /// IDs of the versions in `family`, oldest first.
+    fn family_versions(&self, family: Uuid) -> Result<Vec<Uuid>, ProvenanceError> {
+        let mut ids = Vec::new();
+        for result in self.storage.scan_prefix(FAMILIES_TREE, family.as_bytes()) {
             let (_key, value) = result?;
-            let event: Event = serde_json::from_slice(&value)?;
-            if let Some(f) = &filter {
-                if let Some(et) = &f.event_type {
-                    if event.operation != *et {
-                        continue;
+            ids.push(Uuid::from_slice(&value).map_err(|e| ProvenanceError::DatabaseError(e.to_string()))?);
+        }
+        Ok(ids)
+    }
+
+    /// Stores `artifact` as version `index` of its family; see `stage_version`.
+    fn store_version(&self, artifact: &Artifact, index: u64, previous: Option<Uuid>) -> Result<(), ProvenanceError> {
+        let mut append = Append::default();
+        if self.stage_version(&mut append, artifact, index, previous)? && self.storage.commit(append.batch)? {
+            self.storage.flush()?;
+            return Ok(());
+        }
+        Err(self.not_latest(artifact.family_id.unwrap_or(artifact.id))?)
+    }
+
+    /// Stages `artifact` as version `index` of its family. Unless `index` is 0 the slot
+    /// before it must hold `previous`, either already or in `append`; returns false if it
+    /// does not. The batch expects the slots it relies on, so a concurrent registration
+    /// that took them first makes the commit fail.
+    fn stage_version(
+        &self,
+        append: &mut Append,
+        artifact: &Artifact,
+        index: u64,
+        previous: Option<Uuid>,
+    ) -> Result<bool, ProvenanceError> {
+        let family = artifact.family_id.unwrap_or(artifact.id);
+        let slot = version_key(family, index);
+        if append.versions.contains_key(&slot) {
+            return Ok(false);
+        }
+        if let Some(previous) = previous {
+            let before = version_key(family, index - 1);
+            match append.versions.get(&before) {
+                Some(&id) if id == previous => {}
+                Some(_) => return Ok(false),
+                None => match self.storage.get(FAMILIES_TREE, &before)? {
+                    Some(id) if id == previous.as_bytes() => append.batch.expect(FAMILIES_TREE, &before, Some(&id)),
+                    // First version of a family that predates versioning
+                    None if index == 1 && previous == family => {
+                        append.batch.expect(FAMILIES_TREE, &before, None);
+                        append.batch.insert(FAMILIES_TREE, &before, previous.as_bytes());
+                        append.versions.insert(before, previous);
                     }
+                    _ => return Ok(false),
+                },
+            }
+        }
+        append.batch.expect(FAMILIES_TREE, &slot, None);
+        append.batch.insert(ARTIFACTS_TREE, artifact.id.to_string(), serde_json::to_vec(artifact)?);
+        append.batch.insert(FAMILIES_TREE, &slot, artifact.id.as_bytes());
+        append.versions.insert(slot, artifact.id);
+        append.artifacts.insert(artifact.id);
+        Ok(true)
+    }
+
+    /// The error for a registration that does not extend the latest version of `family`.
+    fn not_latest(&self, family: Uuid) -> Result<ProvenanceError, ProvenanceError> {
+        let latest = self.family_versions(family)?.pop().unwrap_or(family);
+        Ok(ProvenanceError::NotLatestVersion { latest })
+    }
+
+    /// Spawns the background task that seals pending events according to `policy`.
+    /// The task re-checks the policy whenever an event is logged and at least once a second.
+    /// Its outcome is reported by `sealer_status`.
+    pub fn spawn_sealer(&self, policy: SealingPolicy) -> JoinHandle<()> {
+        let mut service = self.clone();
+        self.sealer.lock().unwrap().policy = Some(policy.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = service.seal_notify.notified() => {}
+                    _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {}
                 }
-                if let Some(st) = f.start_time {
-                    if event.timestamp < st {
-                        continue;
-                    }
+                let due = {
+                    let pending = service.pending_events.lock().await;
+                    let oldest = pending.first().map(|e| e.timestamp);
+                    policy.should_seal(pending.len(), oldest, Utc::now())
+                };
+                if !due {
+                    continue;
                 }
-                if let Some(et) = f.end_time {
-                    if event.timestamp > et {
-                        continue;
+                let sealed = service.create_block().await;
+                let mut status = service.sealer.lock().unwrap();
+                match sealed {
+                    Ok(Some(block)) => {
+                        status.last_sealed_height = Some(block.height);
+                        status.last_sealed_at = Some(block.created_at);
+                        status.last_error = None;
+                        status.last_error_at = None;
+                        status.failures = 0;
+                    }
+                    Ok(None) => {}
+                    // Left pending, so the next attempt retries the same events
+                    Err(e) => {
+                        status.last_error = Some(e.to_string());
+                        status.last_error_at = Some(Utc::now());
+                        status.failures += 1;
                     }
                 }
             }
-            events.push(event);
+        })
+    }
+
+    /// What the background sealer has sealed and why it last failed, if it did.
+    pub fn sealer_status(&self) -> SealerStatus {
+        self.sealer.lock().unwrap().clone()
+    }
+
+    /// Subscribes to notifications of logged events and sealed blocks selected by
+    /// `filter`, starting at event sequence number `since_seq` and block height
+    /// `from_height`; either defaults to what comes next. Earlier entries are replayed
+    /// from storage first.
+    pub fn subscribe(
+        &self,
+        filter: SubscriptionFilter,
+        since_seq: Option<u64>,
+        from_height: Option<u64>,
+    ) -> Result<Subscription, ProvenanceError> {
+        let receiver = self.notifications.subscribe();
+        let next_seq = match self.storage.last(LOG_TREE)? {
+            Some((key, _)) => decode_seq(&key)? + 1,
+            None => 0,
+        };
+        let next_height = match self.storage.last(BLOCKS_TREE)? {
+            Some((key, _)) => decode_height(&key)? + 1,
+            None => 0,
+        };
+        let cursor = FeedCursor {
+            seq: since_seq.unwrap_or(next_seq),
+            height: from_height.unwrap_or(next_height),
+        };
+        Ok(Subscription::new(self.storage.clone(), receiver, filter, cursor))
+    }
+
+    /// Keystore holding the actors' signing keys.
+    pub fn keystore(&self) -> &Keystore {
+        &self.keystore
+    }
+
+    /// Blob store holding artifact content.
+    pub fn blobs(&self) -> &BlobStore {
+        &self.blobs
+    }
+
+    /// Events and artifacts of an artifact's lineage subgraph, for PROV export.
+    pub async fn lineage_records(
+        &self,
+        artifact_id: Uuid,
+        query: LineageQuery,
+    ) -> Result<(Vec<Event>, Vec<Artifact>), ProvenanceError> {
+        let lineage = self.lineage(artifact_id, query).await?;
+        let mut ids = vec![artifact_id];
+        ids.extend(lineage.ancestors);
+        ids.extend(lineage.descendants);
+        let artifacts = self.artifacts_for(&lineage.events, ids, lineage.as_of.as_ref()).await?;
+        Ok((lineage.events, artifacts))
+    }
+
+    /// Events in a time range and the artifacts they touch, for PROV export.
+    pub async fn range_records(&self, filter: EventFilter) -> Result<(Vec<Event>, Vec<Artifact>), ProvenanceError> {
+        let mut events = self.get_events(Some(filter)).await?;
+        events.sort_by_key(|e| e.timestamp);
+        let artifacts = self.artifacts_for(&events, Vec::new(), None).await?;
+        Ok((events, artifacts))
+    }
+
+    async fn artifacts_for(
+        &self,
+        events: &[Event],
+        mut ids: Vec<Uuid>,
+        snapshot: Option<&Snapshot>,
+    ) -> Result<Vec<Artifact>, ProvenanceError> {
+        for event in events {
+            ids.extend(event.in_artifacts.iter().chain(&event.out_artifacts));
+        }
+        let mut artifacts: Vec<Artifact> = Vec::new();
+        for id in ids {
+            if artifacts.iter().any(|a| a.id == id) {
+                continue;
+            }
+            if let Some(artifact) = self.artifact_record(id, snapshot).await? {
+                artifacts.push(artifact);
+            }
         }
-        Ok(events)
+        Ok(artifacts)
     }
 
-    async fn create_block(&mut self) -> Result<Block, ProvenanceError> {
+    /// Ingests a PROV-JSON document. Entities naming an existing artifact
+    /// (`artifact:<uuid>`) are reused, other entities are registered as new artifacts,
+    /// and each activity is logged as an event in start-time order. Activities naming
+    /// an event already in the log (`event:<uuid>`) are skipped, so re-importing an
+    /// export is a no-op. The original PROV identifier and start time are kept in the
+    /// event context.
+    /// New entities that carry `capcf:contentHash` must have their content uploaded
+    /// first; entities without one get their PROV description stored as content.
+    /// The document is checked whole and committed in one batch: if any entity or
+    /// activity is refused, nothing is imported.
+    pub async fn import_prov(&mut self, doc: &serde_json::Value) -> Result<ProvImportSummary, ProvenanceError> {
+        let import = prov::parse_prov_json(doc)?;
+        for activity in &import.activities {
+            check_actor(&activity.actor)?;
+        }
+        let mut new_artifacts = Vec::new();
+        let mut summary = ProvImportSummary::default();
+        for entity in import.entities {
+            let existing = match entity.artifact_id {
+                Some(id) => self.get_artifact(id).await?,
+                None => None,
+            };
+            let id = match existing {
+                Some(artifact) => artifact.id,
+                None => {
+                    let mut artifact = entity.artifact;
+                    if artifact.content_hash.is_empty() {
+                        let description = canonical::to_canonical_json(&artifact.metadata)?;
+                        artifact.content_hash = self.blobs.put(&description).await?.content_hash;
+                    }
+                    self.prepare_artifact(&mut artifact).await?;
+                    artifact.family_id = Some(artifact.id);
+                    artifact.previous_version = None;
+                    let id = artifact.id;
+                    new_artifacts.push(artifact);
+                    id
+                }
+            };
+            summary.artifacts.insert(entity.prov_id, id);
+        }
+
         let mut pending = self.pending_events.lock().await;
-        if pending.is_empty() {
-            return Err(ProvenanceError::BlockError);
+        let mut append = self.begin_append()?;
+        let seq = append.next_seq;
+        for artifact in &new_artifacts {
+            self.stage_version(&mut append, artifact, 0, None)?;
+        }
+        let resolve = |names: &[String]| -> Vec<Uuid> { names.iter().map(|n| summary.artifacts[n]).collect() };
+        let mut events = Vec::new();
+        for activity in import.activities {
+            if let Some(id) = activity.event_id {
+                if self.get_event(id).await?.is_some() {
+                    continue;
+                }
+            }
+            let event = Event {
+                id: Uuid::nil(),
+                timestamp: Utc::now(),
+                actor: activity.actor,
+                in_artifacts: resolve(&activity.used),
+                operation: activity.operation,
+                out_artifacts: resolve(&activity.generated),
+                context: serde_json::json!({
+                    "prov:id": activity.prov_id,
+                    "prov:startTime": activity.started_at,
+                    "imported": activity.context,
+                }),
+                signature: None,
+                seq: None,
+                origin: None,
+            };
+            let event = self.sign_event(&append, event)?;
+            events.push(self.stage_event(&mut append, event)?);
+        }
+        if !self.commit_append(&mut pending, append)? {
+            return Err(ProvenanceError::AppendConflict(seq));
         }
+        summary.events = events;
+        Ok(summary)
+    }
 
-        let events = pending.drain(..).collect::<Vec<_>>();
-        let block_id = Uuid::new_v4();
-        let mut last_hash = self.last_block_hash.lock().await.clone();
-        let block_data = serde_json::to_string(&events)?;
-        let hash = format!("{:x}", ring::digest::digest(&ring::digest::SHA256, block_data.as_bytes()));
+    /// Packages an artifact's lineage subgraph as a bundle that `bundle::verify_bundle`
+    /// can check without this database. Events not sealed yet are included without an
+    /// inclusion proof.
+    pub async fn export_bundle(&self, artifact_id: Uuid, mut query: LineageQuery) -> Result<ProvenanceBundle, ProvenanceError> {
+        // Pinned to a height, so every record comes from the same sealed state
+        let snapshot = self.snapshot(query.as_of)?;
+        query.as_of = snapshot.as_ref().map(|snapshot| AsOf::Height(snapshot.height));
+        let (mut events, mut artifacts) = self.lineage_records(artifact_id, query).await?;
+        events.sort_by_key(|e| (e.seq, e.timestamp));
+        // Version links must resolve within the bundle, even past `max_depth`
+        let mut i = 0;
+        while i < artifacts.len() {
+            for id in [artifacts[i].family_id, artifacts[i].previous_version].into_iter().flatten() {
+                if !artifacts.iter().any(|a| a.id == id) {
+                    if let Some(artifact) = self.artifact_record(id, snapshot.as_ref()).await? {
+                        artifacts.push(artifact);
+                    }
+                }
+            }
+            i += 1;
+        }
 
-        let block = Block {
-            id: block_id,
+        let mut inclusion_proofs = Vec::new();
+        for event in &events {
+            match self.get_inclusion_proof(event.id).await {
+                Ok(proof) => inclusion_proofs.push(proof),
+                Err(ProvenanceError::EventNotSealed) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        // Taken after the proofs, so it covers every block they name
+        let checkpoint = self.checkpoint().await?;
+        let mut leaves = self.block_leaves()?;
+        leaves.truncate(checkpoint.tree_size as usize);
+        let blocks: BTreeMap<u64, &str> =
+            inclusion_proofs.iter().map(|p| (p.block_height, p.block_hash.as_str())).collect();
+        let block_proofs = blocks
+            .into_iter()
+            .map(|(height, block_hash)| BlockProof {
+                height,
+                block_hash: block_hash.to_string(),
+                audit_path: merkle::audit_path(&leaves, height as usize).iter().map(hex::encode).collect(),
+            })
+            .collect();
+
+        let mut key_histories = BTreeMap::new();
+        let signers = events
+            .iter()
+            .map(|e| e.actor.as_str())
+            .chain(artifacts.iter().flat_map(|a| a.authorship.iter().map(|entry| entry.signer.as_str())))
+            .chain([CHECKPOINT_SIGNER]);
+        for signer in signers {
+            if key_histories.contains_key(signer) {
+                continue;
+            }
+            let history = self.keystore.history(signer)?;
+            if !history.is_empty() {
+                key_histories.insert(signer.to_string(), history);
+            }
+        }
+
+        Ok(ProvenanceBundle {
+            format: bundle::BUNDLE_FORMAT.to_string(),
+            artifact_id,
+            exported_at: Utc::now(),
+            artifacts,
             events,
-            hash: hash.clone(),
-            previous_hash: last_hash.clone(),
-            created_at: Utc::now(),
+            key_histories,
+            inclusion_proofs,
+            checkpoint,
+            block_proofs,
+        })
+    }
+
+    /// Imports a bundle exported by another Provenance Layer, after checking it with
+    /// `bundle::verify_bundle`. Artifact records are stored as they are, keeping their IDs
+    /// and authorship chains; artifacts already registered are left alone. Events are
+    /// appended to this log as they were signed, keeping their ID, time and signature,
+    /// with their position in the exporting log recorded as their origin. The signers'
+    /// public key histories are imported with them, so those signatures verify here;
+    /// the bundle is refused if a signer already holds different keys here. Events
+    /// imported before, or already in this log, are skipped, so re-importing a bundle is
+    /// a no-op. Everything is committed in one batch, so a refused bundle leaves nothing
+    /// behind.
+    /// Bundles carry no content; upload it separately to serve the artifacts' content.
+    pub async fn import_bundle(&mut self, bundle: &ProvenanceBundle) -> Result<BundleImportSummary, ProvenanceError> {
+        let verification = bundle::verify_bundle(bundle)?;
+        if let Some(failure) = verification.failures.first() {
+            return Err(ProvenanceError::InvalidBundle(format!(
+                "{}: {} ({} problems)",
+                failure.subject,
+                failure.reason,
+                verification.failures.len()
+            )));
+        }
+
+        // Store each family oldest version first, at its position in the version chain
+        let records: BTreeMap<Uuid, &Artifact> = bundle.artifacts.iter().map(|a| (a.id, a)).collect();
+        let position = |artifact: &Artifact| {
+            let mut index = 0;
+            let mut previous = artifact.previous_version;
+            // Bounded, in case the version links of a crafted bundle form a loop
+            while let Some(artifact) = previous.and_then(|id| records.get(&id)).filter(|_| index < records.len() as u64) {
+                index += 1;
+                previous = artifact.previous_version;
+            }
+            index
+        };
+        let mut artifacts: Vec<(u64, &Artifact)> = bundle.artifacts.iter().map(|a| (position(a), a)).collect();
+        artifacts.sort_by_key(|(index, _)| *index);
+        let mut summary = BundleImportSummary {
+            verification,
+            artifacts: Vec::new(),
+            events: Vec::new(),
         };
 
-        let key = block_id.to_string();
-        let value = serde_json::to_vec(&block)?;
-        self.blocks_tree.insert(key, value)?;
-        self.blocks_tree.flush()?;
+        let mut pending = self.pending_events.lock().await;
+        let mut append = self.begin_append()?;
+        let seq = append.next_seq;
+        for (index, artifact) in artifacts {
+            if self.storage.contains(ARTIFACTS_TREE, artifact.id.to_string().as_bytes())? {
+                continue;
+            }
+            if !self.stage_version(&mut append, artifact, index, artifact.previous_version)? {
+                return Err(self.not_latest(artifact.family_id.unwrap_or(artifact.id))?);
+            }
+            summary.artifacts.push(artifact.id);
+        }
 
-        *self.last_block_hash.lock().await = hash;
+        let proofs: BTreeMap<Uuid, &InclusionProof> = bundle.inclusion_proofs.iter().map(|p| (p.event_id, p)).collect();
+        for event in &bundle.events {
+            let key = event.id.to_string();
+            // Bundles imported before events kept their IDs are recorded by event ID
+            if self.storage.contains(BUNDLE_EVENTS_TREE, key.as_bytes())?
+                || self.get_event(event.id).await?.is_some()
+                || append.events.iter().any(|staged| staged.id == event.id)
+            {
+                continue;
+            }
+            check_actor(&event.actor)?;
+            self.validate_event(&append, event)?;
+            let mut imported = event.clone();
+            // An event already imported into the exporting log keeps its first origin
+            imported.origin.get_or_insert_with(|| EventOrigin {
+                seq: event.seq,
+                block_hash: proofs.get(&event.id).map(|p| p.block_hash.clone()),
+            });
+            summary.events.push(self.stage_event(&mut append, imported)?);
+        }
 
-        Ok(block)
+        // The exporting log's own keys sign what is not imported, such as its checkpoints
+        for (signer, history) in bundle.key_histories.iter().filter(|(signer, _)| !RESERVED_ACTORS.contains(&signer.as_str())) {
+            if !self.keystore.stage_history(&mut append.batch, signer, history)? {
+                return Err(ProvenanceError::InvalidBundle(format!(
+                    "key history of {} conflicts with the keys it holds here",
+                    signer
+                )));
+            }
+        }
+        if !self.commit_append(&mut pending, append)? {
+            return Err(ProvenanceError::AppendConflict(seq));
+        }
+        Ok(summary)
     }
 }
 
-impl SledProvenanceService {
-    async fn find_parents(&self, g: &HashMap<Uuid, Vec<Uuid>>, artifact_id: Uuid) -> Vec<Uuid> {
-        let mut parents = Vec::new();
-        for (parent, children) in g.iter() {
-            if children.contains(&artifact_id) {
-                parents.push(*parent);
-            }
+/// Walks the stored blocks in height order against `head`, stopping at the first
+/// broken link.
+pub(crate) fn verify_blocks(storage: &dyn Storage, head: ChainHead) -> Result<ChainVerification, ProvenanceError> {
+    let mut expected = ChainHead::default();
+    let mut first_broken_link = None;
+
+    for result in storage.iter(BLOCKS_TREE) {
+        let (_key, value) = result?;
+        let block: Block = serde_json::from_slice(&value)?;
+        let reason = if block.height != expected.height {
+            Some(format!("expected height {}, found {}", expected.height, block.height))
+        } else if block.previous_hash != expected.hash {
+            Some(format!("previous_hash {} does not match {}", block.previous_hash, expected.hash))
+        } else if block.expected_hash()? != block.hash {
+            Some("block hash does not match its contents".to_string())
+        } else if block.merkle_root.as_ref().is_some_and(|root| {
+            merkle::events_root(&block.events).map(hex::encode).ok().as_ref() != Some(root)
+        }) {
+            Some("merkle root does not match the block events".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            first_broken_link = Some(BrokenLink {
+                height: expected.height,
+                block_id: Some(block.id),
+                reason,
+            });
+            break;
         }
-        parents
+        expected = ChainHead {
+            hash: block.hash,
+            height: block.height + 1,
+        };
     }
-}
\ No newline at end of file
+
+    if first_broken_link.is_none() && (expected.hash != head.hash || expected.height != head.height) {
+        first_broken_link = Some(BrokenLink {
+            height: expected.height,
+            block_id: None,
+            reason: format!("stored chain head {} at height {} does not match the last block", head.hash, head.height),
+        });
+    }
+
+    Ok(ChainVerification {
+        valid: first_broken_link.is_none(),
+        blocks_verified: expected.height,
+        head,
+        first_broken_link,
+    })
+}
+
+/// Refuses to act as one of the service's own actors on a caller's behalf.
+fn check_actor(actor: &str) -> Result<(), ProvenanceError> {
+    if RESERVED_ACTORS.contains(&actor) {
+        return Err(ProvenanceError::ReservedActor(actor.to_string()));
+    }
+    Ok(())
+}
+
+/// Event recording a change to `key`, signed by its actor or, with `admin`, by KEY_ADMIN.
+fn key_event(key: &KeyRecord, operation: &str, admin: bool) -> Event {
+    Event {
+        id: Uuid::nil(),
+        timestamp: Utc::now(),
+        actor: if admin { KEY_ADMIN.to_string() } else { key.actor.clone() },
+        in_artifacts: Vec::new(),
+        operation: operation.to_string(),
+        out_artifacts: Vec::new(),
+        context: serde_json::json!({ "key": key }),
+        signature: None,
+        seq: None,
+        origin: None,
+    }
+}
+
+/// Error for a key that changed while a change to it was being logged.
+fn key_conflict(actor: &str) -> ProvenanceError {
+    ProvenanceError::KeyError(format!("key of {} changed concurrently", actor))
+}
+
+/// When a key change recorded by `event` takes effect: just after the event, so the
+/// key being replaced still covers the event's own signature.
+fn key_change_time(event: &Event) -> DateTime<Utc> {
+    Utc::now().max(event.timestamp + chrono::Duration::nanoseconds(1))
+}
+
+/// Key of version `index` in the `families` tree.
+fn version_key(family: Uuid, index: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(24);
+    key.extend_from_slice(family.as_bytes());
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+/// Reads an event by its ID key through the sequence index.
+fn load_event(storage: &dyn Storage, id: &[u8]) -> Result<Option<Event>, ProvenanceError> {
+    let seq = match storage.get(EVENT_SEQS_TREE, id)? {
+        Some(seq) => seq,
+        None => return Ok(None),
+    };
+    match storage.get(LOG_TREE, &seq)? {
+        Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+        None => Err(ProvenanceError::DatabaseError("indexed event missing from the log".to_string())),
+    }
+}
+
+pub(crate) fn decode_height(key: &[u8]) -> Result<u64, ProvenanceError> {
+    let bytes: [u8; 8] = key
+        .try_into()
+        .map_err(|_| ProvenanceError::DatabaseError("malformed block height".to_string()))?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+pub(crate) fn decode_seq(key: &[u8]) -> Result<u64, ProvenanceError> {
+    let bytes: [u8; 8] = key
+        .try_into()
+        .map_err(|_| ProvenanceError::DatabaseError("malformed log key".to_string()))?;
+    Ok(u64::from_be_bytes(bytes))
+}
@@ -0,0 +1,95 @@
+// In-memory backend for tests and throwaway instances. Nothing survives the process.
+
+use super::{Batch, Entries, Op, Storage};
+use crate::ProvenanceError;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+type Tree = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Storage in ordered maps behind one lock, so batches are trivially atomic.
+#[derive(Default)]
+pub struct MemoryStorage {
+    trees: Mutex<HashMap<String, Tree>>,
+    next_id: AtomicU64,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, tree: &str, key: &[u8]) -> Result<Option<Vec<u8>>, ProvenanceError> {
+        let trees = self.trees.lock().unwrap();
+        Ok(trees.get(tree).and_then(|t| t.get(key)).cloned())
+    }
+
+    fn scan(
+        &self,
+        tree: &str,
+        lo: &[u8],
+        hi: Option<&[u8]>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Entries, ProvenanceError> {
+        let trees = self.trees.lock().unwrap();
+        let tree = match trees.get(tree) {
+            Some(tree) => tree,
+            None => return Ok(Vec::new()),
+        };
+        if hi.is_some_and(|hi| hi <= lo) {
+            return Ok(Vec::new());
+        }
+        let upper = match hi {
+            Some(hi) => Bound::Excluded(hi),
+            None => Bound::Unbounded,
+        };
+        let range = tree.range::<[u8], _>((Bound::Included(lo), upper));
+        let clone = |(k, v): (&Vec<u8>, &Vec<u8>)| (k.clone(), v.clone());
+        Ok(if reverse {
+            range.rev().take(limit).map(clone).collect()
+        } else {
+            range.take(limit).map(clone).collect()
+        })
+    }
+
+    fn commit(&self, batch: Batch) -> Result<bool, ProvenanceError> {
+        let mut trees = self.trees.lock().unwrap();
+        for condition in &batch.conditions {
+            let current = trees.get(&condition.tree).and_then(|t| t.get(&condition.key));
+            if current != condition.expected.as_ref() {
+                return Ok(false);
+            }
+        }
+        for op in batch.ops {
+            match op {
+                Op::Insert { tree, key, value } => {
+                    trees.entry(tree).or_default().insert(key, value);
+                }
+                Op::Remove { tree, key } => {
+                    if let Some(tree) = trees.get_mut(&tree) {
+                        tree.remove(&key);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn generate_id(&self) -> Result<u64, ProvenanceError> {
+        Ok(self.next_id.fetch_add(1, Ordering::SeqCst))
+    }
+
+    fn flush(&self) -> Result<(), ProvenanceError> {
+        Ok(())
+    }
+
+    fn drop_tree(&self, tree: &str) -> Result<(), ProvenanceError> {
+        self.trees.lock().unwrap().remove(tree);
+        Ok(())
+    }
+}
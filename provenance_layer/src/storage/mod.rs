@@ -0,0 +1,296 @@
+// Storage backends for the Provenance Layer.
+// Every piece of PL state (E, A, G_P, B and the keys) is kept in named trees of an
+// ordered key-value store. `Storage` is the small interface the service needs from a
+// backend: point reads, ordered range scans and atomic conditional batches. sled, an
+// in-memory map and SQLite implement it; `StorageConfig` picks one at startup.
+
+use crate::ProvenanceError;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+mod memory;
+mod sled;
+mod sqlite;
+
+pub use self::memory::MemoryStorage;
+pub use self::sled::SledStorage;
+pub use self::sqlite::SqliteStorage;
+
+/// A key and its value.
+pub type Entry = (Vec<u8>, Vec<u8>);
+/// Entries returned by a scan.
+pub type Entries = Vec<Entry>;
+
+/// Ordered key-value store with named trees. Keys within a tree are ordered bytewise.
+pub trait Storage: Send + Sync {
+    /// Value stored under `key` in `tree`.
+    fn get(&self, tree: &str, key: &[u8]) -> Result<Option<Vec<u8>>, ProvenanceError>;
+
+    /// Up to `limit` entries of `tree` with `lo <= key < hi` (no upper bound if `hi` is
+    /// None), in ascending key order or descending if `reverse`.
+    fn scan(
+        &self,
+        tree: &str,
+        lo: &[u8],
+        hi: Option<&[u8]>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Entries, ProvenanceError>;
+
+    /// Applies `batch` atomically if all of its conditions hold.
+    /// Returns false, writing nothing, if any condition fails.
+    fn commit(&self, batch: Batch) -> Result<bool, ProvenanceError>;
+
+    /// Returns a new ID, unique and increasing for the lifetime of the store.
+    fn generate_id(&self) -> Result<u64, ProvenanceError>;
+
+    /// Makes every committed batch durable.
+    fn flush(&self) -> Result<(), ProvenanceError>;
+
+    /// Removes `tree` and everything in it.
+    fn drop_tree(&self, tree: &str) -> Result<(), ProvenanceError>;
+}
+
+/// One write in a batch.
+#[derive(Debug, Clone)]
+pub enum Op {
+    Insert { tree: String, key: Vec<u8>, value: Vec<u8> },
+    Remove { tree: String, key: Vec<u8> },
+}
+
+/// Value a key must hold (None: must be absent) for a batch to commit.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub tree: String,
+    pub key: Vec<u8>,
+    pub expected: Option<Vec<u8>>,
+}
+
+/// Writes to one or more trees, committed together or not at all.
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    pub ops: Vec<Op>,
+    pub conditions: Vec<Condition>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, tree: &str, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) {
+        self.ops.push(Op::Insert {
+            tree: tree.to_string(),
+            key: key.as_ref().to_vec(),
+            value: value.as_ref().to_vec(),
+        });
+    }
+
+    pub fn remove(&mut self, tree: &str, key: impl AsRef<[u8]>) {
+        self.ops.push(Op::Remove { tree: tree.to_string(), key: key.as_ref().to_vec() });
+    }
+
+    /// Commits only if `key` currently holds `expected`; compare-and-swap across trees.
+    pub fn expect(&mut self, tree: &str, key: impl AsRef<[u8]>, expected: Option<&[u8]>) {
+        self.conditions.push(Condition {
+            tree: tree.to_string(),
+            key: key.as_ref().to_vec(),
+            expected: expected.map(<[u8]>::to_vec),
+        });
+    }
+
+    /// Names of the trees the batch reads or writes, without duplicates.
+    pub fn trees(&self) -> Vec<&str> {
+        let mut trees: Vec<&str> = Vec::new();
+        let names = self.conditions.iter().map(|c| c.tree.as_str()).chain(self.ops.iter().map(|op| match op {
+            Op::Insert { tree, .. } | Op::Remove { tree, .. } => tree.as_str(),
+        }));
+        for name in names {
+            if !trees.contains(&name) {
+                trees.push(name);
+            }
+        }
+        trees
+    }
+}
+
+/// Entries fetched per backend call while iterating a range.
+const SCAN_CHUNK: usize = 256;
+
+impl dyn Storage + '_ {
+    pub fn contains(&self, tree: &str, key: &[u8]) -> Result<bool, ProvenanceError> {
+        Ok(self.get(tree, key)?.is_some())
+    }
+
+    /// Writes a single entry unconditionally.
+    pub fn insert(&self, tree: &str, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Result<(), ProvenanceError> {
+        let mut batch = Batch::new();
+        batch.insert(tree, key, value);
+        self.commit(batch)?;
+        Ok(())
+    }
+
+    /// Entry with the greatest key in `tree`.
+    pub fn last(&self, tree: &str) -> Result<Option<Entry>, ProvenanceError> {
+        Ok(self.scan(tree, &[], None, true, 1)?.pop())
+    }
+
+    /// Iterates `lo <= key < hi` in order, fetching entries in chunks.
+    pub fn range(&self, tree: &str, lo: &[u8], hi: Option<&[u8]>, reverse: bool) -> Range<'_> {
+        Range {
+            storage: self,
+            tree: tree.to_string(),
+            lo: lo.to_vec(),
+            hi: hi.map(<[u8]>::to_vec),
+            reverse,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Iterates a whole tree in key order.
+    pub fn iter(&self, tree: &str) -> Range<'_> {
+        self.range(tree, &[], None, false)
+    }
+
+    /// Iterates the keys starting with `prefix`, in order.
+    pub fn scan_prefix(&self, tree: &str, prefix: &[u8]) -> Range<'_> {
+        self.range(tree, prefix, prefix_end(prefix).as_deref(), false)
+    }
+}
+
+/// Smallest key greater than every key starting with `prefix`; None if there is none.
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Iterator over a key range, returned by `range`.
+pub struct Range<'a> {
+    storage: &'a dyn Storage,
+    tree: String,
+    lo: Vec<u8>,
+    hi: Option<Vec<u8>>,
+    reverse: bool,
+    buffer: VecDeque<Entry>,
+    done: bool,
+}
+
+impl Iterator for Range<'_> {
+    type Item = Result<Entry, ProvenanceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() && !self.done {
+            let chunk = match self.storage.scan(&self.tree, &self.lo, self.hi.as_deref(), self.reverse, SCAN_CHUNK) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            self.done = chunk.len() < SCAN_CHUNK;
+            // Narrow the range past the last entry read
+            if let Some((key, _)) = chunk.last() {
+                if self.reverse {
+                    self.hi = Some(key.clone());
+                } else {
+                    self.lo = [key.as_slice(), &[0]].concat();
+                }
+            }
+            self.buffer.extend(chunk);
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+/// Which `Storage` implementation to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sled,
+    Memory,
+    Sqlite,
+}
+
+impl FromStr for Backend {
+    type Err = ProvenanceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sled" => Ok(Backend::Sled),
+            "memory" => Ok(Backend::Memory),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(ProvenanceError::DatabaseError(format!("unknown storage backend: {}", s))),
+        }
+    }
+}
+
+/// Storage backend and location.
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub backend: Backend,
+    /// Database directory (sled) or file (SQLite); unused by the memory backend.
+    pub path: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Sled,
+            path: Self::default_path(Backend::Sled),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Reads `PL_STORAGE` (`sled`, `memory` or `sqlite`; default `sled`) and `PL_DB_PATH`
+    /// (default `provenance_db`, or `provenance.sqlite3` for SQLite).
+    pub fn from_env() -> Result<Self, ProvenanceError> {
+        let backend = match std::env::var("PL_STORAGE") {
+            Ok(name) => name.parse()?,
+            Err(_) => Backend::Sled,
+        };
+        let path = match std::env::var("PL_DB_PATH") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => Self::default_path(backend),
+        };
+        Ok(Self { backend, path })
+    }
+
+    /// Default location of a backend's database.
+    pub fn default_path(backend: Backend) -> PathBuf {
+        match backend {
+            Backend::Sqlite => PathBuf::from("provenance.sqlite3"),
+            Backend::Sled | Backend::Memory => PathBuf::from("provenance_db"),
+        }
+    }
+
+    /// Opens the configured backend.
+    pub fn open(&self) -> Result<Arc<dyn Storage>, ProvenanceError> {
+        Ok(match self.backend {
+            Backend::Sled => Arc::new(SledStorage::open(&self.path)?),
+            Backend::Memory => Arc::new(MemoryStorage::new()),
+            Backend::Sqlite => Arc::new(SqliteStorage::open(&self.path)?),
+        })
+    }
+
+    /// Opens the configured backend without modifying it: SQLite read-only, sled
+    /// through a temporary copy of its directory. The memory backend holds nothing
+    /// to open.
+    pub fn open_read_only(&self) -> Result<Arc<dyn Storage>, ProvenanceError> {
+        Ok(match self.backend {
+            Backend::Sled => Arc::new(SledStorage::open_snapshot(&self.path)?),
+            Backend::Memory => {
+                return Err(ProvenanceError::DatabaseError("the memory backend cannot be opened read-only".to_string()))
+            }
+            Backend::Sqlite => Arc::new(SqliteStorage::open_read_only(&self.path)?),
+        })
+    }
+}
@@ -0,0 +1,137 @@
+// sled backend: each tree is a sled tree and batches are multi-tree transactions.
+
+use super::{Batch, Entries, Op, Storage};
+use crate::ProvenanceError;
+use ::sled::transaction::{ConflictableTransactionError, TransactionError};
+use ::sled::{Db, Transactional, Tree};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Storage in a sled database directory.
+#[derive(Clone)]
+pub struct SledStorage {
+    db: Db,
+    _snapshot: Option<Arc<SnapshotDir>>,
+}
+
+/// Temporary copy of a database directory, removed once the last handle is dropped.
+struct SnapshotDir(PathBuf);
+
+impl Drop for SnapshotDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+impl SledStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ProvenanceError> {
+        Ok(Self { db: ::sled::open(path)?, _snapshot: None })
+    }
+
+    /// Opens a temporary copy of the database at `path`, leaving the original untouched.
+    /// sled has no read-only mode and writes to its files on open.
+    pub fn open_snapshot(path: impl AsRef<Path>) -> Result<Self, ProvenanceError> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(ProvenanceError::DatabaseError(format!("no sled database at {}", path.display())));
+        }
+        let snapshot = SnapshotDir(std::env::temp_dir().join(format!("pl-snapshot-{}", uuid::Uuid::new_v4())));
+        copy_dir(path, &snapshot.0)?;
+        Ok(Self { db: ::sled::open(&snapshot.0)?, _snapshot: Some(Arc::new(snapshot)) })
+    }
+
+    fn tree(&self, name: &str) -> Result<Tree, ProvenanceError> {
+        Ok(self.db.open_tree(name)?)
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), ProvenanceError> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+impl Storage for SledStorage {
+    fn get(&self, tree: &str, key: &[u8]) -> Result<Option<Vec<u8>>, ProvenanceError> {
+        Ok(self.tree(tree)?.get(key)?.map(|v| v.to_vec()))
+    }
+
+    fn scan(
+        &self,
+        tree: &str,
+        lo: &[u8],
+        hi: Option<&[u8]>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Entries, ProvenanceError> {
+        let tree = self.tree(tree)?;
+        if hi.is_some_and(|hi| hi <= lo) {
+            return Ok(Vec::new());
+        }
+        let range = match hi {
+            Some(hi) => tree.range(lo..hi),
+            None => tree.range(lo..),
+        };
+        let entries: Box<dyn Iterator<Item = ::sled::Result<(::sled::IVec, ::sled::IVec)>>> =
+            if reverse { Box::new(range.rev()) } else { Box::new(range) };
+        entries
+            .take(limit)
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((key.to_vec(), value.to_vec()))
+            })
+            .collect()
+    }
+
+    fn commit(&self, batch: Batch) -> Result<bool, ProvenanceError> {
+        let names = batch.trees();
+        let trees = names.iter().map(|name| self.tree(name)).collect::<Result<Vec<_>, _>>()?;
+        let index = |name: &str| names.iter().position(|n| *n == name).unwrap();
+        let result = trees.as_slice().transaction(|views| {
+            for condition in &batch.conditions {
+                let current = views[index(&condition.tree)].get(&condition.key)?;
+                if current.as_deref() != condition.expected.as_deref() {
+                    return Err(ConflictableTransactionError::Abort(()));
+                }
+            }
+            for op in &batch.ops {
+                match op {
+                    Op::Insert { tree, key, value } => {
+                        views[index(tree)].insert(key.as_slice(), value.as_slice())?;
+                    }
+                    Op::Remove { tree, key } => {
+                        views[index(tree)].remove(key.as_slice())?;
+                    }
+                }
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => Ok(true),
+            Err(TransactionError::Abort(())) => Ok(false),
+            Err(TransactionError::Storage(e)) => Err(e.into()),
+        }
+    }
+
+    fn generate_id(&self) -> Result<u64, ProvenanceError> {
+        Ok(self.db.generate_id()?)
+    }
+
+    fn flush(&self) -> Result<(), ProvenanceError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn drop_tree(&self, tree: &str) -> Result<(), ProvenanceError> {
+        self.db.drop_tree(tree)?;
+        Ok(())
+    }
+}
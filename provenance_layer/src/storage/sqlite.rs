@@ -0,0 +1,125 @@
+// Embedded SQLite backend. All trees share one table keyed by (tree, key); SQLite
+// compares BLOBs bytewise, so key order matches the other backends.
+
+use super::{Batch, Entries, Op, Storage};
+use crate::ProvenanceError;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, TransactionBehavior};
+use std::path::Path;
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS kv (
+        tree TEXT NOT NULL,
+        key BLOB NOT NULL,
+        value BLOB NOT NULL,
+        PRIMARY KEY (tree, key)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS ids (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        next INTEGER NOT NULL
+    );
+    INSERT OR IGNORE INTO ids (id, next) VALUES (0, 0);
+";
+
+/// Storage in a single SQLite database file.
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ProvenanceError> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Opens an existing database without write access; every commit fails.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, ProvenanceError> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn get(&self, tree: &str, key: &[u8]) -> Result<Option<Vec<u8>>, ProvenanceError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row("SELECT value FROM kv WHERE tree = ?1 AND key = ?2", params![tree, key], |row| row.get(0))
+            .optional()?)
+    }
+
+    fn scan(
+        &self,
+        tree: &str,
+        lo: &[u8],
+        hi: Option<&[u8]>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Entries, ProvenanceError> {
+        let conn = self.conn.lock().unwrap();
+        let sql = if reverse {
+            "SELECT key, value FROM kv WHERE tree = ?1 AND key >= ?2 AND (?3 IS NULL OR key < ?3)
+             ORDER BY key DESC LIMIT ?4"
+        } else {
+            "SELECT key, value FROM kv WHERE tree = ?1 AND key >= ?2 AND (?3 IS NULL OR key < ?3)
+             ORDER BY key ASC LIMIT ?4"
+        };
+        let mut stmt = conn.prepare_cached(sql)?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let rows = stmt.query_map(params![tree, lo, hi, limit], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn commit(&self, batch: Batch) -> Result<bool, ProvenanceError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for condition in &batch.conditions {
+            let current: Option<Vec<u8>> = tx
+                .query_row(
+                    "SELECT value FROM kv WHERE tree = ?1 AND key = ?2",
+                    params![condition.tree, condition.key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if current != condition.expected {
+                // Dropping the transaction rolls it back
+                return Ok(false);
+            }
+        }
+        for op in &batch.ops {
+            match op {
+                Op::Insert { tree, key, value } => {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO kv (tree, key, value) VALUES (?1, ?2, ?3)",
+                        params![tree, key, value],
+                    )?;
+                }
+                Op::Remove { tree, key } => {
+                    tx.execute("DELETE FROM kv WHERE tree = ?1 AND key = ?2", params![tree, key])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn generate_id(&self) -> Result<u64, ProvenanceError> {
+        let conn = self.conn.lock().unwrap();
+        let id: i64 = conn.query_row("UPDATE ids SET next = next + 1 WHERE id = 0 RETURNING next - 1", [], |row| {
+            row.get(0)
+        })?;
+        Ok(id as u64)
+    }
+
+    fn flush(&self) -> Result<(), ProvenanceError> {
+        // Every commit is durable once it returns
+        Ok(())
+    }
+
+    fn drop_tree(&self, tree: &str) -> Result<(), ProvenanceError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM kv WHERE tree = ?1", params![tree])?;
+        Ok(())
+    }
+}
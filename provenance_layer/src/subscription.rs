@@ -0,0 +1,221 @@
+// Live notifications of logged events and sealed blocks.
+// The service broadcasts each event as it is appended to the log and each block as it is
+// sealed. A Subscription follows that broadcast from a cursor; whenever it falls behind,
+// because it started in the past, missed broadcasts while lagging, or saw a gap, it
+// replays from the log and the chain. So a client that reconnects with its last cursor
+// gets every notification after it, once and in order.
+
+use crate::provenance_impl::{decode_height, decode_seq, BLOCKS_TREE, LOG_TREE};
+use crate::storage::Storage;
+use crate::*;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Notifications buffered for each subscriber before it lags and has to replay.
+pub const CHANNEL_CAPACITY: usize = 1024;
+
+/// Entries read from storage per replay step.
+const REPLAY_BATCH: usize = 256;
+
+/// Something that happened in the Provenance Layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Notification {
+    /// An event appended to the log.
+    Event(Event),
+    /// A block as sealed, before any anchor receipts.
+    Block(Block),
+}
+
+impl Notification {
+    /// `event` or `block`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Notification::Event(_) => "event",
+            Notification::Block(_) => "block",
+        }
+    }
+}
+
+/// Which notifications a subscriber receives.
+#[derive(Debug, Clone)]
+pub struct SubscriptionFilter {
+    /// Selects events, and the blocks that seal any selected event.
+    pub filter: EventFilter,
+    pub events: bool,
+    pub blocks: bool,
+}
+
+impl Default for SubscriptionFilter {
+    fn default() -> Self {
+        Self {
+            filter: EventFilter::default(),
+            events: true,
+            blocks: true,
+        }
+    }
+}
+
+impl SubscriptionFilter {
+    fn matches(&self, notification: &Notification) -> bool {
+        match notification {
+            Notification::Event(event) => self.events && self.filter.matches(event),
+            Notification::Block(block) => self.blocks && block.events.iter().any(|event| self.filter.matches(event)),
+        }
+    }
+}
+
+/// Position in the feed: the sequence number of the next event and the height of the
+/// next block to deliver. Written `<seq>.<height>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedCursor {
+    pub seq: u64,
+    pub height: u64,
+}
+
+impl std::fmt::Display for FeedCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.seq, self.height)
+    }
+}
+
+impl std::str::FromStr for FeedCursor {
+    type Err = ProvenanceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (seq, height) = s.split_once('.').ok_or(ProvenanceError::InvalidCursor)?;
+        Ok(Self {
+            seq: seq.parse().map_err(|_| ProvenanceError::InvalidCursor)?,
+            height: height.parse().map_err(|_| ProvenanceError::InvalidCursor)?,
+        })
+    }
+}
+
+/// A subscriber's view of the feed; see `ProvenanceServiceImpl::subscribe`.
+pub struct Subscription {
+    storage: Arc<dyn Storage>,
+    receiver: broadcast::Receiver<Notification>,
+    filter: SubscriptionFilter,
+    cursor: FeedCursor,
+    /// Notifications to check against the cursor before receiving more, with their
+    /// positions in the log or the chain.
+    queued: VecDeque<(u64, Notification)>,
+    /// Whether to replay from storage before receiving more.
+    behind: bool,
+}
+
+impl Subscription {
+    /// `receiver` must have been subscribed before `cursor` was read, so that nothing
+    /// after the cursor can be missed.
+    pub(crate) fn new(
+        storage: Arc<dyn Storage>,
+        receiver: broadcast::Receiver<Notification>,
+        filter: SubscriptionFilter,
+        cursor: FeedCursor,
+    ) -> Self {
+        Self {
+            storage,
+            receiver,
+            filter,
+            cursor,
+            queued: VecDeque::new(),
+            behind: true,
+        }
+    }
+
+    /// Position after the last notification delivered or filtered out.
+    pub fn cursor(&self) -> FeedCursor {
+        self.cursor
+    }
+
+    /// Waits for the next notification the filter selects. None once the service is gone.
+    pub async fn next(&mut self) -> Option<Result<Notification, ProvenanceError>> {
+        loop {
+            if let Some((position, notification)) = self.queued.pop_front() {
+                if self.advance(position, &notification) && self.filter.matches(&notification) {
+                    return Some(Ok(notification));
+                }
+                continue;
+            }
+            if self.behind {
+                if let Err(e) = self.replay() {
+                    return Some(Err(e));
+                }
+                continue;
+            }
+            match self.receiver.recv().await {
+                Ok(notification) => match notification_position(&notification) {
+                    Some(position) => self.queued.push_back((position, notification)),
+                    None => self.behind = true,
+                },
+                Err(RecvError::Lagged(_)) => self.behind = true,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Moves the cursor past `notification`, found at `position`, if it is the next of
+    /// its kind. Earlier ones were delivered already; a later one means some were missed,
+    /// so those still queued are dropped and read again from storage.
+    fn advance(&mut self, position: u64, notification: &Notification) -> bool {
+        let next = match notification {
+            Notification::Event(_) if !self.filter.events => return false,
+            Notification::Block(_) if !self.filter.blocks => return false,
+            Notification::Event(_) => &mut self.cursor.seq,
+            Notification::Block(_) => &mut self.cursor.height,
+        };
+        if position == *next {
+            *next += 1;
+            true
+        } else {
+            if position > *next {
+                self.queued.clear();
+                self.behind = true;
+            }
+            false
+        }
+    }
+
+    /// Queues the next batch of events and blocks from the cursor on; stays behind
+    /// while there may be more.
+    fn replay(&mut self) -> Result<(), ProvenanceError> {
+        self.behind = false;
+        if self.filter.events {
+            let events = self.read(LOG_TREE, self.cursor.seq, decode_seq)?;
+            self.queued.extend(events.into_iter().map(|(seq, event)| (seq, Notification::Event(event))));
+        }
+        if self.filter.blocks {
+            let blocks = self.read(BLOCKS_TREE, self.cursor.height, decode_height)?;
+            self.queued.extend(blocks.into_iter().map(|(height, block)| (height, Notification::Block(block))));
+        }
+        Ok(())
+    }
+
+    /// The next batch of entries of `tree` with their positions, taken from the keys;
+    /// events migrated from before sequence numbers carry none of their own.
+    fn read<T: DeserializeOwned>(
+        &mut self,
+        tree: &str,
+        from: u64,
+        decode: fn(&[u8]) -> Result<u64, ProvenanceError>,
+    ) -> Result<Vec<(u64, T)>, ProvenanceError> {
+        let mut items = Vec::new();
+        for entry in self.storage.range(tree, &from.to_be_bytes(), None, false).take(REPLAY_BATCH) {
+            let (key, value) = entry?;
+            items.push((decode(&key)?, serde_json::from_slice(&value)?));
+        }
+        self.behind |= items.len() == REPLAY_BATCH;
+        Ok(items)
+    }
+}
+
+/// Position of a broadcast notification. Appended events always carry their sequence
+/// number; one without is left to the replay, which reads it from the log key.
+fn notification_position(notification: &Notification) -> Option<u64> {
+    match notification {
+        Notification::Event(event) => event.seq,
+        Notification::Block(block) => Some(block.height),
+    }
+}
@@ -0,0 +1,89 @@
+// Offline audit of a provenance database.
+
+mod common;
+
+use common::Fixture;
+use provenance_layer::audit;
+use provenance_layer::*;
+use uuid::Uuid;
+
+/// Subjects that failed `check` in an audit report.
+fn audit_failures(report: &audit::AuditReport, check: &str) -> Vec<String> {
+    let check = report.checks.iter().find(|c| c.name == check).unwrap();
+    check.failures.iter().map(|failure| failure.subject.clone()).collect()
+}
+
+#[tokio::test]
+async fn audit_detects_tampering() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let b = f.artifact("b").await;
+    let first = f.event("alice", "touch", &[], &[a.id]).await;
+    f.event("bob", "derive", &[a.id], &[b.id]).await;
+    f.service.create_block().await.unwrap();
+    f.event("carol", "touch", &[], &[b.id]).await;
+
+    let report = audit::audit(f.storage.clone()).unwrap();
+    assert!(report.valid, "{:?}", report.checks);
+    assert_eq!((report.events, report.artifacts, report.blocks), (3, 2, 1));
+
+    // Rewrite a sealed event and append an unsigned one that closes a cycle b -> a
+    // and references an unregistered artifact
+    let mut forged = first.clone();
+    forged.context = serde_json::json!({"forged": true});
+    f.storage.insert("log", 0u64.to_be_bytes(), serde_json::to_vec(&forged).unwrap()).unwrap();
+    let cyclic = Event {
+        id: Uuid::new_v4(),
+        in_artifacts: vec![b.id],
+        out_artifacts: vec![a.id, Uuid::new_v4()],
+        signature: None,
+        seq: Some(3),
+        origin: None,
+        ..first.clone()
+    };
+    f.storage.insert("log", 3u64.to_be_bytes(), serde_json::to_vec(&cyclic).unwrap()).unwrap();
+    f.storage.insert("event_seqs", cyclic.id.to_string(), 3u64.to_be_bytes()).unwrap();
+
+    let report = audit::audit(f.storage.clone()).unwrap();
+    assert!(!report.valid);
+    let (first, cyclic) = (format!("event:{}", first.id), format!("event:{}", cyclic.id));
+    assert_eq!(audit_failures(&report, "event_signatures"), vec![first, cyclic.clone()]);
+    assert_eq!(audit_failures(&report, "dag_acyclic"), vec![cyclic.clone()]);
+    assert_eq!(audit_failures(&report, "referential_integrity"), vec![cyclic, "block:0".to_string()]);
+    assert!(audit_failures(&report, "log_sequence").is_empty());
+    assert!(audit_failures(&report, "block_chain").is_empty());
+    assert!(audit_failures(&report, "merkle_roots").is_empty());
+
+    // Dropping an event from a sealed block breaks its Merkle root and the chain
+    let mut block: Block = serde_json::from_slice(&f.storage.get("blocks", &0u64.to_be_bytes()).unwrap().unwrap()).unwrap();
+    block.events.pop();
+    f.storage.insert("blocks", 0u64.to_be_bytes(), serde_json::to_vec(&block).unwrap()).unwrap();
+    let report = audit::audit(f.storage.clone()).unwrap();
+    assert_eq!(audit_failures(&report, "merkle_roots"), vec!["block:0"]);
+    assert_eq!(audit_failures(&report, "block_chain"), vec!["block:0"]);
+}
+
+#[tokio::test]
+async fn audit_accepts_migrated_logs() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+    f.event("bob", "touch", &[], &[a.id]).await;
+    f.unsequence_log();
+    let report = audit::audit(f.storage.clone()).unwrap();
+    assert!(report.valid, "{:?}", report.checks);
+
+    // Migrated events keep no sequence number; those appended after them need theirs
+    let mut service = f.reopen().await;
+    service.log_event(common::event("carol", "touch", &[], &[a.id])).await.unwrap();
+    service.create_block().await.unwrap();
+    let report = audit::audit(f.storage.clone()).unwrap();
+    assert!(report.valid, "{:?}", report.checks);
+    assert_eq!(report.events, 3);
+
+    let mut moved: Event = serde_json::from_slice(&f.storage.get("log", &2u64.to_be_bytes()).unwrap().unwrap()).unwrap();
+    moved.seq = None;
+    f.storage.insert("log", 2u64.to_be_bytes(), serde_json::to_vec(&moved).unwrap()).unwrap();
+    let report = audit::audit(f.storage.clone()).unwrap();
+    assert_eq!(audit_failures(&report, "log_sequence"), vec![format!("event:{}", moved.id)]);
+}
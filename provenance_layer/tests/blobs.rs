@@ -0,0 +1,70 @@
+// Content-addressed blob store and the content checks made on registration.
+
+mod common;
+
+use common::{artifact, Fixture};
+use provenance_layer::blobstore::{self, BlobStore};
+use provenance_layer::*;
+
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+#[tokio::test]
+async fn blobs_are_content_addressed() {
+    let f = Fixture::new().await;
+    let blobs = BlobStore::open(f.dir.join("store")).unwrap();
+
+    let stored = blobs.put(b"hello").await.unwrap();
+    assert_eq!((stored.content_hash.as_str(), stored.size, stored.created), (HELLO_SHA256, 5, true));
+    assert!(blobs.contains(HELLO_SHA256));
+    let path = blobs.path(HELLO_SHA256).unwrap();
+    assert!(path.ends_with(format!("sha256/2c/{}", HELLO_SHA256)));
+
+    // Identical content, streamed in chunks, is stored once
+    let mut writer = blobs.writer().await.unwrap();
+    writer.write(b"hel").await.unwrap();
+    writer.write(b"lo").await.unwrap();
+    let again = writer.finish().await.unwrap();
+    assert_eq!((again.content_hash.as_str(), again.created), (HELLO_SHA256, false));
+    assert_eq!(std::fs::read_dir(f.dir.join("store/tmp")).unwrap().count(), 0);
+    // An upload broken off before `finish` leaves nothing behind
+    let mut broken = blobs.writer().await.unwrap();
+    broken.write(b"hel").await.unwrap();
+    drop(broken);
+    assert_eq!(std::fs::read_dir(f.dir.join("store/tmp")).unwrap().count(), 0);
+
+    assert_eq!(blobs.read(HELLO_SHA256, 5).await.unwrap().as_deref(), Some(&b"hello"[..]));
+    assert!(blobs.read(HELLO_SHA256, 4).await.unwrap().is_none());
+    assert!(blobs.verify(HELLO_SHA256).await.unwrap());
+
+    // Missing blobs and names that are not content hashes resolve to nothing
+    let missing = "0".repeat(64);
+    assert!(!blobs.contains(&missing) && !blobs.verify(&missing).await.unwrap());
+    assert!(blobs.read(&missing, u64::MAX).await.unwrap().is_none());
+    for name in ["../../etc/passwd", &HELLO_SHA256.to_uppercase(), &HELLO_SHA256[1..]] {
+        assert!(!blobstore::is_content_hash(name));
+        assert!(blobs.path(name).is_none());
+    }
+
+    // Bytes changed on disk no longer verify
+    std::fs::write(&path, b"jello").unwrap();
+    assert!(!blobs.verify(HELLO_SHA256).await.unwrap());
+}
+
+#[tokio::test]
+async fn registration_checks_content() {
+    let mut f = Fixture::new().await;
+    let err = f.service.register_artifact(artifact("x", &"0".repeat(64))).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ContentMismatch(_)));
+    let err = f.service.register_artifact(artifact("x", "not a hash")).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ContentMismatch(_)));
+
+    let blob = f.service.blobs().put(b"paper").await.unwrap();
+    let registered = f.service.register_artifact(artifact("paper", &blob.content_hash)).await.unwrap();
+    assert_eq!(registered.content_hash, blob.content_hash);
+
+    // Content corrupted after upload is refused
+    let path = f.service.blobs().path(&blob.content_hash).unwrap();
+    std::fs::write(path, b"tampered").unwrap();
+    let err = f.service.register_artifact(artifact("copy", &blob.content_hash)).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ContentMismatch(_)));
+}
@@ -0,0 +1,235 @@
+// Sealing, chaining, checkpointing and anchoring blocks.
+
+mod common;
+
+use chrono::Utc;
+use common::{event, Fixture};
+use async_trait::async_trait;
+use provenance_layer::anchor::{self, Anchor, AnchorReceipt, LocalTsa, TimestampAnchor, LOCAL_TSA_NAME};
+use provenance_layer::merkle;
+use provenance_layer::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn blocks_chain_and_prove() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    assert!(f.service.create_block().await.unwrap().is_none());
+
+    let first = f.event("alice", "touch", &[], &[a.id]).await;
+    let err = f.service.get_inclusion_proof(first.id).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::EventNotSealed));
+    f.event("bob", "touch", &[], &[a.id]).await;
+    let block = f.service.create_block().await.unwrap().unwrap();
+    assert_eq!(block.height, 0);
+    assert_eq!(block.events.len(), 2);
+
+    let third = f.event("carol", "touch", &[], &[a.id]).await;
+    let block = f.service.create_block().await.unwrap().unwrap();
+    assert_eq!(block.height, 1);
+    assert_eq!(f.service.list_blocks(0, None).await.unwrap().len(), 2);
+    let tail = f.service.list_blocks(1, Some(5)).await.unwrap();
+    assert_eq!(tail.iter().map(|b| b.id).collect::<Vec<_>>(), vec![block.id]);
+    assert_eq!(f.service.get_block(block.id).await.unwrap().unwrap().height, 1);
+    assert!(f.service.get_block(Uuid::new_v4()).await.unwrap().is_none());
+
+    let report = f.service.verify_chain().await.unwrap();
+    assert!(report.valid);
+    assert_eq!(report.blocks_verified, 2);
+
+    for event in [first, third] {
+        let proof = f.service.get_inclusion_proof(event.id).await.unwrap();
+        assert!(merkle::verify_inclusion(&event, &proof).unwrap());
+    }
+    let err = f.service.get_inclusion_proof(Uuid::new_v4()).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::EventNotFound));
+}
+
+#[tokio::test]
+async fn checkpoints_only_grow() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+    f.service.create_block().await.unwrap();
+    let old = f.service.checkpoint().await.unwrap();
+    assert_eq!(old.tree_size, 1);
+    assert_eq!(f.service.checkpoint().await.unwrap().timestamp, old.timestamp);
+
+    for actor in ["bob", "carol", "dave"] {
+        f.event(actor, "touch", &[], &[a.id]).await;
+        f.service.create_block().await.unwrap();
+    }
+    let new = f.service.checkpoint().await.unwrap();
+    assert_eq!(new.tree_size, 4);
+
+    let public_key = f.service.keystore().public_key(CHECKPOINT_SIGNER).unwrap().unwrap();
+    assert!(old.verify_signature(&public_key).unwrap());
+    assert!(new.verify_signature(&public_key).unwrap());
+
+    let proof = f.service.consistency_proof(1, 4).await.unwrap();
+    assert!(merkle::verify_checkpoints(&old, &new, &proof));
+    let mut forged = new.clone();
+    forged.root_hash = old.root_hash.clone();
+    assert!(!merkle::verify_checkpoints(&old, &forged, &proof));
+    assert!(!forged.verify_signature(&public_key).unwrap());
+
+    let err = f.service.consistency_proof(2, 5).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::InvalidTreeSize { size: 4, .. }));
+}
+
+#[tokio::test]
+async fn blocks_are_anchored() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+    let unanchored = f.service.create_block().await.unwrap().unwrap();
+    assert!(unanchored.anchors.is_empty());
+
+    let tsa = Arc::new(TimestampAnchor::new(LocalTsa::generate().unwrap()));
+    let fingerprint = tsa.authority().fingerprint();
+    let mut service = f.reopen().await.with_anchor(tsa);
+
+    // Sealing anchors the new block; older blocks can be anchored afterwards
+    service.log_event(event("bob", "touch", &[], &[a.id])).await.unwrap();
+    let block = service.create_block().await.unwrap().unwrap();
+    assert_eq!(block.anchors.len(), 1);
+    assert_eq!(block.anchors[0].anchor, LOCAL_TSA_NAME);
+    assert_eq!(block.anchors[0].block_hash, block.hash);
+    let retried = service.anchor_block(unanchored.id).await.unwrap();
+    assert_eq!(retried.anchors.len(), 1);
+    assert_eq!(service.anchor_block(unanchored.id).await.unwrap().anchors.len(), 1);
+
+    let stored = service.list_blocks(0, None).await.unwrap();
+    assert!(stored.iter().all(|b| b.anchors.len() == 1));
+    assert_eq!(service.get_block(block.id).await.unwrap().unwrap().anchors[0].proof, block.anchors[0].proof);
+    for b in [&unanchored, &block] {
+        let report = service.verify_anchors(b.id).await.unwrap();
+        assert_eq!(report.len(), 1);
+        assert!(report[0].valid, "{:?}", report[0].reason);
+        assert_eq!(report[0].signer.as_deref(), Some(fingerprint.as_str()));
+    }
+
+    // Receipts are bound to their block hash and time
+    let receipt = &block.anchors[0];
+    assert!(!anchor::verify_receipt(&unanchored.hash, receipt).valid);
+    let mut moved = receipt.clone();
+    moved.block_hash = unanchored.hash.clone();
+    assert!(!anchor::verify_receipt(&unanchored.hash, &moved).valid);
+    let mut backdated = receipt.clone();
+    backdated.anchored_at -= chrono::Duration::days(1);
+    assert!(!anchor::verify_receipt(&block.hash, &backdated).valid);
+    let mut forged = receipt.clone();
+    let mut token = hex::decode(&forged.proof).unwrap();
+    let last = token.len() - 1;
+    token[last] ^= 1;
+    forged.proof = hex::encode(token);
+    assert!(!anchor::verify_receipt(&block.hash, &forged).valid);
+
+    let err = service.anchor_block(Uuid::new_v4()).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::BlockNotFound));
+}
+
+/// An anchor that is unreachable until `up` is set.
+struct FlakyAnchor {
+    up: AtomicBool,
+}
+
+#[async_trait]
+impl Anchor for FlakyAnchor {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    async fn anchor(&self, block: &Block) -> Result<AnchorReceipt, ProvenanceError> {
+        if !self.up.load(Ordering::SeqCst) {
+            return Err(ProvenanceError::AnchorError("unreachable".to_string()));
+        }
+        Ok(AnchorReceipt {
+            anchor: self.name().to_string(),
+            kind: "test".to_string(),
+            block_hash: block.hash.clone(),
+            anchored_at: Utc::now(),
+            proof: String::new(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn anchor_failures_are_recorded() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let flaky = Arc::new(FlakyAnchor { up: AtomicBool::new(false) });
+    let mut service = f.reopen().await.with_anchor(flaky.clone());
+
+    // A failing anchor does not fail the seal; the block records it
+    service.log_event(event("alice", "touch", &[], &[a.id])).await.unwrap();
+    let block = service.create_block().await.unwrap().unwrap();
+    assert!(block.anchors.is_empty());
+    assert_eq!(block.anchor_failures.len(), 1);
+    assert_eq!(block.anchor_failures[0].anchor, "flaky");
+    assert!(block.anchor_failures[0].error.contains("unreachable"));
+    let stored = service.get_block(block.id).await.unwrap().unwrap();
+    assert_eq!(stored.anchor_failures[0].failed_at, block.anchor_failures[0].failed_at);
+
+    // A retry reports the failure and replaces the recorded one
+    let err = service.anchor_block(block.id).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::AnchorError(_)));
+    let stored = service.get_block(block.id).await.unwrap().unwrap();
+    assert_eq!(stored.anchor_failures.len(), 1);
+    assert!(stored.anchor_failures[0].failed_at > block.anchor_failures[0].failed_at);
+
+    // A receipt clears it
+    flaky.up.store(true, Ordering::SeqCst);
+    let anchored = service.anchor_block(block.id).await.unwrap();
+    assert!(anchored.anchor_failures.is_empty() && anchored.anchors.len() == 1);
+    let stored = service.get_block(block.id).await.unwrap().unwrap();
+    assert!(stored.anchor_failures.is_empty() && stored.anchors.len() == 1);
+}
+
+/// Waits up to 5s for the background sealer to seal the block at `height`.
+async fn sealed_by_sealer(f: &Fixture, height: u64) -> SealerStatus {
+    for _ in 0..100 {
+        let status = f.service.sealer_status();
+        if status.last_sealed_height.is_some_and(|sealed| sealed >= height) {
+            return status;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("no block {} sealed: {:?}", height, f.service.sealer_status());
+}
+
+#[tokio::test]
+async fn sealer_follows_the_policy() {
+    let now = Utc::now();
+    let policy = SealingPolicy { max_events: Some(3), max_age_secs: Some(60) };
+    assert!(!policy.should_seal(0, None, now));
+    assert!(!policy.should_seal(2, Some(now - chrono::Duration::seconds(59)), now));
+    assert!(policy.should_seal(3, Some(now), now));
+    assert!(policy.should_seal(1, Some(now - chrono::Duration::seconds(60)), now));
+    let on_demand = SealingPolicy { max_events: None, max_age_secs: None };
+    assert!(!on_demand.should_seal(1000, Some(now - chrono::Duration::days(1)), now));
+
+    // Sealed once enough events are pending
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let sealer = f.service.spawn_sealer(SealingPolicy { max_events: Some(2), max_age_secs: None });
+    assert_eq!(f.service.sealer_status().policy.unwrap().max_events, Some(2));
+    let first = f.event("alice", "touch", &[], &[a.id]).await;
+    let second = f.event("bob", "touch", &[], &[a.id]).await;
+    let status = sealed_by_sealer(&f, 0).await;
+    assert!(status.last_error.is_none() && status.failures == 0);
+    let block = f.service.list_blocks(0, None).await.unwrap().remove(0);
+    assert_eq!(block.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![first.id, second.id]);
+    sealer.abort();
+
+    // Sealed once the oldest pending event is old enough
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let sealer = f.service.spawn_sealer(SealingPolicy { max_events: None, max_age_secs: Some(1) });
+    let event = f.event("alice", "touch", &[], &[a.id]).await;
+    let status = sealed_by_sealer(&f, 0).await;
+    assert!(status.last_sealed_at.unwrap() >= event.timestamp + chrono::Duration::seconds(1));
+    sealer.abort();
+}
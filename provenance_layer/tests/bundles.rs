@@ -0,0 +1,103 @@
+// Portable provenance bundles: export, offline verification and import.
+
+mod common;
+
+use common::{artifact, event, Fixture};
+use provenance_layer::audit;
+use provenance_layer::bundle::{self, ProvenanceBundle};
+use provenance_layer::*;
+
+/// Subjects of the failures found in a bundle.
+fn bundle_failures(bundle: &ProvenanceBundle) -> Vec<String> {
+    let verification = bundle::verify_bundle(bundle).unwrap();
+    verification.failures.iter().map(|failure| failure.subject.clone()).collect()
+}
+
+#[tokio::test]
+async fn bundles_verify_offline() {
+    let mut f = Fixture::new().await;
+    let notes = f.artifact("notes").await;
+    let a = f.artifact("draft").await;
+    let b = f.artifact("final").await;
+    let outline = f.event("alice", "outline", &[notes.id], &[a.id]).await;
+    f.service.create_block().await.unwrap();
+    let edit = f.event("alice", "edit", &[a.id], &[b.id]).await;
+    f.service.cosign_artifact(b.id, "bob", Some("editor".to_string())).await.unwrap();
+    let blob = f.service.blobs().put(b"final v2").await.unwrap();
+    let (v2, _) = f.service.register_artifact_version(b.id, artifact("final", &blob.content_hash), "alice").await.unwrap();
+    f.service.create_block().await.unwrap();
+    let review = f.event("carol", "review", &[a.id], &[b.id]).await;
+
+    // Verified from its serialized form alone
+    let exported = f.service.export_bundle(b.id, LineageQuery::default()).await.unwrap();
+    let bundle: ProvenanceBundle = serde_json::from_slice(&serde_json::to_vec(&exported).unwrap()).unwrap();
+    let verification = bundle::verify_bundle(&bundle).unwrap();
+    assert!(verification.valid, "{:?}", verification.failures);
+    assert!(!verification.complete);
+    assert_eq!(verification.unsealed_events, vec![review.id]);
+    assert_eq!((verification.events, verification.artifacts), (4, 4));
+    let log_key = f.service.keystore().public_key(CHECKPOINT_SIGNER).unwrap().map(hex::encode);
+    assert_eq!(verification.log_public_key, log_key);
+    assert_eq!(bundle.block_proofs.len(), 2);
+
+    let mut tampered = bundle.clone();
+    let forged = tampered.events.iter_mut().find(|e| e.id == edit.id).unwrap();
+    forged.context = serde_json::json!({"forged": true});
+    assert_eq!(bundle_failures(&tampered), vec![format!("event:{}", edit.id); 2]);
+    let mut tampered = bundle.clone();
+    tampered.artifacts.iter_mut().find(|x| x.id == b.id).unwrap().metadata = serde_json::json!({"name": "other"});
+    assert_eq!(bundle_failures(&tampered), vec![format!("artifact:{}", b.id)]);
+    let mut tampered = bundle.clone();
+    tampered.key_histories.remove("bob");
+    assert_eq!(bundle_failures(&tampered), vec![format!("artifact:{}", b.id)]);
+    let mut tampered = bundle.clone();
+    tampered.checkpoint.root_hash = tampered.block_proofs[0].block_hash.clone();
+    // A checkpoint over other blocks unties every sealed event from it
+    let failures = bundle_failures(&tampered);
+    assert_eq!(failures[..3], ["checkpoint", "block:0", "block:1"]);
+    assert_eq!(failures[3], format!("event:{}", outline.id));
+    assert_eq!(failures.len(), 3 + bundle.inclusion_proofs.len());
+
+    // Imported elsewhere with the artifacts' IDs and authorship and the events'
+    // signatures kept; a second import is a no-op
+    let mut g = Fixture::new().await;
+    let err = g.service.import_bundle(&tampered).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::InvalidBundle(_)));
+    let summary = g.service.import_bundle(&bundle).await.unwrap();
+    assert_eq!(summary.artifacts.len(), 4);
+    assert_eq!(summary.events.len(), 4);
+    let logged = &summary.events[1];
+    assert_eq!((logged.id, logged.timestamp, logged.seq), (edit.id, edit.timestamp, Some(1)));
+    assert_eq!(logged.signature.as_ref().unwrap().signature, edit.signature.as_ref().unwrap().signature);
+    let sealed_in = bundle.inclusion_proofs.iter().find(|p| p.event_id == edit.id).unwrap();
+    let origin = EventOrigin { seq: edit.seq, block_hash: Some(sealed_in.block_hash.clone()) };
+    assert_eq!(logged.origin, Some(origin));
+    let report = audit::audit(g.storage.clone()).unwrap();
+    assert!(report.valid, "{:?}", report.checks);
+    // Only public keys travel
+    let err = g.service.log_event(event("alice", "edit", &[a.id], &[b.id])).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::KeyNotFound(_)));
+    let imported = g.service.get_artifact(b.id).await.unwrap().unwrap();
+    assert_eq!(imported.authorship.len(), 1);
+    assert_eq!(g.service.latest_version(b.id).await.unwrap().id, v2.id);
+    let lineage = g.service.get_lineage(b.id, LineageQuery::default()).await.unwrap();
+    assert!(lineage.ancestors.contains(&a.id) && lineage.ancestors.contains(&notes.id));
+    let again = g.service.import_bundle(&bundle).await.unwrap();
+    assert!(again.artifacts.is_empty() && again.events.is_empty());
+    let again = f.service.import_bundle(&bundle).await.unwrap();
+    assert!(again.artifacts.is_empty() && again.events.is_empty());
+
+    // Imported events seal here and verify when exported again
+    g.service.create_block().await.unwrap();
+    let reexported = g.service.export_bundle(b.id, LineageQuery::default()).await.unwrap();
+    let verification = bundle::verify_bundle(&reexported).unwrap();
+    assert!(verification.valid && verification.complete, "{:?}", verification.failures);
+
+    // A signer holding other keys here refuses the whole bundle
+    let mut h = Fixture::new().await;
+    h.service.keystore().generate("carol").unwrap();
+    let err = h.service.import_bundle(&bundle).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::InvalidBundle(_)));
+    assert!(h.service.get_artifact(b.id).await.unwrap().is_none());
+    assert!(h.service.get_events(None).await.unwrap().is_empty());
+}
@@ -0,0 +1,62 @@
+// Checks the canonical encoding against the shipped interoperability vectors.
+
+use provenance_layer::canonical;
+use provenance_layer::{Block, Event};
+use serde_json::Value;
+
+fn vectors() -> Value {
+    serde_json::from_str(include_str!("vectors/canonical.json")).unwrap()
+}
+
+fn cases<'a>(vectors: &'a Value, section: &str) -> &'a Vec<Value> {
+    vectors[section].as_array().unwrap()
+}
+
+#[test]
+fn canonical_json_vectors() {
+    let vectors = vectors();
+    for case in cases(&vectors, "canonical") {
+        let name = case["name"].as_str().unwrap();
+        let input: Value = serde_json::from_str(case["input"].as_str().unwrap()).unwrap();
+        let canonical = canonical::to_canonical_json(&input).unwrap();
+        assert_eq!(
+            String::from_utf8(canonical).unwrap(),
+            case["canonical"].as_str().unwrap(),
+            "{}",
+            name
+        );
+        assert_eq!(canonical::digest_hex(&input).unwrap(), case["sha256"].as_str().unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn event_signing_payload_vectors() {
+    let vectors = vectors();
+    for case in cases(&vectors, "event_signing_payload") {
+        let event: Event = serde_json::from_value(case["event"].clone()).unwrap();
+        let payload = event.signing_payload().unwrap();
+        assert_eq!(hex::encode(payload), case["sha256"].as_str().unwrap(), "{}", case["name"]);
+    }
+}
+
+#[test]
+fn metadata_digest_vectors() {
+    let vectors = vectors();
+    for case in cases(&vectors, "metadata_digest") {
+        let canonical = canonical::to_canonical_json(&case["metadata"]).unwrap();
+        assert_eq!(String::from_utf8(canonical).unwrap(), case["canonical"].as_str().unwrap());
+        assert_eq!(canonical::digest_hex(&case["metadata"]).unwrap(), case["sha256"].as_str().unwrap());
+    }
+}
+
+#[test]
+fn block_hash_vectors() {
+    let vectors = vectors();
+    for case in cases(&vectors, "block_hash") {
+        let events: Vec<Event> = serde_json::from_value(case["events"].clone()).unwrap();
+        let canonical = canonical::to_canonical_json(&events).unwrap();
+        assert_eq!(String::from_utf8(canonical).unwrap(), case["canonical"].as_str().unwrap());
+        let previous_hash = case["previous_hash"].as_str().unwrap();
+        assert_eq!(Block::compute_hash(&events, previous_hash).unwrap(), case["sha256"].as_str().unwrap());
+    }
+}
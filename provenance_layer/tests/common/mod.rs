@@ -0,0 +1,114 @@
+// Shared fixture for the integration tests: a service over a fresh storage backend
+// with its files in a temporary directory, and builders for artifacts and events.
+// Each test binary uses a different subset of it.
+#![allow(dead_code)]
+
+use chrono::Utc;
+use provenance_layer::blobstore::BlobStore;
+use provenance_layer::provenance_impl::ProvenanceServiceImpl;
+use provenance_layer::storage::{Backend, Batch, Storage, StorageConfig};
+use provenance_layer::{Artifact, Event, ProvenanceService};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A service on a fresh backend, with its files in a temporary directory.
+pub struct Fixture {
+    pub dir: PathBuf,
+    pub storage: Arc<dyn Storage>,
+    pub service: ProvenanceServiceImpl,
+}
+
+impl Fixture {
+    /// A service on the in-memory backend.
+    pub async fn new() -> Self {
+        Self::with_backend(Backend::Memory).await
+    }
+
+    pub async fn with_backend(backend: Backend) -> Self {
+        let dir = std::env::temp_dir().join(format!("pl-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = StorageConfig { backend, path: dir.join("db") }.open().unwrap();
+        let service = Self::open(&dir, storage.clone()).await;
+        Self { dir, storage, service }
+    }
+
+    async fn open(dir: &Path, storage: Arc<dyn Storage>) -> ProvenanceServiceImpl {
+        let blobs = BlobStore::open(dir.join("blobs")).unwrap();
+        ProvenanceServiceImpl::open(storage, blobs, 16).await.unwrap()
+    }
+
+    /// Opens a second service over the same storage, as after a restart.
+    pub async fn reopen(&self) -> ProvenanceServiceImpl {
+        Self::open(&self.dir, self.storage.clone()).await
+    }
+
+    /// Registers an artifact whose content is its name.
+    pub async fn artifact(&mut self, name: &str) -> Artifact {
+        self.stored(name, name.as_bytes()).await
+    }
+
+    /// Registers an artifact whose content is `content`.
+    pub async fn stored(&mut self, name: &str, content: &[u8]) -> Artifact {
+        let blob = self.service.blobs().put(content).await.unwrap();
+        self.service.register_artifact(artifact(name, &blob.content_hash)).await.unwrap()
+    }
+
+    pub async fn event(&mut self, actor: &str, operation: &str, inputs: &[Uuid], outputs: &[Uuid]) -> Event {
+        self.service.log_event(event(actor, operation, inputs, outputs)).await.unwrap()
+    }
+
+    /// Rewrites an unsealed log as a database from before sequence numbers wrote it:
+    /// events keyed by ID alone and signed without `seq`. Reopening migrates it.
+    pub fn unsequence_log(&self) {
+        let mut batch = Batch::new();
+        for entry in self.storage.iter("log") {
+            let (_seq, value) = entry.unwrap();
+            let mut event: Event = serde_json::from_slice(&value).unwrap();
+            event.seq = None;
+            let payload = event.signing_payload().unwrap();
+            event.signature = Some(self.service.keystore().sign(&event.actor, &payload).unwrap());
+            batch.insert("events", event.id.to_string(), serde_json::to_vec(&event).unwrap());
+        }
+        batch.remove("chain", "log_sequenced");
+        self.storage.commit(batch).unwrap();
+        self.storage.drop_tree("log").unwrap();
+        self.storage.drop_tree("event_seqs").unwrap();
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+pub fn artifact(name: &str, content_hash: &str) -> Artifact {
+    Artifact {
+        id: Uuid::nil(),
+        name: name.to_string(),
+        version: "1".to_string(),
+        content_hash: content_hash.to_string(),
+        metadata: serde_json::json!({"name": name}),
+        metadata_digest: String::new(),
+        registered_at: Utc::now(),
+        family_id: None,
+        previous_version: None,
+        authorship: Vec::new(),
+    }
+}
+
+pub fn event(actor: &str, operation: &str, inputs: &[Uuid], outputs: &[Uuid]) -> Event {
+    Event {
+        id: Uuid::nil(),
+        timestamp: Utc::now(),
+        actor: actor.to_string(),
+        in_artifacts: inputs.to_vec(),
+        operation: operation.to_string(),
+        out_artifacts: outputs.to_vec(),
+        context: serde_json::json!({}),
+        signature: None,
+        seq: None,
+        origin: None,
+    }
+}
@@ -0,0 +1,80 @@
+// Metadata and content diffs.
+
+use provenance_layer::diff::{self, ContentDiff, DiffOp};
+use serde_json::json;
+
+/// (path, op) of each entry of a JSON diff.
+fn ops(from: serde_json::Value, to: serde_json::Value) -> Vec<(String, DiffOp)> {
+    diff::json_diff(&from, &to).into_iter().map(|entry| (entry.path, entry.op)).collect()
+}
+
+/// (op, line, text) of each change of a text diff.
+fn lines(from: &str, to: &str) -> Vec<(DiffOp, usize, String)> {
+    diff::text_diff(from, to).into_iter().map(|change| (change.op, change.line, change.text)).collect()
+}
+
+#[test]
+fn json_diffs_walk_objects() {
+    assert!(diff::json_diff(&json!({"a": [1, 2]}), &json!({"a": [1, 2]})).is_empty());
+    let changes = ops(
+        json!({"title": "Draft", "tags": ["a"], "meta": {"x": 1, "y": 2}, "gone": null}),
+        json!({"title": "Final", "tags": ["a", "b"], "meta": {"x": 1, "z": 3}, "a/b~c": true}),
+    );
+    assert_eq!(
+        changes,
+        [
+            ("/a~1b~0c", DiffOp::Added),
+            ("/gone", DiffOp::Removed),
+            ("/meta/y", DiffOp::Removed),
+            ("/meta/z", DiffOp::Added),
+            ("/tags", DiffOp::Changed),
+            ("/title", DiffOp::Changed),
+        ]
+        .map(|(path, op)| (path.to_string(), op))
+    );
+
+    // Values are kept on the side they come from; a type change replaces the value
+    let entries = diff::json_diff(&json!({"n": 1}), &json!({"n": {"m": 1}}));
+    assert_eq!((entries[0].from.clone(), entries[0].to.clone()), (Some(json!(1)), Some(json!({"m": 1}))));
+    assert_eq!(ops(json!(1), json!(2)), vec![(String::new(), DiffOp::Changed)]);
+    let serialized = serde_json::to_value(diff::json_diff(&json!({}), &json!({"k": 1}))).unwrap();
+    assert_eq!(serialized, json!([{"path": "/k", "op": "added", "to": 1}]));
+}
+
+#[test]
+fn text_diffs_follow_a_common_subsequence() {
+    assert!(lines("a\nb", "a\nb").is_empty());
+    assert_eq!(lines("", "a"), vec![(DiffOp::Added, 1, "a".to_string())]);
+    assert_eq!(lines("a\nb\nc", "a\nc"), vec![(DiffOp::Removed, 2, "b".to_string())]);
+    // Line numbers are in the old text for removals and the new one for additions
+    assert_eq!(
+        lines("keep\nold 1\nsame\nold 2\nend", "keep\nsame\nnew\nend\nmore"),
+        vec![
+            (DiffOp::Removed, 2, "old 1".to_string()),
+            (DiffOp::Removed, 4, "old 2".to_string()),
+            (DiffOp::Added, 3, "new".to_string()),
+            (DiffOp::Added, 5, "more".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn content_diffs_pick_a_format() {
+    let Some(ContentDiff::Json { changes }) = diff::content_diff(br#"{"k":1}"#, br#"{"k":2}"#) else { panic!() };
+    assert_eq!(changes[0].path, "/k");
+    let Some(ContentDiff::Text { added, removed, .. }) = diff::content_diff(b"one\ntwo", b"one\n2\nthree") else {
+        panic!()
+    };
+    assert_eq!((added, removed), (2, 1));
+    // JSON scalars and JSON against text are compared as text
+    assert!(matches!(diff::content_diff(b"1", b"2"), Some(ContentDiff::Text { .. })));
+    assert!(matches!(diff::content_diff(br#"{"k":1}"#, b"k = 1"), Some(ContentDiff::Text { .. })));
+    // Binary content is not diffed
+    assert!(diff::content_diff(b"text", &[0xff, 0xfe]).is_none());
+    assert!(diff::content_diff(b"a\0b", b"a\0c").is_none());
+    assert!(diff::content_diff(b"same", b"same").unwrap().is_empty());
+
+    let serialized = serde_json::to_value(diff::content_diff(b"a", b"b").unwrap()).unwrap();
+    assert_eq!(serialized["format"], "text");
+    assert_eq!(serialized["lines"][0], json!({"op": "removed", "line": 1, "text": "a"}));
+}
@@ -0,0 +1,65 @@
+// Event queries over the secondary indexes.
+
+mod common;
+
+use chrono::{DateTime, Utc};
+use common::Fixture;
+use provenance_layer::*;
+
+#[tokio::test]
+async fn queries_paginate() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let b = f.artifact("b").await;
+    let mut logged = Vec::new();
+    for i in 0..7 {
+        let actor = if i % 2 == 0 { "alice" } else { "bob" };
+        let out = if i < 4 { a.id } else { b.id };
+        logged.push(f.event(actor, "touch", &[], &[out]).await.id);
+    }
+
+    for order in [SortOrder::Asc, SortOrder::Desc] {
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let query = EventQuery { order, limit: Some(3), cursor, ..EventQuery::default() };
+            let page = f.service.query_events(query).await.unwrap();
+            seen.extend(page.events.iter().map(|e| e.id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        let mut expected = logged.clone();
+        if order == SortOrder::Desc {
+            expected.reverse();
+        }
+        assert_eq!(seen, expected);
+    }
+
+    let filter = EventFilter { actor: Some("alice".to_string()), artifact_id: Some(b.id), ..EventFilter::default() };
+    let events = f.service.get_events(Some(filter)).await.unwrap();
+    assert_eq!(events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![logged[4], logged[6]]);
+
+    let query = EventQuery { cursor: Some("zz".to_string()), ..EventQuery::default() };
+    assert!(matches!(f.service.query_events(query).await, Err(ProvenanceError::InvalidCursor)));
+}
+
+#[tokio::test]
+async fn queries_take_extreme_values() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let long = "x".repeat(usize::from(u16::MAX) + 1);
+    let logged = f.event(&long, "touch", &[], &[a.id]).await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+
+    // The latest time there is bounds nothing
+    let until_max = EventFilter { end_time: Some(DateTime::<Utc>::MAX_UTC), ..EventFilter::default() };
+    assert_eq!(f.service.get_events(Some(until_max)).await.unwrap().len(), 2);
+    // Values too long for the index key are found by their hash
+    let by_actor = EventFilter { actor: Some(long.clone()), ..EventFilter::default() };
+    let events = f.service.get_events(Some(by_actor)).await.unwrap();
+    assert_eq!(events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![logged.id]);
+    let other = EventFilter { actor: Some(format!("{}y", long)), ..EventFilter::default() };
+    assert!(f.service.get_events(Some(other)).await.unwrap().is_empty());
+}
@@ -0,0 +1,146 @@
+// Actor key registration, rotation and revocation.
+
+mod common;
+
+use chrono::Utc;
+use common::{event, Fixture};
+use provenance_layer::audit;
+use provenance_layer::bundle;
+use provenance_layer::*;
+
+/// Checks `event`'s signature against the signer's key valid at `at`.
+async fn signature_at(f: &Fixture, event: &Event, at: chrono::DateTime<Utc>) -> keystore::SignatureVerification {
+    let payload = event.signing_payload().unwrap();
+    f.service.verify_signature(&payload, event.signature.as_ref().unwrap(), at).await.unwrap()
+}
+
+#[tokio::test]
+async fn keys_rotate_and_revoke() {
+    let mut f = Fixture::new().await;
+    let notes = f.artifact("notes").await;
+    let a = f.artifact("draft").await;
+    let b = f.artifact("final").await;
+    let outline = f.event("alice", "outline", &[notes.id], &[a.id]).await;
+
+    // The old key signs the rotation and still covers what it signed before
+    let rotation = f.service.rotate_key("alice", None, false).await.unwrap();
+    assert_eq!((rotation.key.version, rotation.event.operation.as_str()), (1, "key_rotate"));
+    assert_eq!(rotation.event.actor, "alice");
+    assert_eq!(signature_at(&f, &rotation.event, rotation.event.timestamp).await.key_version, Some(0));
+    let edit = f.event("alice", "edit", &[a.id], &[b.id]).await;
+    let verification = signature_at(&f, &outline, outline.timestamp).await;
+    assert!(verification.valid && verification.key_version == Some(0));
+    let verification = signature_at(&f, &edit, edit.timestamp).await;
+    assert!(verification.valid && verification.key_version == Some(1));
+    // An old-key signature dated after the rotation is rejected
+    let verification = signature_at(&f, &outline, edit.timestamp).await;
+    assert!(!verification.valid && !verification.revoked);
+
+    // Revoked by the admin, once its key is provisioned: alice cannot sign, and
+    // later-dated signatures are flagged
+    let err = f.service.revoke_key("alice", None, true).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::KeyNotFound(actor) if actor == KEY_ADMIN));
+    f.service.keystore().provision(KEY_ADMIN).unwrap();
+    let revocation = f.service.revoke_key("alice", Some("laptop lost".to_string()), true).await.unwrap();
+    assert_eq!((revocation.event.actor.as_str(), revocation.event.operation.as_str()), (KEY_ADMIN, "key_revoke"));
+    assert_eq!(revocation.key.revocation_reason.as_deref(), Some("laptop lost"));
+    let err = f.service.log_event(event("alice", "touch", &[], &[a.id])).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::KeyRevoked(_)));
+    assert!(matches!(f.service.rotate_key("alice", None, false).await, Err(ProvenanceError::KeyRevoked(_))));
+    assert!(signature_at(&f, &edit, edit.timestamp).await.valid);
+    let verification = signature_at(&f, &edit, Utc::now()).await;
+    assert!(!verification.valid && verification.revoked);
+    assert!(matches!(f.service.rotate_key("nobody", None, false).await, Err(ProvenanceError::KeyNotFound(_))));
+
+    // A new key may be registered after a revocation
+    let registration = f.service.register_key("alice", None, false).await.unwrap();
+    assert_eq!(registration.key.version, 2);
+    f.event("alice", "touch", &[], &[b.id]).await;
+    let history = f.service.key_history("alice").await.unwrap();
+    assert_eq!(history.iter().map(|key| key.version).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert!(history[0].revoked_at.is_none() && history[1].revoked_at.is_some());
+
+    // Every signature is judged by the key valid when it was made, here and offline
+    f.service.create_block().await.unwrap();
+    assert!(audit::audit(f.storage.clone()).unwrap().valid);
+    let bundle = f.service.export_bundle(b.id, LineageQuery::default()).await.unwrap();
+    assert_eq!(bundle.key_histories["alice"].len(), 3);
+    let verification = bundle::verify_bundle(&bundle).unwrap();
+    assert!(verification.valid, "{:?}", verification.failures);
+}
+
+#[tokio::test]
+async fn signatures_cannot_predate_the_key() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    // A key generated on first use already covers the event it signs
+    let first = f.event("alice", "touch", &[], &[a.id]).await;
+    assert!(f.service.key_history("alice").await.unwrap()[0].valid_from <= first.timestamp);
+    assert!(signature_at(&f, &first, first.timestamp).await.valid);
+
+    // The same key signing a time before it existed is rejected, here and by the audit
+    let mut backdated = first.clone();
+    backdated.timestamp -= chrono::Duration::days(1);
+    let payload = backdated.signing_payload().unwrap();
+    backdated.signature = Some(f.service.keystore().sign("alice", &payload).unwrap());
+    let verification = signature_at(&f, &backdated, backdated.timestamp).await;
+    assert!(!verification.valid && verification.key_version.is_none());
+    assert_eq!(verification.reason.as_deref(), Some("signed before the signer had a key"));
+    f.storage.insert("log", 0u64.to_be_bytes(), serde_json::to_vec(&backdated).unwrap()).unwrap();
+    let report = audit::audit(f.storage.clone()).unwrap();
+    let signatures = report.checks.iter().find(|check| check.name == "event_signatures").unwrap();
+    assert_eq!(signatures.failures[0].subject, format!("event:{}", first.id));
+}
+
+#[tokio::test]
+async fn reserved_actors_are_refused() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    // The log key exists from the start; the others are never generated on first use
+    assert!(f.service.keystore().public_key(CHECKPOINT_SIGNER).unwrap().is_some());
+    for actor in [KEY_ADMIN, anchor::LOCAL_TSA_ACTOR] {
+        assert!(matches!(f.service.keystore().sign(actor, b"data"), Err(ProvenanceError::KeyNotFound(_))));
+    }
+
+    // No caller may act as them
+    for actor in RESERVED_ACTORS {
+        let err = f.service.log_event(event(actor, "touch", &[], &[a.id])).await.unwrap_err();
+        assert!(matches!(err, ProvenanceError::ReservedActor(_)));
+        let err = f.service.cosign_artifact(a.id, actor, None).await.unwrap_err();
+        assert!(matches!(err, ProvenanceError::ReservedActor(_)));
+        let err = f.service.register_key(actor, None, false).await.unwrap_err();
+        assert!(matches!(err, ProvenanceError::ReservedActor(_)));
+        let err = f.service.revoke_key(actor, None, false).await.unwrap_err();
+        assert!(matches!(err, ProvenanceError::ReservedActor(_)));
+    }
+    assert!(f.service.get_events(None).await.unwrap().is_empty());
+    assert!(f.service.keystore().history(KEY_ADMIN).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn key_changes_commit_with_their_events() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+
+    // A change whose event cannot be signed leaves the keys as they were
+    let err = f.service.register_key("dave", None, true).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::KeyNotFound(actor) if actor == KEY_ADMIN));
+    assert!(f.service.key_history("dave").await.unwrap().is_empty());
+    assert!(f.service.keystore().public_key("dave").unwrap().is_none());
+    for err in [
+        f.service.rotate_key("alice", None, true).await.unwrap_err(),
+        f.service.revoke_key("alice", None, true).await.unwrap_err(),
+    ] {
+        assert!(matches!(err, ProvenanceError::KeyNotFound(actor) if actor == KEY_ADMIN));
+    }
+    let history = f.service.key_history("alice").await.unwrap();
+    assert!(history.len() == 1 && history[0].valid_until.is_none());
+    assert_eq!(f.service.get_events(None).await.unwrap().len(), 1);
+
+    // A registered key signs the event recording it
+    let registration = f.service.register_key("dave", None, false).await.unwrap();
+    assert!(signature_at(&f, &registration.event, registration.event.timestamp).await.valid);
+    assert!(matches!(f.service.register_key("dave", None, false).await, Err(ProvenanceError::KeyExists(_))));
+    assert_eq!(f.service.get_events(None).await.unwrap().len(), 2);
+}
@@ -0,0 +1,219 @@
+// Lineage traversal, time travel and change descriptions over G_P.
+
+mod common;
+
+use common::{event, Fixture};
+use provenance_layer::graph::{FilteredGraph, GraphView, ProvenanceGraph};
+use provenance_layer::*;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn lineage_rejects_cycles() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let b = f.artifact("b").await;
+    let c = f.artifact("c").await;
+    f.event("alice", "derive", &[a.id], &[b.id]).await;
+    f.event("alice", "derive", &[b.id], &[c.id]).await;
+
+    let err = f.service.log_event(event("alice", "derive", &[c.id], &[a.id])).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::CycleDetected { .. }));
+
+    let lineage = f.service.get_lineage(c.id, LineageQuery::default()).await.unwrap();
+    assert_eq!(lineage.parent_ids, vec![b.id]);
+    assert_eq!(lineage.ancestors, vec![b.id, a.id]);
+    assert_eq!(lineage.events.len(), 2);
+    let lineage = f.service.get_lineage(a.id, LineageQuery::default()).await.unwrap();
+    assert_eq!(lineage.descendants, vec![b.id, c.id]);
+}
+
+/// An event with a fresh ID deriving `outputs` from `inputs`, as the graph sees it.
+fn edge(inputs: &[Uuid], outputs: &[Uuid]) -> Event {
+    Event { id: Uuid::new_v4(), ..event("alice", "derive", inputs, outputs) }
+}
+
+#[test]
+fn traversals_follow_direction_and_depth() {
+    // a -> b -> c -> d, with x -> c as a second parent of c
+    let [a, b, c, d, x] = [(); 5].map(|_| Uuid::new_v4());
+    let events = [edge(&[a], &[b]), edge(&[b], &[c]), edge(&[c], &[d]), edge(&[x], &[c])];
+    let mut graph = ProvenanceGraph::new();
+    for event in &events {
+        graph.add_event(event);
+    }
+    // Replaying an event adds no duplicate edges
+    graph.add_event(&events[0]);
+    assert_eq!(graph.children(a).unwrap(), vec![b]);
+
+    let backward = graph.traverse(d, LineageDirection::Backward, None).unwrap();
+    assert_eq!((backward.ancestors, backward.descendants.len()), (vec![c, b, x, a], 0));
+    assert_eq!(backward.edges.len(), 4);
+    let forward = graph.traverse(a, LineageDirection::Forward, None).unwrap();
+    assert_eq!((forward.ancestors.len(), forward.descendants), (0, vec![b, c, d]));
+    let both = graph.traverse(c, LineageDirection::Both, None).unwrap();
+    assert_eq!((both.ancestors, both.descendants), (vec![b, x, a], vec![d]));
+
+    // max_depth cuts the walk off after that many hops
+    let near = graph.traverse(d, LineageDirection::Backward, Some(2)).unwrap();
+    assert_eq!(near.ancestors, vec![c, b, x]);
+    assert!(near.edges.iter().all(|edge| edge.parent != a));
+    let none = graph.traverse(d, LineageDirection::Both, Some(0)).unwrap();
+    assert!(none.ancestors.is_empty() && none.descendants.is_empty() && none.edges.is_empty());
+
+    // Paths run from ancestor to descendant only
+    let path = graph.path(a, d).unwrap().unwrap();
+    assert_eq!(path.iter().map(|edge| (edge.parent, edge.child)).collect::<Vec<_>>(), vec![(a, b), (b, c), (c, d)]);
+    assert!(graph.path(d, a).unwrap().is_none());
+    assert!(graph.path(x, b).unwrap().is_none());
+    assert!(graph.reaches(x, d).unwrap() && !graph.reaches(d, x).unwrap());
+    assert_eq!(graph.find_cycle(&edge(&[d], &[a])).unwrap(), Some((d, a)));
+    assert_eq!(graph.find_cycle(&edge(&[b], &[b])).unwrap(), Some((b, b)));
+    assert_eq!(graph.find_cycle(&edge(&[a], &[d])).unwrap(), None);
+
+    // A filtered graph hides the edges of the events it rejects
+    let hidden = events[1].id;
+    let filtered = FilteredGraph::new(&graph, |event_id| Ok(event_id != hidden));
+    assert_eq!(filtered.parents(c).unwrap(), vec![x]);
+    let backward = filtered.traverse(d, LineageDirection::Backward, None).unwrap();
+    assert_eq!(backward.ancestors, vec![c, x]);
+    assert!(filtered.path(a, d).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn lineage_queries_take_direction_and_depth() {
+    let mut f = Fixture::new().await;
+    let [a, b, c, d] = [f.artifact("a").await, f.artifact("b").await, f.artifact("c").await, f.artifact("d").await];
+    let ab = f.event("alice", "derive", &[a.id], &[b.id]).await;
+    let bc = f.event("alice", "derive", &[b.id], &[c.id]).await;
+    f.event("alice", "derive", &[c.id], &[d.id]).await;
+
+    let query = |direction, max_depth| LineageQuery { direction, max_depth, ..LineageQuery::default() };
+    let lineage = f.service.get_lineage(c.id, query(LineageDirection::Backward, None)).await.unwrap();
+    assert_eq!((lineage.ancestors, lineage.descendants), (vec![b.id, a.id], vec![]));
+    assert_eq!(lineage.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![ab.id, bc.id]);
+    // Immediate neighbours are reported whatever the direction
+    assert_eq!((lineage.parent_ids, lineage.child_ids), (vec![b.id], vec![d.id]));
+    let lineage = f.service.get_lineage(b.id, query(LineageDirection::Forward, Some(1))).await.unwrap();
+    assert_eq!((lineage.ancestors, lineage.descendants), (vec![], vec![c.id]));
+    assert_eq!(lineage.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![bc.id]);
+    let err = f.service.get_lineage(Uuid::new_v4(), LineageQuery::default()).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ArtifactNotFound));
+}
+
+#[tokio::test]
+async fn lineage_as_of() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let b = f.artifact("b").await;
+    let c = f.artifact("c").await;
+    let first = f.event("alice", "derive", &[a.id], &[b.id]).await;
+    let block0 = f.service.create_block().await.unwrap().unwrap();
+    f.service.cosign_artifact(b.id, "bob", None).await.unwrap();
+    let second = f.event("alice", "derive", &[b.id], &[c.id]).await;
+    let block1 = f.service.create_block().await.unwrap().unwrap();
+    let d = f.artifact("d").await;
+    f.event("alice", "derive", &[c.id], &[d.id]).await;
+
+    // G_P as it stood at each block, from sealed events only
+    let at = |as_of| LineageQuery { as_of: Some(as_of), ..LineageQuery::default() };
+    let lineage = f.service.get_lineage(b.id, at(AsOf::Height(0))).await.unwrap();
+    assert_eq!((lineage.ancestors, lineage.descendants), (vec![a.id], vec![]));
+    assert_eq!(lineage.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![first.id]);
+    let snapshot = lineage.as_of.unwrap();
+    assert_eq!((snapshot.height, snapshot.tree_size, snapshot.block_hash), (0, 1, block0.hash.clone()));
+    let lineage = f.service.get_lineage(b.id, at(AsOf::Time(block1.created_at))).await.unwrap();
+    assert_eq!((lineage.descendants, lineage.as_of.unwrap().height), (vec![c.id], 1));
+    let live = f.service.get_lineage(b.id, LineageQuery::default()).await.unwrap();
+    assert_eq!((live.descendants, live.as_of), (vec![c.id, d.id], None));
+    let err = f.service.get_lineage(d.id, at(AsOf::Height(1))).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ArtifactNotFound));
+    let before = AsOf::Time(block0.created_at - chrono::Duration::nanoseconds(1));
+    for as_of in [before, AsOf::Height(2)] {
+        let err = f.service.get_lineage(b.id, at(as_of)).await.unwrap_err();
+        assert!(matches!(err, ProvenanceError::NotSealedAsOf(_)));
+    }
+    assert_eq!("7".parse::<AsOf>().unwrap(), AsOf::Height(7));
+    assert_eq!(block1.created_at.to_rfc3339().parse::<AsOf>().unwrap(), AsOf::Time(block1.created_at));
+    assert!(matches!("yesterday".parse::<AsOf>(), Err(ProvenanceError::InvalidAsOf(_))));
+
+    // Event queries see only what was sealed
+    let query = |as_of| EventQuery { as_of: Some(as_of), ..EventQuery::default() };
+    let page = f.service.query_events(query(AsOf::Height(1))).await.unwrap();
+    assert_eq!(page.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![first.id, second.id]);
+    assert_eq!(page.as_of.unwrap().height, 1);
+
+    // Artifact records as they stood: later co-signatures and registrations are left out
+    let then = f.service.get_artifact_as_of(b.id, AsOf::Height(0)).await.unwrap().unwrap();
+    assert!(then.authorship.is_empty());
+    let then = f.service.get_artifact_as_of(b.id, AsOf::Height(1)).await.unwrap().unwrap();
+    assert_eq!(then.authorship.len(), 1);
+    assert!(f.service.get_artifact_as_of(d.id, AsOf::Height(1)).await.unwrap().is_none());
+
+    // And the answer can be exported as a bundle that proves itself
+    let bundle = f.service.export_bundle(b.id, at(AsOf::Height(0))).await.unwrap();
+    assert_eq!(bundle.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![first.id]);
+    assert!(bundle.artifacts.iter().all(|artifact| artifact.authorship.is_empty()));
+    let verification = bundle::verify_bundle(&bundle).unwrap();
+    assert!(verification.complete, "{:?}", verification.failures);
+}
+
+#[tokio::test]
+async fn lineage_describes_changes() {
+    let mut f = Fixture::new().await;
+    let notes = f.stored("notes", b"line1\nline2\nline3").await;
+    let draft = f.stored("draft", b"line1\nline2 edited\nline3\nline4").await;
+    let config = f.stored("config", br#"{"k":1,"l":[1]}"#).await;
+    let tuned = f.stored("tuned", br#"{"k":2,"l":[1],"m":true}"#).await;
+    let mut derive = event("alice", "summarize", &[notes.id], &[draft.id]);
+    derive.context = serde_json::json!({
+        "tool": "gpt-4",
+        "model": "ignored",
+        "prompt": "Summarize the notes",
+        "parameters": {"temperature": 0.2},
+    });
+    f.service.log_event(derive).await.unwrap();
+    f.event("bob", "extract", &[draft.id], &[config.id]).await;
+    f.event("bob", "transform", &[config.id], &[tuned.id]).await;
+
+    // Each edge says what its event did, from the context and both contents
+    let lineage = f.service.get_lineage(draft.id, LineageQuery::default()).await.unwrap();
+    let edge = lineage.edges.iter().find(|edge| edge.child == draft.id).unwrap();
+    let change = edge.change.as_ref().unwrap();
+    let expected = "summarize by alice using gpt-4 with prompt Summarize the notes with temperature=0.2; \
+                    metadata: /name; content: +2 -1 lines";
+    assert_eq!(change.summary, expected);
+    assert_eq!(lineage.changes, expected);
+    let Some(diff::ContentDiff::Text { lines, .. }) = &change.content else { panic!("{:?}", change.content) };
+    let lines: Vec<_> = lines.iter().map(|line| (line.op, line.line, line.text.as_str())).collect();
+    assert_eq!(
+        lines,
+        vec![
+            (diff::DiffOp::Removed, 2, "line2"),
+            (diff::DiffOp::Added, 2, "line2 edited"),
+            (diff::DiffOp::Added, 4, "line4"),
+        ]
+    );
+    let roots = f.service.get_lineage(notes.id, LineageQuery::default()).await.unwrap();
+    assert!(roots.changes.is_empty());
+
+    // Forward lineage still describes how the artifact itself was made
+    let forward = LineageQuery { direction: LineageDirection::Forward, ..LineageQuery::default() };
+    let lineage = f.service.get_lineage(tuned.id, forward).await.unwrap();
+    assert_eq!(lineage.changes, "transform by bob; metadata: /name; content: /k, /m");
+
+    // Diffs between any two artifacts on a derivation path, in either order
+    for (from, to) in [(notes.id, tuned.id), (tuned.id, notes.id)] {
+        let diff = f.service.diff_artifacts(from, to).await.unwrap();
+        assert_eq!((diff.from, diff.to), (from, to));
+        let hops: Vec<_> = diff.path.iter().map(|edge| (edge.parent, edge.child)).collect();
+        assert_eq!(hops, vec![(notes.id, draft.id), (draft.id, config.id), (config.id, tuned.id)]);
+        assert!(diff.path.iter().all(|edge| edge.change.is_some()));
+        assert!(!diff.metadata.is_empty());
+    }
+    let diff = f.service.diff_artifacts(config.id, tuned.id).await.unwrap();
+    let Some(diff::ContentDiff::Json { changes }) = diff.content else { panic!("{:?}", diff.content) };
+    assert_eq!(changes.iter().map(|entry| entry.path.as_str()).collect::<Vec<_>>(), vec!["/k", "/m"]);
+    let stray = f.artifact("stray").await;
+    let err = f.service.diff_artifacts(notes.id, stray.id).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::NoLineagePath { .. }));
+}
@@ -0,0 +1,94 @@
+// W3C PROV export and import.
+
+mod common;
+
+use common::Fixture;
+use provenance_layer::prov;
+use provenance_layer::*;
+use serde_json::json;
+
+#[tokio::test]
+async fn prov_exports_round_trip() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("draft").await;
+    let b = f.artifact("edit").await;
+    let c = f.artifact("final").await;
+    f.event("alice", "edit", &[a.id], &[b.id]).await;
+    f.event("bob", "merge", &[a.id, b.id], &[c.id]).await;
+    let (events, artifacts) = f.service.lineage_records(c.id, LineageQuery::default()).await.unwrap();
+    let doc = prov::to_prov_json(&events, &artifacts);
+
+    // Everything the export names is already in its own log
+    let summary = f.service.import_prov(&doc).await.unwrap();
+    assert!(summary.events.is_empty());
+    assert_eq!(summary.artifacts[&format!("artifact:{}", c.id)], c.id);
+
+    // Another log rebuilds the same lineage
+    let mut g = Fixture::new().await;
+    for content in ["draft", "edit", "final"] {
+        g.service.blobs().put(content.as_bytes()).await.unwrap();
+    }
+    let summary = g.service.import_prov(&doc).await.unwrap();
+    let id = |artifact: &Artifact| summary.artifacts[&format!("artifact:{}", artifact.id)];
+    let logged: Vec<_> = summary
+        .events
+        .iter()
+        .map(|e| (e.actor.as_str(), e.operation.as_str(), e.in_artifacts.clone(), e.out_artifacts.clone()))
+        .collect();
+    assert_eq!(
+        logged,
+        vec![("alice", "edit", vec![id(&a)], vec![id(&b)]), ("bob", "merge", vec![id(&a), id(&b)], vec![id(&c)])]
+    );
+    let mut ancestors = g.service.get_lineage(id(&c), LineageQuery::default()).await.unwrap().ancestors;
+    ancestors.sort();
+    let mut expected = vec![id(&a), id(&b)];
+    expected.sort();
+    assert_eq!(ancestors, expected);
+    let imported = g.service.get_artifact(id(&c)).await.unwrap().unwrap();
+    assert_eq!((imported.name, imported.content_hash), (c.name, c.content_hash));
+}
+
+#[tokio::test]
+async fn prov_imports_whole_documents() {
+    let mut f = Fixture::new().await;
+
+    // A derivation that names no activity is logged as one
+    let doc = json!({
+        "entity": {"ex:a": {"prov:label": "a"}, "ex:b": {"prov:label": "b"}},
+        "wasDerivedFrom": {"_:d": {"prov:generatedEntity": "ex:b", "prov:usedEntity": "ex:a"}},
+    });
+    let summary = f.service.import_prov(&doc).await.unwrap();
+    assert_eq!(summary.events.len(), 1);
+    let derived = &summary.events[0];
+    assert_eq!((derived.operation.as_str(), derived.actor.as_str()), ("prov:wasDerivedFrom", "prov-import"));
+    assert_eq!(derived.in_artifacts, vec![summary.artifacts["ex:a"]]);
+    assert_eq!(derived.out_artifacts, vec![summary.artifacts["ex:b"]]);
+
+    let unknown = json!({
+        "wasDerivedFrom": {"_:d": {"prov:generatedEntity": "ex:b", "prov:usedEntity": "ex:a", "prov:activity": "ex:gone"}},
+    });
+    let err = f.service.import_prov(&unknown).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::InvalidProv(_)));
+
+    // A refused activity leaves none of the document behind
+    let b = format!("artifact:{}", summary.artifacts["ex:b"]);
+    let cyclic = json!({
+        "entity": {"ex:c": {"prov:label": "c"}},
+        "activity": {
+            "ex:one": {"prov:startTime": "2024-01-01T00:00:00Z"},
+            "ex:two": {"prov:startTime": "2024-01-02T00:00:00Z"},
+        },
+        "used": {
+            "_:u1": {"prov:activity": "ex:one", "prov:entity": "ex:c"},
+            "_:u2": {"prov:activity": "ex:two", "prov:entity": b},
+        },
+        "wasGeneratedBy": {
+            "_:g1": {"prov:activity": "ex:one", "prov:entity": b},
+            "_:g2": {"prov:activity": "ex:two", "prov:entity": "ex:c"},
+        },
+    });
+    let err = f.service.import_prov(&cyclic).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::CycleDetected { .. }));
+    assert_eq!(f.service.list_artifacts(None, None).await.unwrap().artifacts.len(), 2);
+    assert_eq!(f.service.get_events(None).await.unwrap().len(), 1);
+}
@@ -0,0 +1,135 @@
+// Artifact registration, versions and authorship chains.
+
+mod common;
+
+use common::{artifact, event, Fixture};
+use provenance_layer::*;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn events_reference_registered_artifacts() {
+    let mut f = Fixture::new().await;
+    let unknown = Uuid::new_v4();
+    let err = f.service.log_event(event("alice", "use", &[unknown], &[])).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::UnknownArtifact(id) if id == unknown));
+
+    let err = f.service.register_artifact(artifact("x", &"0".repeat(64))).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ContentMismatch(_)));
+
+    let a = f.artifact("a").await;
+    let stored = f.service.get_artifact(a.id).await.unwrap().unwrap();
+    assert_eq!(stored.metadata_digest, a.metadata_digest);
+    assert_eq!(stored.family_id, Some(a.id));
+}
+
+#[tokio::test]
+async fn artifacts_paginate() {
+    let mut f = Fixture::new().await;
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        ids.push(f.artifact(&format!("a{}", i)).await.id);
+    }
+    ids.sort_by_key(|id| id.to_string());
+
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = f.service.list_artifacts(cursor, Some(2)).await.unwrap();
+        seen.extend(page.artifacts.iter().map(|a| a.id));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(seen, ids);
+    assert!(f.service.get_artifact(Uuid::new_v4()).await.unwrap().is_none());
+    let err = f.service.list_artifacts(Some("nope".to_string()), None).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::InvalidCursor));
+}
+
+#[tokio::test]
+async fn versions_are_linear() {
+    let mut f = Fixture::new().await;
+    let v1 = f.artifact("doc").await;
+    let blob = f.service.blobs().put(b"doc v2").await.unwrap();
+    let mut next = artifact("doc", &blob.content_hash);
+    next.version = "2".to_string();
+    let (v2, derive) = f.service.register_artifact_version(v1.id, next.clone(), "alice").await.unwrap();
+    assert_eq!(v2.previous_version, Some(v1.id));
+    assert_eq!(derive.in_artifacts, vec![v1.id]);
+
+    let err = f.service.register_artifact_version(v1.id, next.clone(), "bob").await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::NotLatestVersion { latest } if latest == v2.id));
+
+    // A version whose derive event cannot be logged is not registered either
+    f.service.register_key("bob", None, false).await.unwrap();
+    f.service.revoke_key("bob", None, false).await.unwrap();
+    let err = f.service.register_artifact_version(v2.id, next, "bob").await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::KeyRevoked(_)));
+    assert_eq!(f.service.list_artifacts(None, None).await.unwrap().artifacts.len(), 2);
+
+    let versions = f.service.list_versions(v1.id).await.unwrap();
+    assert_eq!(versions.iter().map(|v| v.id).collect::<Vec<_>>(), vec![v1.id, v2.id]);
+    assert_eq!(f.service.latest_version(v1.id).await.unwrap().id, v2.id);
+    let diff = f.service.diff_metadata(v1.id, v2.id).await.unwrap();
+    assert!(diff.changes.is_empty());
+}
+
+#[tokio::test]
+async fn cosigning_extends_the_chain() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("paper").await;
+    f.service.cosign_artifact(a.id, "alice", Some("author".to_string())).await.unwrap();
+    let signed = f.service.cosign_artifact(a.id, "bob", None).await.unwrap();
+    assert_eq!(signed.authorship.len(), 2);
+    let err = f.service.cosign_artifact(a.id, "bob", None).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::AlreadySigned(_)));
+
+    let required = vec!["alice".to_string(), "carol".to_string()];
+    let report = f.service.verify_authorship(a.id, required).await.unwrap();
+    assert!(report.valid);
+    assert_eq!(report.signers, vec!["alice", "bob"]);
+    assert_eq!(report.missing, vec!["carol"]);
+}
+
+/// Verifies an artifact whose stored authorship chain was rewritten by `tamper`.
+async fn verify_tampered(f: &Fixture, id: Uuid, tamper: impl FnOnce(&mut Artifact)) -> AuthorshipVerification {
+    let mut artifact = f.service.get_artifact(id).await.unwrap().unwrap();
+    let original = serde_json::to_vec(&artifact).unwrap();
+    tamper(&mut artifact);
+    f.storage.insert("artifacts", id.to_string(), serde_json::to_vec(&artifact).unwrap()).unwrap();
+    let report = f.service.verify_authorship(id, vec!["alice".to_string()]).await.unwrap();
+    f.storage.insert("artifacts", id.to_string(), original).unwrap();
+    report
+}
+
+#[tokio::test]
+async fn authorship_chain_detects_tampering() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("paper").await;
+    f.service.cosign_artifact(a.id, "alice", Some("author".to_string())).await.unwrap();
+    f.service.cosign_artifact(a.id, "bob", Some("editor".to_string())).await.unwrap();
+    f.service.cosign_artifact(a.id, "carol", None).await.unwrap();
+    let report = f.service.verify_authorship(a.id, vec!["alice".to_string()]).await.unwrap();
+    assert!(report.complete && report.first_invalid.is_none());
+
+    // Reordering breaks the links, and the report stops at the first bad entry
+    let report = verify_tampered(&f, a.id, |artifact| artifact.authorship.swap(1, 2)).await;
+    assert_eq!((report.valid, report.first_invalid, report.signers.clone()), (false, Some(1), vec!["alice".to_string()]));
+    // Dropping the first entry unlinks the rest from the genesis
+    let report = verify_tampered(&f, a.id, |artifact| {
+        artifact.authorship.remove(0);
+    }).await;
+    assert_eq!((report.first_invalid, report.missing.clone()), (Some(0), vec!["alice".to_string()]));
+    // Signatures cover the content hash and the role
+    let report = verify_tampered(&f, a.id, |artifact| artifact.content_hash = "0".repeat(64)).await;
+    assert_eq!(report.first_invalid, Some(0));
+    let report = verify_tampered(&f, a.id, |artifact| artifact.authorship[1].role = Some("author".to_string())).await;
+    assert_eq!((report.first_invalid, report.reason.as_deref()), (Some(1), Some("signature does not verify")));
+    // An entry claimed for another actor is rejected
+    let report = verify_tampered(&f, a.id, |artifact| artifact.authorship[2].signer = "mallory".to_string()).await;
+    assert_eq!(report.first_invalid, Some(2));
+
+    let err = f.service.cosign_artifact(Uuid::new_v4(), "alice", None).await.unwrap_err();
+    assert!(matches!(err, ProvenanceError::ArtifactNotFound));
+}
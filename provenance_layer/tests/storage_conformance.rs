@@ -0,0 +1,130 @@
+// Conformance suite for the storage backends.
+// Every scenario runs against sled, the in-memory backend and SQLite; a backend
+// conforms if it honours the `Storage` contract and the service's log survives on it
+// identically. Feature behaviour is tested on the in-memory backend in the other files.
+
+mod common;
+
+use common::{event, Fixture};
+use provenance_layer::storage::{Backend, Batch};
+use provenance_layer::*;
+
+async fn storage_primitives(backend: Backend) {
+    let f = Fixture::with_backend(backend).await;
+    let storage = f.storage.as_ref();
+
+    // Enough entries to span several scan chunks
+    let mut batch = Batch::new();
+    for i in 0u32..600 {
+        batch.insert("t", i.to_be_bytes(), i.to_string());
+    }
+    batch.insert("other", b"k", b"v");
+    assert!(storage.commit(batch).unwrap());
+
+    let keys: Vec<u32> = storage
+        .iter("t")
+        .map(|e| u32::from_be_bytes(e.unwrap().0.try_into().unwrap()))
+        .collect();
+    assert_eq!(keys, (0..600).collect::<Vec<_>>());
+    let reversed: Vec<Vec<u8>> = storage
+        .range("t", &10u32.to_be_bytes(), Some(&300u32.to_be_bytes()), true)
+        .map(|e| e.unwrap().0)
+        .collect();
+    assert_eq!(reversed.len(), 290);
+    assert_eq!(reversed[0], 299u32.to_be_bytes());
+    assert_eq!(reversed[289], 10u32.to_be_bytes());
+    assert_eq!(storage.last("t").unwrap().unwrap().1, b"599");
+    assert_eq!(storage.scan_prefix("t", &[0, 0, 1]).count(), 256);
+    assert_eq!(storage.get("t", &7u32.to_be_bytes()).unwrap(), Some(b"7".to_vec()));
+    assert_eq!(storage.get("missing", b"k").unwrap(), None);
+
+    // A failed condition writes nothing, in any tree
+    let mut batch = Batch::new();
+    batch.expect("other", b"k", Some(b"stale"));
+    batch.insert("t", b"new", b"x");
+    batch.remove("other", b"k");
+    assert!(!storage.commit(batch).unwrap());
+    assert!(!storage.contains("t", b"new").unwrap());
+    assert!(storage.contains("other", b"k").unwrap());
+
+    let mut batch = Batch::new();
+    batch.expect("other", b"k", Some(b"v"));
+    batch.expect("other", b"absent", None);
+    batch.remove("other", b"k");
+    assert!(storage.commit(batch).unwrap());
+    assert!(!storage.contains("other", b"k").unwrap());
+
+    let a = storage.generate_id().unwrap();
+    let b = storage.generate_id().unwrap();
+    assert!(b > a);
+
+    storage.drop_tree("t").unwrap();
+    assert_eq!(storage.iter("t").count(), 0);
+}
+
+async fn log_is_sequenced(backend: Backend) {
+    let mut f = Fixture::with_backend(backend).await;
+    let a = f.artifact("a").await;
+    for i in 0..5 {
+        let event = f.event("alice", "touch", &[], &[a.id]).await;
+        assert_eq!(event.seq, Some(i));
+        assert!(event.signature.is_some());
+        assert_eq!(f.service.get_event(event.id).await.unwrap().unwrap().seq, Some(i));
+    }
+
+    let page = f.service.events_since(2, EventFilter::default(), Some(2)).await.unwrap();
+    let seqs: Vec<_> = page.events.iter().map(|e| e.seq.unwrap()).collect();
+    assert_eq!(seqs, vec![2, 3]);
+    assert_eq!(page.next_seq, Some(4));
+    let page = f.service.events_since(4, EventFilter::default(), None).await.unwrap();
+    assert_eq!(page.events.len(), 1);
+    assert_eq!(page.next_seq, Some(5));
+}
+
+async fn state_survives_reopen(backend: Backend) {
+    let mut f = Fixture::with_backend(backend).await;
+    let a = f.artifact("a").await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+    f.service.create_block().await.unwrap();
+    let pending = f.event("bob", "touch", &[], &[a.id]).await;
+
+    let mut reopened = f.reopen().await;
+    assert!(reopened.get_artifact(a.id).await.unwrap().is_some());
+    let next = reopened.log_event(event("carol", "touch", &[], &[a.id])).await.unwrap();
+    assert_eq!(next.seq, Some(2));
+
+    // The unsealed event is recovered and sealed after the restart
+    let block = reopened.create_block().await.unwrap().unwrap();
+    assert_eq!(block.height, 1);
+    assert_eq!(block.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![pending.id, next.id]);
+    assert!(reopened.verify_chain().await.unwrap().valid);
+}
+
+macro_rules! conformance {
+    ($scenarios:tt; $($backend:ident => $kind:expr),* $(,)?) => {
+        $(
+            mod $backend {
+                conformance!(@scenarios $kind, $scenarios);
+            }
+        )*
+    };
+    (@scenarios $kind:expr, [$($scenario:ident),* $(,)?]) => {
+        $(
+            #[tokio::test]
+            async fn $scenario() {
+                super::$scenario($kind).await;
+            }
+        )*
+    };
+}
+
+conformance!(
+    [
+        storage_primitives,
+        log_is_sequenced,
+        state_survives_reopen,
+    ];
+    sled => super::Backend::Sled,
+    memory => super::Backend::Memory,
+    sqlite => super::Backend::Sqlite,
+);
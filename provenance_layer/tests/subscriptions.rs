@@ -0,0 +1,89 @@
+// Live subscriptions to the event log and the chain.
+
+mod common;
+
+use common::{event, Fixture};
+use provenance_layer::subscription::{self, Notification, Subscription, SubscriptionFilter};
+use provenance_layer::*;
+
+/// The next notification of a subscription, as `event:<seq>` or `block:<height>`.
+async fn next_notification(subscription: &mut Subscription) -> String {
+    let next = tokio::time::timeout(std::time::Duration::from_secs(5), subscription.next());
+    match next.await.expect("notification within 5s").unwrap().unwrap() {
+        Notification::Event(event) => format!("event:{}", event.seq.unwrap()),
+        Notification::Block(block) => format!("block:{}", block.height),
+    }
+}
+
+#[tokio::test]
+async fn subscriptions_resume() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+    f.event("bob", "touch", &[], &[a.id]).await;
+    f.service.create_block().await.unwrap();
+
+    // Replays the log and the chain, then follows them live
+    let alice = SubscriptionFilter {
+        filter: EventFilter { actor: Some("alice".to_string()), ..EventFilter::default() },
+        ..SubscriptionFilter::default()
+    };
+    let mut sub = f.service.subscribe(alice.clone(), Some(0), Some(0)).unwrap();
+    assert_eq!(next_notification(&mut sub).await, "event:0");
+    assert_eq!(next_notification(&mut sub).await, "block:0");
+    let mut live = f.service.subscribe(SubscriptionFilter::default(), None, None).unwrap();
+    f.event("bob", "touch", &[], &[a.id]).await;
+    f.event("alice", "touch", &[], &[a.id]).await;
+    f.service.create_block().await.unwrap();
+    assert_eq!(next_notification(&mut sub).await, "event:3");
+    assert_eq!(next_notification(&mut sub).await, "block:1");
+    for expected in ["event:2", "event:3", "block:1"] {
+        assert_eq!(next_notification(&mut live).await, expected);
+    }
+
+    // Resuming from a cursor delivers only what came after it
+    let cursor = sub.cursor();
+    drop(sub);
+    f.event("alice", "touch", &[], &[a.id]).await;
+    let mut resumed = f.service.subscribe(alice, Some(cursor.seq), Some(cursor.height)).unwrap();
+    assert_eq!(next_notification(&mut resumed).await, "event:4");
+
+    // A subscriber that falls behind the broadcast catches up from storage
+    let events_only = SubscriptionFilter { blocks: false, ..SubscriptionFilter::default() };
+    let mut lagging = f.service.subscribe(events_only, None, None).unwrap();
+    for _ in 0..subscription::CHANNEL_CAPACITY + 10 {
+        f.service.log_event(event("carol", "touch", &[], &[a.id])).await.unwrap();
+    }
+    for seq in 5..5 + subscription::CHANNEL_CAPACITY as u64 + 10 {
+        assert_eq!(next_notification(&mut lagging).await, format!("event:{}", seq));
+    }
+}
+
+#[tokio::test]
+async fn subscriptions_replay_migrated_logs() {
+    let mut f = Fixture::new().await;
+    let a = f.artifact("a").await;
+    let mut logged = Vec::new();
+    for actor in ["alice", "bob", "carol"] {
+        logged.push(f.event(actor, "touch", &[], &[a.id]).await.id);
+    }
+    f.unsequence_log();
+    let mut service = f.reopen().await;
+    logged.push(service.log_event(event("dave", "touch", &[], &[a.id])).await.unwrap().id);
+
+    // Migrated events carry no sequence number; their log position stands for it
+    let events_only = SubscriptionFilter { blocks: false, ..SubscriptionFilter::default() };
+    let mut sub = service.subscribe(events_only, Some(0), None).unwrap();
+    let mut replayed = Vec::new();
+    for _ in 0..logged.len() {
+        let next = tokio::time::timeout(std::time::Duration::from_secs(5), sub.next());
+        match next.await.expect("notification within 5s").unwrap().unwrap() {
+            Notification::Event(event) => replayed.push((event.id, event.seq)),
+            Notification::Block(_) => unreachable!(),
+        }
+    }
+    let seqs: Vec<_> = replayed.iter().map(|(_, seq)| *seq).collect();
+    assert_eq!(seqs, vec![None, None, None, Some(3)]);
+    assert_eq!(replayed.into_iter().map(|(id, _)| id).collect::<Vec<_>>(), logged);
+    assert_eq!(sub.cursor().seq, 4);
+}